@@ -1,10 +1,12 @@
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use crate::commands::run::RestartPolicy;
+use crate::output::OutputFormat;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
-    /// 工作目录
+    /// 工作区根目录(不指定时依次使用 FUCKRUN_HOME、向上查找 .fuckrun/ 或 fuckrun.yaml、用户默认目录)
     #[arg(short, long)]
     pub workspace: Option<PathBuf>,
 
@@ -12,6 +14,10 @@ pub struct Cli {
     #[arg(short, long)]
     pub config: Option<PathBuf>,
 
+    /// 输出格式(text/json), json时stdout只输出JSON文档, 日志写入stderr
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -24,12 +30,8 @@ pub enum Commands {
         #[arg(short, long)]
         name: Option<String>,
 
-        /// 是否使用Python解释器
-        #[arg(short = 'y', long)]
-        python: bool,
-
-        /// 端口号
-        #[arg(short, long)]
+        /// 已弃用, 端口在进程配置中设置
+        #[arg(short, long, hide = true)]
         port: Option<u16>,
 
         /// 是否分离运行
@@ -66,12 +68,8 @@ pub enum Commands {
         #[arg(short, long)]
         name: Option<String>,
 
-        /// 是否使用Python解释器
-        #[arg(short, long)]
-        python: bool,
-
-        /// 端口号
-        #[arg(short, long)]
+        /// 已弃用, 健康检查地址在进程配置中设置
+        #[arg(short, long, hide = true)]
         port: Option<u16>,
 
         /// 应用程序目录
@@ -190,12 +188,276 @@ pub enum Commands {
         #[arg(long)]
         max_mem: Option<u64>,
 
-        /// 输出JSON格式
-        #[arg(long)]
+        /// 已弃用, 等同于 --output json
+        #[arg(long, hide = true)]
         json: bool,
 
         /// 实时更新
         #[arg(short, long)]
         watch: bool,
     },
-} 
\ No newline at end of file
+
+    /// 查看进程崩溃记录
+    Crashes {
+        /// 进程名称
+        name: String,
+
+        /// 显示指定ID(或唯一前缀)的崩溃详情
+        #[arg(long)]
+        id: Option<String>,
+
+        /// 显示最近一次崩溃的详情
+        #[arg(long)]
+        last: bool,
+
+        /// 已弃用, 等同于 --output json
+        #[arg(long, hide = true)]
+        json: bool,
+    },
+
+    /// 查看进程生命周期事件
+    Events {
+        /// 进程名称(不指定时显示所有进程)
+        name: Option<String>,
+
+        /// 只显示此时间之后的事件(如 30m、2h、7d、2024-01-01、2024-01-01 12:00:00)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// 已弃用, 等同于 --output json
+        #[arg(long, hide = true)]
+        json: bool,
+    },
+
+    /// 提供Prometheus指标接口
+    Metrics {
+        /// 监听地址(默认使用配置中的 global.metrics.listen)
+        #[arg(long)]
+        listen: Option<String>,
+
+        /// 只输出一次指标后退出
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// 初始化工作区: 创建目录结构和初始配置文件(默认在当前目录)
+    Init {
+        /// 覆盖已存在的配置文件
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// 添加进程配置并创建应用目录
+    Add {
+        /// 进程名称
+        name: String,
+
+        /// 可执行程序
+        #[arg(long)]
+        program: String,
+
+        /// 端口号
+        #[arg(short, long)]
+        port: Option<u16>,
+
+        /// 健康检查URL
+        #[arg(long)]
+        health_check_url: Option<String>,
+
+        /// 崩溃后自动重启
+        #[arg(long)]
+        auto_restart: bool,
+
+        /// 程序参数(写在 -- 之后)
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
+    /// 不写配置直接运行并托管一个命令, 之后可以像配置中的进程一样管理
+    Run {
+        /// 进程名称
+        #[arg(short, long)]
+        name: String,
+
+        /// 重启策略
+        #[arg(long, value_enum, default_value_t = RestartPolicy::No)]
+        restart: RestartPolicy,
+
+        /// 健康检查URL
+        #[arg(long)]
+        health: Option<String>,
+
+        /// 端口号
+        #[arg(short, long)]
+        port: Option<u16>,
+
+        /// 环境变量(KEY=VALUE, 可多次指定)
+        #[arg(short, long)]
+        env: Vec<String>,
+
+        /// 是否作为守护进程运行
+        #[arg(long)]
+        daemon: bool,
+
+        /// 启动成功后把进程保存到配置文件
+        #[arg(long)]
+        save: bool,
+
+        /// 要运行的命令和参数(写在 -- 之后)
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+
+    /// 重启进程
+    Restart {
+        /// 进程名称
+        #[arg(short, long)]
+        name: String,
+
+        /// 是否作为守护进程运行
+        #[arg(long)]
+        daemon: bool,
+
+        /// 是否自动重启
+        #[arg(long)]
+        auto_restart: bool,
+    },
+
+    /// 创建进程的Python虚拟环境并安装依赖
+    Prepare {
+        /// 进程名称
+        name: String,
+
+        /// 依赖文件未变化时也重新安装
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// 发布新版本: 复制目录或解压tar包到新版本并切换, 健康检查失败时自动回滚
+    Deploy {
+        /// 进程名称
+        name: String,
+
+        /// 应用目录或tar包
+        source: PathBuf,
+
+        /// 保留的版本数量
+        #[arg(long, default_value_t = 5)]
+        keep: usize,
+
+        /// 是否作为守护进程运行
+        #[arg(long)]
+        daemon: bool,
+
+        /// 是否自动重启
+        #[arg(long)]
+        auto_restart: bool,
+    },
+
+    /// 回滚到之前的版本
+    Rollback {
+        /// 进程名称
+        name: String,
+
+        /// 目标版本(默认为当前版本的上一个版本)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// 是否作为守护进程运行
+        #[arg(long)]
+        daemon: bool,
+
+        /// 是否自动重启
+        #[arg(long)]
+        auto_restart: bool,
+    },
+
+    /// 运行进程的蓝绿切换代理(由start自动在后台启动)
+    Proxy {
+        /// 进程名称
+        name: String,
+
+        /// 监听地址
+        #[arg(long)]
+        listen: String,
+    },
+
+    /// 让运行中的监控进程在线切换到当前程序, 被监控的进程不重启
+    SelfReexec {
+        /// 进程名称(不指定时升级所有进程的监控进程)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// 等待每个监控进程完成切换的秒数
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+
+    /// 恢复监控进程已退出的进程: 接管仍在运行的进程, 重新启动已退出的进程
+    Recover {
+        /// 进程名称(不指定时检查所有进程)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+
+    /// 接管升级前的监控进程交接的进程(由self-reexec自动执行)
+    #[command(hide = true)]
+    Resume {
+        /// 进程名称
+        name: String,
+    },
+
+    /// 工作区管理
+    Workspace {
+        #[command(subcommand)]
+        action: WorkspaceAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WorkspaceAction {
+    /// 显示当前使用的工作区及选择原因
+    Info,
+}
+
+impl Cli {
+    /// 实际使用的输出格式, 兼容已弃用的 --json 参数
+    pub fn output_format(&self) -> OutputFormat {
+        match self.command {
+            Commands::List { json: true, .. }
+            | Commands::Crashes { json: true, .. }
+            | Commands::Events { json: true, .. } => OutputFormat::Json,
+            _ => self.output,
+        }
+    }
+}
+
+impl Commands {
+    /// 命令名称, 用于JSON输出
+    pub fn name(&self) -> &'static str {
+        match self {
+            Commands::Start { .. } => "start",
+            Commands::Stop { .. } => "stop",
+            Commands::Status { .. } => "status",
+            Commands::Monitor { .. } => "monitor",
+            Commands::Logs { .. } => "logs",
+            Commands::SystemLogs { .. } => "system-logs",
+            Commands::List { .. } => "list",
+            Commands::Crashes { .. } => "crashes",
+            Commands::Events { .. } => "events",
+            Commands::Metrics { .. } => "metrics",
+            Commands::Init { .. } => "init",
+            Commands::Add { .. } => "add",
+            Commands::Run { .. } => "run",
+            Commands::Restart { .. } => "restart",
+            Commands::Prepare { .. } => "prepare",
+            Commands::Deploy { .. } => "deploy",
+            Commands::Rollback { .. } => "rollback",
+            Commands::Proxy { .. } => "proxy",
+            Commands::SelfReexec { .. } => "self-reexec",
+            Commands::Recover { .. } => "recover",
+            Commands::Resume { .. } => "resume",
+            Commands::Workspace { .. } => "workspace",
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use log::{info, warn};
+use serde_json::json;
+use serde_yaml::{Mapping, Value};
+use crate::config::{Config, ProcessConfig};
+use crate::error::Failure;
+use crate::output::{self, OutputFormat};
+use crate::workspace::Workspace;
+
+/// 要添加的进程
+#[derive(Debug, Clone, Default)]
+pub struct NewProcess {
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub port: Option<u16>,
+    pub health_check_url: Option<String>,
+    pub auto_restart: bool,
+    pub working_dir: Option<PathBuf>,
+    pub env: HashMap<String, String>,
+}
+
+impl NewProcess {
+    /// 生成配置条目, 只包含显式指定的字段
+    fn to_entry(&self) -> Mapping {
+        let mut entry = Mapping::new();
+        entry.insert("name".into(), self.name.clone().into());
+        entry.insert("program".into(), self.program.clone().into());
+        if !self.args.is_empty() {
+            entry.insert("args".into(), self.args.iter().cloned().map(Value::from).collect());
+        }
+        if let Some(dir) = &self.working_dir {
+            entry.insert("working_dir".into(), dir.to_string_lossy().into_owned().into());
+        }
+        if !self.env.is_empty() {
+            let mut env: Vec<_> = self.env.iter().collect();
+            env.sort();
+            entry.insert("env".into(), env.into_iter()
+                .map(|(key, value)| (Value::from(key.clone()), Value::from(value.clone())))
+                .collect::<Mapping>()
+                .into());
+        }
+        if self.auto_restart {
+            entry.insert("auto_restart".into(), true.into());
+        }
+        if let Some(url) = &self.health_check_url {
+            entry.insert("health_check_url".into(), url.clone().into());
+        }
+        if let Some(port) = self.port {
+            let mut process = Mapping::new();
+            process.insert("default_port".into(), port.into());
+            entry.insert("process".into(), process.into());
+        }
+        entry
+    }
+
+    /// 校验进程配置, 返回解析后的配置
+    pub(crate) fn validate(&self, config: &Config) -> Result<ProcessConfig> {
+        let invalid = |message: String| -> anyhow::Error { Failure::ConfigInvalid(message).into() };
+
+        let valid_name = !self.name.is_empty()
+            && self.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(invalid(format!("进程名称无效: {:?}, 只能包含字母、数字、'-'和'_'", self.name)));
+        }
+        if config.processes.contains_key(&self.name) {
+            return Err(invalid(format!("进程 {} 已存在", self.name)));
+        }
+        if self.program.trim().is_empty() {
+            return Err(invalid("程序不能为空".into()));
+        }
+        if self.port == Some(0) {
+            return Err(invalid("端口不能为0".into()));
+        }
+        if let Some(port) = self.port {
+            if let Some(other) = config.processes.values().find(|p| p.process.default_port == port) {
+                return Err(invalid(format!("端口 {} 已被进程 {} 使用", port, other.name)));
+            }
+        }
+        if let Some(url) = &self.health_check_url {
+            reqwest::Url::parse(url).map_err(|e| invalid(format!("健康检查URL无效: {}", e)))?;
+        }
+
+        let process: ProcessConfig = serde_yaml::from_value(Value::Mapping(self.to_entry()))
+            .context(Failure::ConfigInvalid("生成进程配置失败".into()))?;
+        process.process.validate()
+            .map_err(|e| invalid(format!("进程配置无效: {}", e)))?;
+
+        if which::which(&self.program).is_err() {
+            warn!("未找到程序 {}, 请确认启动前已安装", self.program);
+        }
+        Ok(process)
+    }
+}
+
+pub fn handle_add(
+    workspace: &Workspace,
+    config_path: &Path,
+    process: NewProcess,
+    output: OutputFormat,
+) -> Result<()> {
+    if !config_path.exists() {
+        return Err(Failure::ConfigInvalid(format!(
+            "配置文件不存在: {}, 请先运行 fuckrun init",
+            config_path.display()
+        )).into());
+    }
+    let config = Config::from_file(config_path)?;
+    process.validate(&config)?;
+    append_process(config_path, config, &process)?;
+    info!("已添加进程配置: {}", process.name);
+
+    // 与start使用的工作目录一致, 代码放在这里即可启动
+    let working_dir = workspace.get_process_working_dir(&process.name);
+    fs::create_dir_all(&working_dir).context("创建应用目录失败")?;
+    info!("已创建应用目录: {:?}", working_dir);
+
+    if output.is_json() {
+        output::print_result("add", json!({
+            "process": process.name,
+            "config": config_path,
+            "app_dir": working_dir,
+        }));
+    } else {
+        println!("已添加进程 {} 到 {}", process.name, config_path.display());
+        println!("应用目录: {}", working_dir.display());
+    }
+    Ok(())
+}
+
+/// 把进程条目写入配置文件, YAML文件尽量保留原有注释, 配置文件不存在时新建
+pub(crate) fn append_process(config_path: &Path, config: Config, process: &NewProcess) -> Result<()> {
+    let is_json = config_path.extension().is_some_and(|ext| ext == "json");
+    let content = if config_path.exists() {
+        fs::read_to_string(config_path).context("读取配置文件失败")?
+    } else {
+        String::new()
+    };
+    let updated = if is_json {
+        None
+    } else {
+        insert_process_entry(&content, &process.name, &process.to_entry())
+            .filter(|updated| {
+                serde_yaml::from_str::<Config>(updated)
+                    .is_ok_and(|parsed| parsed.processes.contains_key(&process.name))
+            })
+    };
+
+    match updated {
+        Some(updated) => fs::write(config_path, updated).context("保存配置文件失败")?,
+        None => {
+            if !is_json {
+                warn!("无法在原文件中插入配置, 将重新生成配置文件, 注释会丢失");
+            }
+            let mut config = config;
+            let entry = serde_yaml::from_value(Value::Mapping(process.to_entry()))
+                .context("生成进程配置失败")?;
+            config.processes.insert(process.name.clone(), entry);
+            config.save_to_file(config_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// 在YAML文本的processes块末尾插入进程条目, 保留其他内容和注释
+///
+/// processes使用流式写法(如 `processes: {a: ...}`)时无法插入, 返回None
+fn insert_process_entry(content: &str, name: &str, entry: &Mapping) -> Option<String> {
+    let mut wrapped = Mapping::new();
+    wrapped.insert(name.into(), entry.clone().into());
+    let entry_yaml = serde_yaml::to_string(&wrapped).ok()?;
+
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let is_top_level = |line: &str| !line.is_empty() && !line.starts_with([' ', '\t', '#']);
+    let header = lines.iter().position(|line| {
+        line.split('#').next().unwrap_or_default().trim_end().starts_with("processes:")
+            && is_top_level(line)
+    });
+
+    let Some(header) = header else {
+        // 没有processes块, 追加到文件末尾
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push("processes:".to_string());
+        lines.extend(indent(&entry_yaml, "  "));
+        return Some(lines.join("\n") + "\n");
+    };
+
+    let value = lines[header]["processes:".len()..].split('#').next().unwrap_or_default().trim();
+    match value {
+        "" => {}
+        "{}" | "~" | "null" => lines[header] = "processes:".to_string(),
+        _ => return None,
+    }
+
+    // 块结束于下一个顶层键, 紧挨着它的注释属于下一个键
+    let mut end = lines[header + 1..].iter()
+        .position(|line| is_top_level(line))
+        .map_or(lines.len(), |i| header + 1 + i);
+    while end > header + 1 && {
+        let line = lines[end - 1].trim_start();
+        line.is_empty() || (line.starts_with('#') && !lines[end - 1].starts_with([' ', '\t']))
+    } {
+        end -= 1;
+    }
+
+    let indent_unit = lines[header + 1..end].iter()
+        .find(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|line| line[..line.len() - line.trim_start().len()].to_string())
+        .unwrap_or_else(|| "  ".to_string());
+    lines.splice(end..end, indent(&entry_yaml, &indent_unit));
+    Some(lines.join("\n") + "\n")
+}
+
+fn indent(yaml: &str, prefix: &str) -> Vec<String> {
+    yaml.lines().map(|line| format!("{}{}", prefix, line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::init::STARTER_CONFIG;
+
+    fn web() -> NewProcess {
+        NewProcess {
+            name: "web".into(),
+            program: "python".into(),
+            args: vec!["app.py".into()],
+            port: Some(8000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_insert_preserves_comments() -> Result<()> {
+        let updated = insert_process_entry(STARTER_CONFIG, "web", &web().to_entry()).unwrap();
+        assert!(updated.starts_with("# FuckRun 配置文件"));
+        assert!(updated.contains("#     health_check_url: http://127.0.0.1:8000/health"));
+
+        let api = NewProcess { name: "api".into(), port: Some(9000), ..web() };
+        let content = format!("{}\n# 通知配置\nmetrics:\n  listen: 127.0.0.1:9464\n", updated);
+        let updated = insert_process_entry(&content, "api", &api.to_entry()).unwrap();
+        assert!(updated.contains("# 通知配置\nmetrics:"));
+
+        let config: Config = serde_yaml::from_str(&updated)?;
+        assert_eq!(config.processes["web"].args, vec!["app.py".to_string()]);
+        assert_eq!(config.processes["api"].process.default_port, 9000);
+        assert_eq!(config.global.metrics.listen, "127.0.0.1:9464");
+
+        // 流式写法无法插入
+        assert!(insert_process_entry("processes: {a: {name: a, program: x}}\n", "web", &web().to_entry()).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_validates_and_creates_app_dir() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        let config_path = dir.path().join("fuckrun.yaml");
+        fs::write(&config_path, STARTER_CONFIG)?;
+
+        handle_add(&workspace, &config_path, web(), OutputFormat::Json)?;
+        assert!(workspace.get_process_working_dir("web").is_dir());
+        assert_eq!(workspace.get_process_working_dir("web"), workspace.get_process_deploy_dir("web"));
+        assert!(fs::read_to_string(&config_path)?.starts_with("# FuckRun 配置文件"));
+
+        let duplicate = handle_add(&workspace, &config_path, web(), OutputFormat::Json).unwrap_err();
+        assert_eq!(crate::error::exit_code(&duplicate), 6);
+        let port_taken = NewProcess { name: "api".into(), ..web() };
+        assert!(handle_add(&workspace, &config_path, port_taken, OutputFormat::Json).is_err());
+        let bad_name = NewProcess { name: "a b".into(), port: None, ..web() };
+        assert!(handle_add(&workspace, &config_path, bad_name, OutputFormat::Json).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use log::info;
+use prettytable::{Table, row};
+use serde_json::json;
+use crate::crash::CrashRecord;
+use crate::output::{self, OutputFormat};
+use crate::workspace::Workspace;
+
+pub async fn handle_crashes(
+    workspace: &Workspace,
+    name: String,
+    id: Option<String>,
+    last: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    info!("查看进程 {} 的崩溃记录", name);
+
+    // 显示单条记录详情
+    let record = if let Some(id) = id {
+        Some(CrashRecord::load(workspace, &name, &id)?)
+    } else if last {
+        let record = CrashRecord::list(workspace, &name)?.pop();
+        if record.is_none() {
+            anyhow::bail!("进程 {} 没有崩溃记录", name);
+        }
+        record
+    } else {
+        None
+    };
+
+    if let Some(record) = record {
+        if output.is_json() {
+            output::print_result("crashes", json!({ "record": record }));
+        } else {
+            print_record(&record);
+        }
+        return Ok(());
+    }
+
+    // 列出所有记录
+    let records = CrashRecord::list(workspace, &name)?;
+    if output.is_json() {
+        output::print_result("crashes", json!({ "records": records }));
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.add_row(row!["ID", "TIME", "PID", "REASON", "UPTIME", "RESTARTS", "LAST LINE"]);
+    for record in records.iter().rev() {
+        let last_line = record.output.last()
+            .map(|l| truncate(&l.line, 60))
+            .unwrap_or_default();
+        table.add_row(row![
+            record.id,
+            record.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            record.pid.map(|p| p.to_string()).unwrap_or_else(|| "-".into()),
+            record.reason(),
+            format!("{}s", record.uptime_secs),
+            record.restart_count,
+            last_line
+        ]);
+    }
+    table.printstd();
+
+    Ok(())
+}
+
+fn print_record(record: &CrashRecord) {
+    println!("ID:        {}", record.id);
+    println!("进程:      {}", record.process_name);
+    println!("时间:      {}", record.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"));
+    println!("PID:       {}", record.pid.map(|p| p.to_string()).unwrap_or_else(|| "-".into()));
+    println!("退出原因:  {}", record.reason());
+    println!("运行时长:  {}s", record.uptime_secs);
+    println!("重启次数:  {}", record.restart_count);
+    println!("环境哈希:  {}", record.env_hash);
+    println!("最后 {} 行输出:", record.output.len());
+    for line in &record.output {
+        println!("[{}] {}", line.stream, line.line);
+    }
+}
+
+fn truncate(line: &str, max_chars: usize) -> String {
+    if line.chars().count() <= max_chars {
+        line.to_string()
+    } else {
+        let mut out: String = line.chars().take(max_chars).collect();
+        out.push_str("...");
+        out
+    }
+}
@@ -0,0 +1,223 @@
+use std::path::Path;
+use anyhow::{Result, Context};
+use log::{info, warn, error};
+use serde_json::json;
+use crate::commands::handle_restart;
+use crate::config::{Config, ProcessConfig};
+use crate::deploy::Releases;
+use crate::error::Failure;
+use crate::events::{self, EventKind};
+use crate::fs::FsManager;
+use crate::output::{self, OutputFormat};
+use crate::process::ProcessManager;
+use crate::workspace::Workspace;
+
+/// 重启进程的方式, 与 `restart` 命令的参数一致
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RestartMode {
+    pub daemon: bool,
+    pub auto_restart: bool,
+}
+
+/// 发布新版本: 复制或解压到新版本目录, 切换current链接后重启进程
+///
+/// 新版本启动失败或健康检查不通过时自动切回之前的版本并重启
+pub async fn handle_deploy(
+    workspace: &Workspace,
+    config: &Config,
+    name: String,
+    source: &Path,
+    keep: usize,
+    mode: RestartMode,
+    output: OutputFormat,
+) -> Result<()> {
+    let process = find_process(config, &name)?;
+    let releases = Releases::new(workspace, &name);
+    let previous = releases.current();
+
+    let release = releases.create(source)?;
+    info!("已创建版本: {}", release);
+    switch_release(workspace, config, &process, &releases, &release, previous.as_deref(), mode).await?;
+    events::record(workspace, &name, EventKind::Deployed { release: release.clone() });
+
+    let removed = releases.prune(keep.max(1), &FsManager::new(config.global.fs.clone())).await?;
+
+    if output.is_json() {
+        output::print_result("deploy", json!({
+            "process": name,
+            "release": release,
+            "previous": previous,
+            "removed": removed,
+        }));
+    } else {
+        println!("进程 {} 已发布版本 {}", name, release);
+        if !removed.is_empty() {
+            println!("已删除旧版本: {}", removed.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// 回滚到指定版本, 不指定时回滚到当前版本的上一个版本
+pub async fn handle_rollback(
+    workspace: &Workspace,
+    config: &Config,
+    name: String,
+    to: Option<String>,
+    mode: RestartMode,
+    output: OutputFormat,
+) -> Result<()> {
+    let process = find_process(config, &name)?;
+    let releases = Releases::new(workspace, &name);
+    let current = releases.current();
+    let available = releases.list()?;
+
+    let target = match to {
+        Some(release) if available.contains(&release) => release,
+        Some(release) => return Err(Failure::NotFound(format!("版本不存在: {}", release)).into()),
+        None => available.iter()
+            .rev()
+            .find(|release| current.as_ref().is_some_and(|current| *release < current))
+            .cloned()
+            .ok_or_else(|| Failure::NotFound(format!("进程 {} 没有可回滚的更早版本", name)))?,
+    };
+
+    switch_release(workspace, config, &process, &releases, &target, current.as_deref(), mode).await?;
+    events::record(workspace, &name, EventKind::RolledBack { from: current.clone(), to: target.clone() });
+
+    if output.is_json() {
+        output::print_result("rollback", json!({
+            "process": name,
+            "release": target,
+            "previous": current,
+        }));
+    } else {
+        println!("进程 {} 已回滚到版本 {}", name, target);
+    }
+    Ok(())
+}
+
+fn find_process(config: &Config, name: &str) -> Result<ProcessConfig> {
+    config.get_process_config(name)
+        .ok_or_else(|| Failure::NotFound(format!("未找到进程配置: {}", name)).into())
+}
+
+/// 切换到指定版本并重启, 失败时切回previous并重启
+async fn switch_release(
+    workspace: &Workspace,
+    config: &Config,
+    process: &ProcessConfig,
+    releases: &Releases,
+    release: &str,
+    previous: Option<&str>,
+    mode: RestartMode,
+) -> Result<()> {
+    releases.activate(release)?;
+    let Err(e) = restart_healthy(workspace, config, process, mode).await else {
+        return Ok(());
+    };
+    error!("版本 {} 启动失败: {}", release, e);
+
+    let Some(previous) = previous.filter(|previous| *previous != release) else {
+        // 首次发布失败时恢复发布前的工作目录
+        if previous.is_none() {
+            releases.deactivate()?;
+        }
+        return Err(e.context(format!("版本 {} 启动失败, 没有可回滚的版本", release)));
+    };
+    warn!("回滚到版本 {}", previous);
+    releases.activate(previous)?;
+    restart_healthy(workspace, config, process, mode).await
+        .with_context(|| format!("版本 {} 启动失败, 回滚到 {} 后仍然启动失败", release, previous))?;
+    events::record(workspace, &process.name, EventKind::RolledBack {
+        from: Some(release.to_string()),
+        to: previous.to_string(),
+    });
+    Err(e.context(format!("版本 {} 启动失败, 已回滚到 {}", release, previous)))
+}
+
+/// 重启进程并等待健康检查通过
+///
+/// 守护进程模式下启动命令不等待健康检查, 这里按进程的健康检查配置再确认一次。
+/// 使用代理的进程在切换前已经检查过新实例
+async fn restart_healthy(
+    workspace: &Workspace,
+    config: &Config,
+    process: &ProcessConfig,
+    mode: RestartMode,
+) -> Result<()> {
+    handle_restart(workspace, config, process.name.clone(), mode.daemon, mode.auto_restart).await?;
+
+    match process.health_check_url.as_deref() {
+        Some(url) if process.proxy.is_none() => {
+            ProcessManager::with_config(workspace, process.name.clone(), process.process.clone())
+                .wait_healthy(url)
+                .await
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::test_support::{Fixture, TestWorkspace};
+
+    #[tokio::test]
+    async fn test_deploy_rolls_back_broken_release() -> Result<()> {
+        let ws = TestWorkspace::new();
+        // 只有带ok文件的版本能正常运行
+        let mut process = Fixture::Sleep.process_config("web");
+        process.args = vec!["-c".into(), "test -f ok || exit 1; exec sleep 60".into()];
+        let config = ws.write_config(vec![process])?;
+
+        let good = ws.root().join("good");
+        fs::create_dir_all(&good)?;
+        fs::write(good.join("ok"), "")?;
+        handle_deploy(&ws.workspace, &config, "web".into(), &good, 5, RestartMode::default(), OutputFormat::Json).await?;
+        let releases = Releases::new(&ws.workspace, "web");
+        let first = releases.current().unwrap();
+        let first_pid = ws.state("web").pid.unwrap();
+
+        let broken = ws.root().join("broken");
+        fs::create_dir_all(&broken)?;
+        let err = handle_deploy(&ws.workspace, &config, "web".into(), &broken, 5, RestartMode::default(), OutputFormat::Json)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("已回滚到"));
+        assert_eq!(releases.current(), Some(first.clone()));
+        let pid = ws.state("web").pid.unwrap();
+        assert_ne!(pid, first_pid);
+        assert!(crate::process::backend::native().exists(pid));
+        assert!(ws.events("web").iter().any(|kind| matches!(kind, EventKind::RolledBack { .. })));
+
+        // 没有更早的版本时无法回滚
+        let err = handle_rollback(&ws.workspace, &config, "web".into(), None, RestartMode::default(), OutputFormat::Json)
+            .await
+            .unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_first_deploy_restores_working_dir() -> Result<()> {
+        let ws = TestWorkspace::new();
+        let mut process = Fixture::Sleep.process_config("web");
+        process.args = vec!["-c".into(), "test -f ok || exit 1; exec sleep 60".into()];
+        let config = ws.write_config(vec![process])?;
+
+        let broken = ws.root().join("broken");
+        fs::create_dir_all(&broken)?;
+        let err = handle_deploy(&ws.workspace, &config, "web".into(), &broken, 5, RestartMode::default(), OutputFormat::Json)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("没有可回滚的版本"));
+
+        let releases = Releases::new(&ws.workspace, "web");
+        assert_eq!(releases.current(), None);
+        assert!(fs::symlink_metadata(releases.current_link()).is_err());
+        assert_eq!(ws.workspace.get_process_working_dir("web"), ws.workspace.get_process_deploy_dir("web"));
+        Ok(())
+    }
+}
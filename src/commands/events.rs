@@ -0,0 +1,61 @@
+use anyhow::Result;
+use log::info;
+use prettytable::{Table, row};
+use serde_json::json;
+use crate::events::{self, ProcessEvent};
+use crate::output::{self, OutputFormat};
+use crate::workspace::Workspace;
+
+pub async fn handle_events(
+    workspace: &Workspace,
+    name: Option<String>,
+    since: Option<String>,
+    output: OutputFormat,
+) -> Result<()> {
+    let since = since.as_deref().map(events::parse_since).transpose()?;
+
+    // 未指定进程名时列出所有进程的事件
+    let names = match name {
+        Some(name) => vec![name],
+        None => {
+            let mut names = Vec::new();
+            let processes_dir = workspace.get_processes_dir();
+            if processes_dir.exists() {
+                for entry in std::fs::read_dir(processes_dir)? {
+                    let path = entry?.path();
+                    if path.is_dir() {
+                        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                            names.push(name.to_string());
+                        }
+                    }
+                }
+            }
+            names
+        }
+    };
+    info!("查看进程事件: {:?}", names);
+
+    let mut all_events = Vec::new();
+    for name in &names {
+        all_events.extend(ProcessEvent::list(workspace, name, since)?);
+    }
+    all_events.sort_by_key(|event| event.timestamp);
+
+    if output.is_json() {
+        output::print_result("events", json!({ "events": all_events }));
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.add_row(row!["TIME", "NAME", "EVENT", "DETAIL"]);
+    for event in &all_events {
+        table.add_row(row![
+            event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            event.process_name,
+            event.kind.name(),
+            event.kind.detail()
+        ]);
+    }
+    table.printstd();
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context};
+use log::info;
+use serde_json::json;
+use crate::output::{self, OutputFormat};
+use crate::workspace::{Workspace, MARKER_FILE};
+
+/// 新工作区的初始配置
+pub const STARTER_CONFIG: &str = r#"# FuckRun 配置文件
+#
+# 这个文件同时标记了工作区根目录, 在任意子目录中运行 fuckrun 都会使用这里的配置。
+# 完整的配置说明见 docs/config.md
+
+global:
+  # 所有进程共享的环境变量
+  env: {}
+  log:
+    # 日志级别: debug/info/warn/error
+    level: info
+
+# 进程配置, 可以用 `fuckrun add <名称> --program <程序> --port <端口> -- <参数>` 添加, 例如:
+#
+# processes:
+#   web:
+#     name: web
+#     program: python
+#     args: ["app.py"]
+#     # 崩溃后自动重启
+#     auto_restart: true
+#     health_check_url: http://127.0.0.1:8000/health
+#     process:
+#       default_port: 8000
+processes: {}
+"#;
+
+pub fn handle_init(root: &Path, force: bool, output: OutputFormat) -> Result<()> {
+    let workspace = Workspace::new(root);
+    workspace.init().context("初始化工作区失败")?;
+
+    let config_path = workspace.get_root_dir().join(MARKER_FILE);
+    let existing = [config_path.clone(), workspace.get_root_dir().join("config.yaml")]
+        .into_iter()
+        .find(|path| path.exists());
+    let created = match existing {
+        Some(path) if !force => {
+            info!("配置文件已存在, 不覆盖: {:?}", path);
+            false
+        }
+        _ => {
+            fs::write(&config_path, STARTER_CONFIG).context("写入配置文件失败")?;
+            info!("已创建配置文件: {:?}", config_path);
+            true
+        }
+    };
+
+    if output.is_json() {
+        output::print_result("init", json!({
+            "root": workspace.get_root_dir(),
+            "config": config_path,
+            "config_created": created,
+        }));
+    } else {
+        println!("已初始化工作区: {}", workspace.get_root_dir().display());
+        if created {
+            println!("已创建配置文件: {}", config_path.display());
+        } else {
+            println!("配置文件已存在, 未覆盖(使用 --force 覆盖)");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_init_creates_layout_and_starter_config() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        handle_init(dir.path(), false, OutputFormat::Json)?;
+
+        assert!(dir.path().join(".fuckrun").join("processes").is_dir());
+        assert!(dir.path().join("deployments").is_dir());
+        let config = Config::from_file(dir.path().join(MARKER_FILE))?;
+        assert!(config.processes.is_empty());
+
+        // 已有配置时不覆盖
+        fs::write(dir.path().join(MARKER_FILE), "processes: {}\n")?;
+        handle_init(dir.path(), false, OutputFormat::Json)?;
+        assert_eq!(fs::read_to_string(dir.path().join(MARKER_FILE))?, "processes: {}\n");
+        Ok(())
+    }
+}
@@ -1,32 +1,41 @@
 use anyhow::{Result, Context};
-use log::info;
+use log::{info, warn};
 use prettytable::{Table, row};
 use std::time::UNIX_EPOCH;
-use tokio::process::Command;
 use std::path::PathBuf;
 use serde::Serialize;
 use crate::workspace::Workspace;
 use crate::state::ProcessState;
+use crate::events::{EventStats, ProcessEvent};
+use crate::output::{self, OutputFormat};
+use crate::process::backend::{self, ProcessBackend};
+use crate::process::cgroup::{Cgroup, CgroupStats};
 
 // 定义进程信息结构体
 #[derive(Debug, Serialize)]
-struct ProcessInfo {
-    name: String,
-    pid: String,
-    monitor_pid: String,
-    status: String,
-    restarts: u32,
-    uptime: String,
-    uptime_seconds: u64,  // 添加原始秒数用于筛选
-    cpu: String,
-    cpu_float: f64,      // 添加浮点数用于筛选
-    mem: String,
-    mem_bytes: u64,      // 添加字节数用于筛选
-    last_start: String,
+pub(crate) struct ProcessInfo {
+    pub(crate) name: String,
+    pub(crate) pid: String,
+    pub(crate) monitor_pid: String,
+    pub(crate) status: String,
+    pub(crate) restarts: u32,
+    pub(crate) uptime: String,
+    pub(crate) uptime_seconds: u64,  // 添加原始秒数用于筛选
+    pub(crate) cpu: String,
+    pub(crate) cpu_float: f64,      // 添加浮点数用于筛选
+    pub(crate) mem: String,
+    pub(crate) mem_bytes: u64,      // 添加字节数用于筛选
+    pub(crate) io: String,
+    /// 进程树累计读写的字节数, 只有进程在cgroup中时才有
+    pub(crate) io_read_bytes: Option<u64>,
+    pub(crate) io_write_bytes: Option<u64>,
+    pub(crate) last_start: String,
+    pub(crate) mtbf: String,
+    pub(crate) mtbf_seconds: Option<u64>,
 }
 
 // 定义筛选选项
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ListFilter {
     pub name: Option<String>,
     pub status: Option<String>,
@@ -42,19 +51,21 @@ pub async fn handle_list(
     workspace: &Workspace, 
     app_dir: Option<PathBuf>,
     filter: Option<ListFilter>,
-    json: bool,
     watch: bool,
+    output: OutputFormat,
 ) -> Result<()> {
     info!("列出所有运行中的进程");
     
     if watch {
         loop {
-            // 清屏
-            print!("\x1B[2J\x1B[1;1H");
+            // 清屏, JSON输出时每次刷新输出一行
+            if !output.is_json() {
+                print!("\x1B[2J\x1B[1;1H");
+            }
             // 获取并显示进程列表
             let process_list = get_process_list(workspace, &app_dir, &filter).await?;
             // 输出结果
-            output_process_list(&process_list, json)?;
+            output_process_list(&process_list, output)?;
             // 等待1秒
             tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         }
@@ -62,17 +73,18 @@ pub async fn handle_list(
         // 获取进程列表
         let process_list = get_process_list(workspace, &app_dir, &filter).await?;
         // 输出结果
-        output_process_list(&process_list, json)?;
+        output_process_list(&process_list, output)?;
     }
     
     Ok(())
 }
 
-async fn get_process_list(
+pub(crate) async fn get_process_list(
     workspace: &Workspace,
     app_dir: &Option<PathBuf>,
     filter: &Option<ListFilter>,
 ) -> Result<Vec<ProcessInfo>> {
+    let backend = backend::native();
     // 获取进程状态目录
     let processes_dir = workspace.get_processes_dir();
     info!("进程状态目录: {:?}", processes_dir);
@@ -119,17 +131,45 @@ async fn get_process_list(
 
             // 读取进程状态
             if let Ok(state) = ProcessState::load(workspace, process_name) {
-                let (status, uptime, uptime_seconds, cpu, cpu_float, mem, mem_bytes) = 
+                // 进程在自己的cgroup中时统计整个进程树的资源
+                let tree = state.pid
+                    .and_then(|pid| Cgroup::open(workspace, process_name).filter(|cgroup| cgroup.contains(pid)))
+                    .map(|cgroup| cgroup.stats());
+                let io_bytes = tree.as_ref().and_then(|tree| tree.io_bytes);
+                let (status, mut uptime, mut uptime_seconds, cpu, cpu_float, mem, mem_bytes) = 
                     if let Some(pid) = state.pid {
-                        info!("进程 {} 正在运行，PID: {}", process_name, pid);
-                        get_process_info(pid).await?
+                        if state.identity.as_ref().is_some_and(|identity| !identity.verify(pid)) {
+                            warn!("进程 {} 的PID {} 已被其他进程复用", process_name, pid);
+                            ("stale".into(), "0".into(), 0, "0%".into(), 0.0, "0MB".into(), 0)
+                        } else {
+                            info!("进程 {} 正在运行，PID: {}", process_name, pid);
+                            get_process_info(backend.as_ref(), pid, tree.as_ref())
+                        }
+                    } else if state.stale {
+                        ("stale".into(), "0".into(), 0, "0%".into(), 0.0, "0MB".into(), 0)
                     } else {
                         info!("进程 {} 未运行", process_name);
                         ("stopped".into(), "0".into(), 0, "0%".into(), 0.0, "0MB".into(), 0)
                     };
 
-                // 获取最后启动时间
-                let last_start = if let Ok(metadata) = tokio::fs::metadata(&path.join("state.json")).await {
+                // 根据事件历史计算运行时长、最后启动时间和平均故障间隔
+                let events = ProcessEvent::list(workspace, process_name, None).unwrap_or_else(|e| {
+                    warn!("读取进程 {} 的事件失败: {}", process_name, e);
+                    Vec::new()
+                });
+                let stats = EventStats::from_events(&events, chrono::Local::now());
+                if status == "online" {
+                    if let Some(seconds) = stats.uptime_secs {
+                        uptime_seconds = seconds;
+                        uptime = format_uptime(seconds);
+                    }
+                }
+                let mtbf = stats.mtbf_secs.map(format_uptime).unwrap_or_else(|| "-".into());
+
+                // 获取最后启动时间, 没有事件记录时退回到状态文件的修改时间
+                let last_start = if let Some(last_start) = stats.last_start {
+                    last_start.format("%Y-%m-%d %H:%M:%S").to_string()
+                } else if let Ok(metadata) = tokio::fs::metadata(&path.join("state.json")).await {
                     if let Ok(modified) = metadata.modified() {
                         if let Ok(duration) = modified.duration_since(UNIX_EPOCH) {
                             chrono::DateTime::<chrono::Local>::from(
@@ -158,7 +198,14 @@ async fn get_process_list(
                     cpu_float,
                     mem,
                     mem_bytes,
+                    io: io_bytes
+                        .map(|(read, write)| format!("{}MB/{}MB", read / 1024 / 1024, write / 1024 / 1024))
+                        .unwrap_or_else(|| "-".into()),
+                    io_read_bytes: io_bytes.map(|(read, _)| read),
+                    io_write_bytes: io_bytes.map(|(_, write)| write),
                     last_start,
+                    mtbf,
+                    mtbf_seconds: stats.mtbf_secs,
                 };
 
                 // 应用筛选条件
@@ -230,15 +277,14 @@ async fn get_process_list(
     Ok(process_list)
 }
 
-fn output_process_list(process_list: &[ProcessInfo], json: bool) -> Result<()> {
-    if json {
-        // JSON输出
-        println!("{}", serde_json::to_string_pretty(process_list)?);
+fn output_process_list(process_list: &[ProcessInfo], output: OutputFormat) -> Result<()> {
+    if output.is_json() {
+        output::print_result("list", serde_json::json!({ "processes": process_list }));
     } else {
         // 表格输出
         let mut table = Table::new();
         table.add_row(row![
-            "NAME", "PID", "MONITOR", "STATUS", "RESTARTS", "UPTIME", "CPU", "MEM", "LAST START"
+            "NAME", "PID", "MONITOR", "STATUS", "RESTARTS", "UPTIME", "CPU", "MEM", "IO (R/W)", "LAST START", "MTBF"
         ]);
 
         for info in process_list {
@@ -251,7 +297,9 @@ fn output_process_list(process_list: &[ProcessInfo], json: bool) -> Result<()> {
                 info.uptime,
                 info.cpu,
                 info.mem,
-                info.last_start
+                info.io,
+                info.last_start,
+                info.mtbf
             ]);
         }
 
@@ -261,125 +309,34 @@ fn output_process_list(process_list: &[ProcessInfo], json: bool) -> Result<()> {
     Ok(())
 }
 
-#[cfg(windows)]
-async fn get_process_info(pid: i32) -> Result<(String, String, u64, String, f64, String, u64)> {
-    // 检查进程是否存在并获取进程名
-    let status = Command::new("C:\\Windows\\System32\\tasklist.exe")
-        .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
-        .output()
-        .await
-        .context("执行 tasklist 命令失败")?;
-
-    if status.stdout.is_empty() || String::from_utf8_lossy(&status.stdout).contains("No tasks") {
-        return Ok(("stopped".into(), "0".into(), 0, "0%".into(), 0.0, "0MB".into(), 0));
+/// 获取运行中进程的状态、运行时长、CPU和内存
+///
+/// 有cgroup统计时CPU和内存为整个进程树的用量
+fn get_process_info(backend: &dyn ProcessBackend, pid: i32, tree: Option<&CgroupStats>) -> (String, String, u64, String, f64, String, u64) {
+    if !backend.exists(pid) {
+        return ("stopped".into(), "0".into(), 0, "0%".into(), 0.0, "0MB".into(), 0);
     }
 
-    // 解析进程名
-    let output_str = String::from_utf8_lossy(&status.stdout);
-    let parts: Vec<&str> = output_str.split(',').collect();
-    if parts.is_empty() {
-        return Ok(("online".into(), "N/A".into(), 0, "N/A".into(), 0.0, "N/A".into(), 0));
-    }
-    
-    // 去除引号获取进程名
-    let process_name = parts[0].trim_matches('"');
-
-    // 获取进程启动时间
-    let uptime_output = Command::new("C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.exe")
-        .args([
-            "-NoProfile",
-            "-Command",
-            &format!("$p = Get-Process -Id {}; $uptime = (Get-Date) - $p.StartTime; Write-Output $uptime.TotalSeconds", pid)
-        ])
-        .output()
-        .await
-        .context("执行 PowerShell 命令获取进程运行时间失败")?;
-
-    let uptime_str = String::from_utf8_lossy(&uptime_output.stdout);
-    let uptime_seconds = if uptime_str.trim().is_empty() {
-        0
-    } else {
-        uptime_str.trim().parse::<f64>().unwrap_or(0.0) as u64
-    };
-    let uptime = format_uptime(uptime_seconds);
-
-    // 获取CPU使用率
-    let cpu_output = Command::new("C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.exe")
-        .args([
-            "-NoProfile",
-            "-Command",
-            &format!("Get-Process -Id {} | Select-Object -ExpandProperty CPU", pid)
-        ])
-        .output()
-        .await
-        .context("执行 PowerShell 命令获取 CPU 使用率失败")?;
-
-    let cpu_str = String::from_utf8_lossy(&cpu_output.stdout);
-    let cpu_float = if cpu_str.trim().is_empty() {
-        0.0
-    } else {
-        cpu_str.trim().parse::<f64>().unwrap_or(0.0)
-    };
-    let cpu = format!("{:.1}%", cpu_float.max(0.0));
-
-    // 获取内存使用
-    let mem_output = Command::new("C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.exe")
-        .args([
-            "-NoProfile",
-            "-Command",
-            &format!("Get-Process -Id {} | Select-Object -ExpandProperty WorkingSet64", pid)
-        ])
-        .output()
-        .await
-        .context("执行 PowerShell 命令失败")?;
-
-    let mem_str = String::from_utf8_lossy(&mem_output.stdout);
-    let mem_bytes = if mem_str.trim().is_empty() {
-        0
-    } else {
-        mem_str.trim().parse::<u64>().unwrap_or(0)
-    };
-    let mem = format!("{}MB", mem_bytes / 1024 / 1024);
-
-    Ok(("online".into(), uptime, uptime_seconds, cpu, cpu_float, mem, mem_bytes))
-}
-
-#[cfg(unix)]
-async fn get_process_info(pid: i32) -> Result<(String, String, u64, String, f64, String, u64)> {
-    use nix::unistd::Pid;
-    use nix::sys::signal::{kill, Signal};
-    // 检查进程是否存在
-    if !kill(Pid::from_raw(pid), None).is_ok() {
-        return Ok(("stopped".into(), "0".into(), 0, "0%".into(), 0.0, "0MB".into(), 0));
-    }
-
-    // 获取进程信息
-    let output = Command::new("ps")
-        .args(["-p", &pid.to_string(), "-o", "pid,etime,%cpu,%mem", "--no-headers"])
-        .output()
-        .await?;
-
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let parts: Vec<&str> = output_str.split_whitespace().collect();
-    
-    if parts.len() >= 4 {
-        // 解析运行时间
-        let uptime_str = parts[1].to_string();
-        let uptime_seconds = parse_ps_etime(&uptime_str);
-        
-        // 解析CPU使用率
-        let cpu_float = parts[2].trim().parse::<f64>().unwrap_or(0.0);
-        let cpu = format!("{}%", parts[2]);
-        
-        // 解析内存使用率并转换为字节
-        let mem_percent = parts[3].trim().parse::<f64>().unwrap_or(0.0);
-        let total_mem = get_total_memory()?;
-        let mem_bytes = (total_mem as f64 * mem_percent / 100.0) as u64;
-        let mem = format!("{}MB", mem_bytes / 1024 / 1024);
-        
-        Ok(("online".into(), uptime_str, uptime_seconds, cpu, cpu_float, mem, mem_bytes))
-    } else {
-        Ok(("online".into(), "N/A".into(), 0, "N/A".into(), 0.0, "N/A".into(), 0))
+    match backend.metrics(pid) {
+        Some(mut stats) => {
+            if let Some(cpu_usec) = tree.and_then(|tree| tree.cpu_usec) {
+                stats.cpu_seconds = cpu_usec as f64 / 1_000_000.0;
+            }
+            if let Some(memory_bytes) = tree.and_then(|tree| tree.memory_bytes) {
+                stats.rss_bytes = memory_bytes;
+            }
+            let cpu_float = stats.cpu_percent();
+            (
+                "online".into(),
+                format_uptime(stats.uptime_secs),
+                stats.uptime_secs,
+                format!("{:.1}%", cpu_float),
+                cpu_float,
+                format!("{}MB", stats.rss_bytes / 1024 / 1024),
+                stats.rss_bytes,
+            )
+        }
+        None => ("online".into(), "N/A".into(), 0, "N/A".into(), 0.0, "N/A".into(), 0),
     }
 }
 
@@ -398,31 +355,40 @@ fn format_uptime(seconds: u64) -> String {
     }
 }
 
-#[cfg(unix)]
-fn parse_ps_etime(etime: &str) -> u64 {
-    let parts: Vec<&str> = etime.split(':').collect();
-    match parts.len() {
-        1 => parts[0].parse::<u64>().unwrap_or(0),
-        2 => {
-            let minutes = parts[0].parse::<u64>().unwrap_or(0);
-            let seconds = parts[1].parse::<u64>().unwrap_or(0);
-            minutes * 60 + seconds
-        }
-        3 => {
-            let hours = parts[0].parse::<u64>().unwrap_or(0);
-            let minutes = parts[1].parse::<u64>().unwrap_or(0);
-            let seconds = parts[2].parse::<u64>().unwrap_or(0);
-            hours * 3600 + minutes * 60 + seconds
-        }
-        _ => 0
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::commands::start::handle_start;
+    use crate::process::ProcessManager;
+    use crate::test_support::{Fixture, TestWorkspace};
+
+    #[tokio::test]
+    async fn test_list_reports_started_and_stopped_processes() -> Result<()> {
+        let env = TestWorkspace::new();
+        let config = env.write_config(vec![
+            Fixture::Sleep.process_config("web"),
+            Fixture::Sleep.process_config("worker"),
+        ])?;
+        handle_start(&env.workspace, &config, Some("web".into()), true, false, false).await?;
+        handle_start(&env.workspace, &config, Some("worker".into()), true, false, false).await?;
+
+        let worker = config.get_process_config("worker").unwrap();
+        ProcessManager::with_config(&env.workspace, "worker".into(), worker.process).stop().await?;
+
+        let mut list = get_process_list(&env.workspace, &None, &None).await?;
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].name, "web");
+        assert_eq!(list[0].status, "online");
+        assert_eq!(list[0].pid, env.state("web").pid.unwrap().to_string());
+        assert!(list[0].mem_bytes > 0);
+        assert_eq!(list[1].name, "worker");
+        assert_eq!(list[1].status, "stopped");
+
+        // 按状态筛选
+        let filter = Some(ListFilter { status: Some("online".into()), ..Default::default() });
+        let online = get_process_list(&env.workspace, &None, &filter).await?;
+        assert_eq!(online.len(), 1);
+        Ok(())
     }
 }
-
-#[cfg(unix)]
-fn get_total_memory() -> Result<u64> {
-    let output = std::process::Command::new("sysctl")
-        .args(["-n", "hw.memsize"])
-        .output()?;
-    let mem_str = String::from_utf8_lossy(&output.stdout);
-    Ok(mem_str.trim().parse::<u64>().unwrap_or(0))
-} 
\ No newline at end of file
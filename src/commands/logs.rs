@@ -1,9 +1,15 @@
 use anyhow::Result;
 use log::{info, error};
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
+use std::path::Path;
 use std::process::Stdio;
+use std::time::Duration;
 use chrono::Local;
+use serde_json::json;
 use crate::workspace::Workspace;
+use crate::error::Failure;
+use crate::output::{self, OutputFormat};
 
 pub async fn handle_logs(
     workspace: &Workspace,
@@ -11,6 +17,7 @@ pub async fn handle_logs(
     follow: bool,
     log_type: String,
     date: Option<String>,
+    output: OutputFormat,
 ) -> Result<()> {
     let date = date.unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string());
     let log_path = if log_type == "stderr" {
@@ -27,35 +34,31 @@ pub async fn handle_logs(
 
     if !log_path.exists() {
         error!("日志文件不存在: {:?}", log_path);
-        anyhow::bail!("日志文件不存在");
+        anyhow::bail!(Failure::NotFound(format!("日志文件不存在: {}", log_path.display())));
     }
 
+    if output.is_json() {
+        return stream_json_lines(&name, &log_type, &log_path, follow, |doc| println!("{}", doc)).await;
+    }
+
+    let mut cmd = viewer_command(&log_path, follow);
+    cmd.stdout(Stdio::inherit())
+       .stderr(Stdio::inherit());
+    let mut child = cmd.spawn()?;
+    child.wait().await?;
+    Ok(())
+}
+
+/// 查看进程日志的命令: follow时持续输出新内容, 否则用分页器查看
+fn viewer_command(log_path: &Path, follow: bool) -> Command {
     #[cfg(windows)]
     {
-        let mut cmd = if follow {
-            let mut cmd = Command::new("powershell");
-            cmd.args([
-                "Get-Content",
-                "-Path",
-                &log_path.to_string_lossy(),
-                "-Tail",
-                "1000",
-                "-Wait"
-            ]);
-            cmd
-        } else {
-            let mut cmd = Command::new("powershell");
-            cmd.args([
-                "Get-Content",
-                "-Path",
-                &log_path.to_string_lossy(),
-            ]);
-            cmd
-        };
-        cmd.stdout(Stdio::inherit())
-           .stderr(Stdio::inherit());
-        let mut child = cmd.spawn()?;
-        child.wait().await?;
+        let mut cmd = Command::new("powershell");
+        cmd.args(["Get-Content", "-Path"]).arg(log_path);
+        if follow {
+            cmd.args(["-Tail", "1000", "-Wait"]);
+        }
+        cmd
     }
 
     #[cfg(unix)]
@@ -69,14 +72,9 @@ pub async fn handle_logs(
             cmd.args(["+F"]);
             cmd
         };
-        cmd.arg(&log_path)
-           .stdout(Stdio::inherit())
-           .stderr(Stdio::inherit());
-        let mut child = cmd.spawn()?;
-        child.wait().await?;
+        cmd.arg(log_path);
+        cmd
     }
-
-    Ok(())
 }
 
 pub async fn handle_system_logs(
@@ -92,7 +90,7 @@ pub async fn handle_system_logs(
 
     if !log_path.exists() {
         error!("日志文件不存在: {:?}", log_path);
-        anyhow::bail!("日志文件不存在");
+        anyhow::bail!(Failure::NotFound(format!("日志文件不存在: {}", log_path.display())));
     }
 
     #[cfg(windows)]
@@ -146,4 +144,89 @@ pub async fn handle_system_logs(
     }
 
     Ok(())
-} 
\ No newline at end of file
+}
+
+/// 以NDJSON格式输出日志, 每行日志一个文档交给 `emit`; follow时持续输出新写入的行
+async fn stream_json_lines(
+    name: &str,
+    stream: &str,
+    log_path: &Path,
+    follow: bool,
+    mut emit: impl FnMut(serde_json::Value),
+) -> Result<()> {
+    let file = tokio::fs::File::open(log_path).await?;
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    loop {
+        let n = reader.read_line(&mut line).await?;
+        // follow时不完整的行留到下次读取补全
+        if n == 0 || (follow && !line.ends_with('\n')) {
+            if !follow {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            continue;
+        }
+        emit(output::document("logs", "log", json!({
+            "process": name,
+            "stream": stream,
+            "line": line.trim_end_matches(['\r', '\n']),
+        })));
+        line.clear();
+    }
+    if !line.is_empty() {
+        emit(output::document("logs", "log", json!({
+            "process": name,
+            "stream": stream,
+            "line": line,
+        })));
+    }
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::process::daemon::ProcessManagerDaemonExt;
+    use crate::test_support::{Fixture, TestWorkspace};
+
+    #[tokio::test]
+    async fn test_logs_of_monitored_process() -> Result<()> {
+        let env = TestWorkspace::new();
+        let err = handle_logs(&env.workspace, "web".into(), false, "stdout".into(), None, OutputFormat::Json)
+            .await.unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 3);
+
+        let fixture = Fixture::Print(vec!["hello".into(), "world".into()]);
+        let working_dir = env.working_dir("web");
+        std::fs::create_dir_all(&working_dir)?;
+        env.manager("web").monitor_and_restart(fixture.spawn(&working_dir)?).await?;
+
+        let log_path = env.workspace.get_process_dir("web")
+            .join("logs")
+            .join(Local::now().format("%Y-%m-%d").to_string())
+            .join("stdout.log");
+        let logged = crate::test_support::wait_until(Duration::from_secs(2), || {
+            std::fs::read_to_string(&log_path).is_ok_and(|content| content == "hello\nworld\n")
+        }).await;
+        assert!(logged);
+
+        let mut docs = Vec::new();
+        stream_json_lines("web", "stdout", &log_path, false, |doc| docs.push(doc)).await?;
+        let lines: Vec<String> = docs.iter().map(|doc| doc.to_string()).collect();
+        assert_eq!(lines, vec![
+            r#"{"command":"logs","line":"hello","process":"web","stream":"stdout","type":"log","version":1}"#,
+            r#"{"command":"logs","line":"world","process":"web","stream":"stdout","type":"log","version":1}"#,
+        ]);
+
+        // 输出不是终端时分页器直接输出日志内容
+        let viewed = viewer_command(&log_path, false)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .output()
+            .await?;
+        assert!(viewed.status.success());
+        assert_eq!(String::from_utf8(viewed.stdout)?, "hello\nworld\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use anyhow::Result;
+use log::info;
+use serde_json::json;
+use crate::config::Config;
+use crate::metrics::{self, MetricsExporter};
+use crate::output::{self, OutputFormat};
+use crate::workspace::Workspace;
+
+pub async fn handle_metrics(
+    workspace: &Workspace,
+    config: &Config,
+    listen: Option<String>,
+    once: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let exporter = MetricsExporter::new(
+        workspace.clone(),
+        config.global.process.health_check_timeout(),
+    );
+
+    if once {
+        let processes = exporter.collect().await?;
+        if output.is_json() {
+            output::print_result("metrics", json!({ "processes": processes }));
+        } else {
+            print!("{}", metrics::render(&processes));
+        }
+        return Ok(());
+    }
+
+    let listen = listen.unwrap_or_else(|| config.global.metrics.listen.clone());
+    info!("启动指标接口: {}", listen);
+    exporter.serve(&listen).await
+}
@@ -4,10 +4,36 @@ pub mod status;
 pub mod monitor;
 pub mod logs;
 pub mod list;
+pub mod crashes;
+pub mod events;
+pub mod metrics;
+pub mod workspace;
+pub mod init;
+pub mod add;
+pub mod run;
+pub mod restart;
+pub mod prepare;
+pub mod deploy;
+pub mod proxy;
+pub mod reexec;
+pub mod recover;
 
 pub use start::handle_start;
 pub use stop::handle_stop;
 pub use status::handle_status;
 pub use monitor::handle_monitor;
 pub use logs::{handle_logs, handle_system_logs};
-pub use list::handle_list; 
\ No newline at end of file
+pub use list::handle_list; 
+pub use crashes::handle_crashes;
+pub use events::handle_events;
+pub use metrics::handle_metrics;
+pub use workspace::handle_workspace_info;
+pub use init::handle_init;
+pub use add::handle_add;
+pub use run::handle_run;
+pub use restart::handle_restart;
+pub use prepare::handle_prepare;
+pub use deploy::{handle_deploy, handle_rollback};
+pub use proxy::handle_proxy;
+pub use reexec::{handle_self_reexec, handle_resume};
+pub use recover::handle_recover;
\ No newline at end of file
@@ -12,6 +12,8 @@ use crate::process::daemon::ProcessManagerDaemonExt;
 use tokio::time::sleep;
 use std::time::Duration;
 use crate::state::ProcessState;
+use crate::events::{self, EventKind};
+use crate::notify::Notifier;
 use std::io::Write;
 
 pub async fn handle_monitor(
@@ -29,16 +31,7 @@ pub async fn handle_monitor(
     info!("进程名称: {}", process_name);
     info!("程序: {}", program);
     
-    // 检查Python解释器是否存在
-    let program = if cfg!(windows) && program.contains("python.exe") {
-        // 在Windows上，优先使用py启动器
-        String::from("py")
-    } else {
-        program
-    };
-    
-    info!("使用Python解释器: {}", program);
-    
+    // 程序和参数已由启动方按运行时解析, 这里原样执行
     info!("参数: {:?}", args);
     info!("工作目录: {:?}", working_dir);
     
@@ -68,34 +61,8 @@ pub async fn handle_monitor(
         return Err(anyhow::anyhow!("工作目录不存在: {:?}", working_dir));
     }
     
-    // 检查并修正参数中的文件路径
-    let mut fixed_args = Vec::new();
-    for arg in args {
-        if arg.ends_with(".py") {
-            let script_path = if arg.contains('\\') || arg.contains('/') {
-                // 如果参数中已经包含路径分隔符,说明是完整路径
-                PathBuf::from(arg)
-            } else {
-                // 否则基于工作目录构建路径
-                working_dir.join(arg)
-            };
-            info!("Python脚本路径: {:?}", script_path);
-            info!("Python脚本是否存在: {}", script_path.exists());
-            if script_path.exists() {
-                info!("Python脚本大小: {} bytes", std::fs::metadata(&script_path).map(|m| m.len()).unwrap_or(0));
-            }
-            if !script_path.exists() {
-                error!("Python脚本不存在: {:?}", script_path);
-                return Err(anyhow::anyhow!("Python脚本不存在: {:?}", script_path));
-            }
-            fixed_args.push(script_path.to_string_lossy().to_string());
-        } else {
-            fixed_args.push(arg);
-        }
-    }
-    
-    info!("修正后的参数: {:?}", fixed_args);
-    
+    let fixed_args = args;
+
     // 解析环境变量
     let mut env_map = HashMap::new();
     for env in env_vars {
@@ -113,6 +80,22 @@ pub async fn handle_monitor(
     
     // 设置自动重启
     manager.set_auto_restart(auto_restart);
+
+    // 重启时沿用相同的环境变量
+    manager.set_env_vars(env_map.clone());
+
+    // 设置进程日志投递目标、重启次数上限、生命周期钩子和状态通知
+    let process_config = config.get_process_config(&process_name);
+    if let Some(cfg) = &process_config {
+        manager.set_max_restarts(cfg.max_restarts);
+        manager.set_hooks(cfg.hooks.clone());
+    }
+    manager.set_notifier(Notifier::new(config.global.notifications.clone()));
+    let log_sinks = process_config
+        .map(|cfg| cfg.log.sinks)
+        .unwrap_or_default();
+    logger.attach_process_sinks(&process_name, &log_sinks);
+    manager.set_log_sinks(log_sinks);
     
     // 启动并监控进程
     let mut cmd = Command::new(&program);
@@ -153,7 +136,7 @@ pub async fn handle_monitor(
     // 写入启动信息
     write_log("[INFO] 启动监控进程")?;
     write_log(&format!("[INFO] 进程名称: {}", process_name))?;
-    write_log(&format!("[INFO] Python解释器: {}", program))?;
+    write_log(&format!("[INFO] 程序: {}", program))?;
     write_log(&format!("[INFO] 参数: {:?}", fixed_args))?;
     write_log(&format!("[INFO] 工作目录: {:?}", working_dir))?;
     
@@ -172,23 +155,18 @@ pub async fn handle_monitor(
     let health_check_url = process_config.as_ref().and_then(|cfg| cfg.health_check_url.as_deref());
 
     // 保存进程状态
-    let mut state = ProcessState::load(workspace, &process_name)
-        .unwrap_or_else(|_| ProcessState::default());
-    info!("加载现有状态: {:?}", state);
-    write_log(&format!("[INFO] 加载现有状态: {:?}", state))?;
-    
-    let monitor_pid = state.monitor_pid;  // 保存monitor_pid
-    state.pid = Some(pid as i32);
-    state.program = program.clone();
-    state.args = fixed_args.clone();
-    state.working_dir = working_dir.clone();
-    state.port = config.global.process.default_port;
-    state.health_check_url = health_check_url.map(String::from);
-    state.monitor_pid = monitor_pid;  // 恢复monitor_pid
+    // 加锁更新, 保留monitor_pid和重启计数
+    let state = ProcessState::update(workspace, &process_name, |state| {
+        state.set_pid(pid as i32);
+        state.program = program.clone();
+        state.args = fixed_args.clone();
+        state.working_dir = working_dir.clone();
+        state.port = config.global.process.default_port;
+        state.health_check_url = health_check_url.map(String::from);
+    })?;
     info!("更新后状态: {:?}", state);
     write_log(&format!("[INFO] 更新后状态: {:?}", state))?;
-    
-    state.save(workspace, &process_name)?;
+    events::record(workspace, &process_name, EventKind::Started { pid: pid as i32 });
     info!("进程状态已保存");
     write_log("[INFO] 进程状态已保存")?;
 
@@ -196,6 +174,7 @@ pub async fn handle_monitor(
     ProcessManager::handle_process_output(
         process_name.clone(),
         logger,
+        manager.output_tail.clone(),
         child.stdout.take(),
         child.stderr.take()
     ).await;
@@ -0,0 +1,43 @@
+use anyhow::Result;
+use serde_json::json;
+use crate::config::Config;
+use crate::error::Failure;
+use crate::output::{self, OutputFormat};
+use crate::venv;
+use crate::workspace::Workspace;
+
+/// 创建进程的虚拟环境并安装依赖, 和 `start` 前自动执行的准备步骤相同
+pub async fn handle_prepare(
+    workspace: &Workspace,
+    config: &Config,
+    name: String,
+    force: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let Some(process_config) = config.get_process_config(&name) else {
+        return Err(Failure::NotFound(format!("未找到进程配置: {}", name)).into());
+    };
+    let Some(venv_config) = process_config.venv() else {
+        return Err(Failure::ConfigInvalid(format!("进程 {} 没有配置 python.venv", name)).into());
+    };
+
+    let working_dir = workspace.get_process_working_dir(&name);
+    let outcome = venv::prepare(workspace, &name, &working_dir, venv_config, force).await?;
+
+    if output.is_json() {
+        output::print_result("prepare", json!({
+            "process": name,
+            "venv": outcome.venv,
+            "created": outcome.created,
+            "installed": outcome.installed,
+        }));
+    } else {
+        println!("虚拟环境: {}{}", outcome.venv.display(), if outcome.created { " (新建)" } else { "" });
+        if outcome.installed {
+            println!("已安装依赖");
+        } else {
+            println!("依赖未变化, 跳过安装");
+        }
+    }
+    Ok(())
+}
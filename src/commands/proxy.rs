@@ -0,0 +1,299 @@
+use std::process::Stdio;
+use std::time::Duration;
+use anyhow::{Result, Context};
+use log::{info, warn};
+use crate::commands::start::start_process;
+use crate::config::{Config, ProcessConfig};
+use crate::process::ProcessManager;
+use crate::proxy::{self, ProxyConfig, TcpProxy};
+use crate::state::ProcessState;
+use crate::workspace::Workspace;
+
+/// 运行进程的TCP代理, 由 `start` 在后台启动
+pub async fn handle_proxy(workspace: &Workspace, name: String, listen: String) -> Result<()> {
+    TcpProxy::new(workspace.clone(), name).serve(&listen).await
+}
+
+fn proxy_config(process: &ProcessConfig) -> Result<ProxyConfig> {
+    let proxy = process.proxy.clone()
+        .with_context(|| format!("进程 {} 没有配置proxy", process.name))?;
+    proxy.validate()?;
+    Ok(proxy)
+}
+
+/// 启动代理和当前目标端口上的实例
+pub(crate) async fn start_proxied(
+    workspace: &Workspace,
+    config: &Config,
+    process: &ProcessConfig,
+    daemon: bool,
+    auto_restart: bool,
+) -> Result<()> {
+    let proxy = proxy_config(process)?;
+    ensure_proxy(workspace, process, &proxy).await?;
+    start_active(workspace, config, process, daemon, auto_restart).await?;
+    Ok(())
+}
+
+/// 在代理当前的目标端口启动实例, 还没有目标时使用第一个端口
+async fn start_active(
+    workspace: &Workspace,
+    config: &Config,
+    process: &ProcessConfig,
+    daemon: bool,
+    auto_restart: bool,
+) -> Result<u16> {
+    let proxy = proxy_config(process)?;
+    let port = proxy::active_port(workspace, &process.name)
+        .filter(|port| proxy.ports.contains(port))
+        .unwrap_or(proxy.ports[0]);
+    start_instance(workspace, config, process, port, daemon, auto_restart).await?;
+    proxy::set_active_port(workspace, &process.name, port)?;
+    Ok(port)
+}
+
+/// 确保代理在运行, 然后蓝绿切换到新实例
+pub(crate) async fn restart_proxied(
+    workspace: &Workspace,
+    config: &Config,
+    process: &ProcessConfig,
+    daemon: bool,
+    auto_restart: bool,
+) -> Result<u16> {
+    let proxy = proxy_config(process)?;
+    ensure_proxy(workspace, process, &proxy).await?;
+    switch(workspace, config, process, daemon, auto_restart).await
+}
+
+/// 蓝绿切换: 在另一个端口启动新实例, 健康检查通过后切换代理目标, 排空并停止旧实例
+///
+/// 新实例启动失败时代理仍指向旧实例, 返回切换后的端口
+async fn switch(
+    workspace: &Workspace,
+    config: &Config,
+    process: &ProcessConfig,
+    daemon: bool,
+    auto_restart: bool,
+) -> Result<u16> {
+    let proxy = proxy_config(process)?;
+    let current = proxy::active_port(workspace, &process.name).filter(|port| proxy.ports.contains(port));
+    let running = match current {
+        Some(port) => instance_manager(workspace, process, port).status(None).await?,
+        None => false,
+    };
+    let Some(current) = current.filter(|_| running) else {
+        info!("进程 {} 没有运行中的实例, 直接启动", process.name);
+        return start_active(workspace, config, process, daemon, auto_restart).await;
+    };
+
+    let next = proxy.other_port(current);
+    stop_instance(workspace, process, next).await?;
+    info!("在端口 {} 启动新实例", next);
+    if let Err(e) = start_instance(workspace, config, process, next, daemon, auto_restart).await {
+        if let Err(stop_error) = stop_instance(workspace, process, next).await {
+            warn!("停止启动失败的实例失败: {}", stop_error);
+        }
+        return Err(e.context(format!("新实例启动失败, 代理仍指向端口 {}", current)));
+    }
+
+    proxy::set_active_port(workspace, &process.name, next)?;
+    drain(workspace, &process.name, current, proxy.drain_timeout()).await;
+    stop_instance(workspace, process, current).await?;
+    Ok(next)
+}
+
+/// 停止所有实例和代理
+pub(crate) async fn stop_proxied(workspace: &Workspace, process: &ProcessConfig) -> Result<()> {
+    let proxy = proxy_config(process)?;
+    for port in proxy.ports {
+        stop_instance(workspace, process, port).await?;
+    }
+    if workspace.get_process_state_file(&process.name).exists() {
+        info!("正在停止代理: {}", process.name);
+        let mut settings = process.process.clone();
+        settings.default_port = proxy.listen_port().unwrap_or(settings.default_port);
+        ProcessManager::with_config(workspace, process.name.clone(), settings).stop().await?;
+    }
+    Ok(())
+}
+
+fn instance_manager<'a>(workspace: &'a Workspace, process: &ProcessConfig, port: u16) -> ProcessManager<'a> {
+    let instance = proxy::instance_config(process, port);
+    let mut manager = ProcessManager::with_config(workspace, instance.name, instance.process);
+    manager.set_env_vars(instance.env);
+    manager.set_hooks(instance.hooks);
+    manager
+}
+
+/// 启动实例并等待健康检查通过
+async fn start_instance(
+    workspace: &Workspace,
+    config: &Config,
+    process: &ProcessConfig,
+    port: u16,
+    daemon: bool,
+    auto_restart: bool,
+) -> Result<()> {
+    let instance = proxy::instance_config(process, port);
+    start_process(workspace, config, &process.name, &instance, daemon, auto_restart).await?;
+    if let Some(url) = instance.health_check_url.as_deref() {
+        instance_manager(workspace, process, port).wait_healthy(url).await?;
+    }
+    Ok(())
+}
+
+async fn stop_instance(workspace: &Workspace, process: &ProcessConfig, port: u16) -> Result<()> {
+    let instance = proxy::instance_name(&process.name, port);
+    if !workspace.get_process_state_file(&instance).exists() {
+        return Ok(());
+    }
+    info!("正在停止实例: {}", instance);
+    instance_manager(workspace, process, port).stop().await
+}
+
+/// 等待代理到旧端口的连接结束, 超时后不再等待
+async fn drain(workspace: &Workspace, name: &str, port: u16, timeout: Duration) {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let connections = proxy::active_connections(workspace, name, port);
+        if connections == 0 {
+            info!("端口 {} 上的连接已排空", port);
+            return;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            warn!("端口 {} 上仍有 {} 个连接, 排空超时", port, connections);
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// 代理进程没有运行时在后台启动, 并等待它开始监听
+///
+/// 代理进程记录在进程名对应的状态中, `list` 和 `status` 显示的是代理的状态
+async fn ensure_proxy(workspace: &Workspace, process: &ProcessConfig, proxy: &ProxyConfig) -> Result<()> {
+    let manager = ProcessManager::with_config(workspace, process.name.clone(), process.process.clone());
+    if manager.status(None).await? {
+        return Ok(());
+    }
+
+    let program = std::env::current_exe().context("获取程序路径失败")?;
+    let root = workspace.get_root_dir().to_path_buf();
+    let args = vec![
+        "--workspace".to_string(),
+        root.to_string_lossy().to_string(),
+        "proxy".to_string(),
+        process.name.clone(),
+        "--listen".to_string(),
+        proxy.listen.clone(),
+    ];
+    let mut command = std::process::Command::new(&program);
+    command
+        .args(&args)
+        .current_dir(&root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // 脱离当前终端的进程组, 命令行退出或Ctrl+C时代理继续运行
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        use winapi::um::winbase::{CREATE_NEW_PROCESS_GROUP, DETACHED_PROCESS};
+        command.creation_flags(CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS);
+    }
+    let mut child = command.spawn().context("启动代理进程失败")?;
+    let pid = child.id() as i32;
+    info!("代理进程已启动, PID: {}, 监听: {}", pid, proxy.listen);
+
+    workspace.ensure_process_dirs(&process.name)?;
+    ProcessState::update(workspace, &process.name, |state| {
+        state.set_pid(pid);
+        state.program = program.to_string_lossy().to_string();
+        state.args = args.clone();
+        state.working_dir = root.clone();
+        state.port = proxy.listen_port().unwrap_or(state.port);
+        state.health_check_url = None;
+    })?;
+
+    for _ in 0..50 {
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!("代理进程已退出({}), 请查看系统日志", status);
+        }
+        if tokio::net::TcpStream::connect(&proxy.listen).await.is_ok() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    anyhow::bail!("代理进程没有在5秒内开始监听 {}", proxy.listen)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use crate::process::backend;
+    use crate::test_support::{free_port, Fixture, TestWorkspace};
+
+    async fn fetch(addr: &str) -> Result<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(b"GET / HTTP/1.0\r\n\r\n").await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    #[tokio::test]
+    async fn test_switch_starts_new_instance_before_stopping_old() -> Result<()> {
+        if which::which("python3").is_err() {
+            return Ok(());
+        }
+        let ws = TestWorkspace::new();
+        let mut process = Fixture::Sleep.process_config("web");
+        process.program = "python3".into();
+        process.args = vec!["-m".into(), "http.server".into(), "--bind".into(), "127.0.0.1".into(), "{port}".into()];
+        process.health_check_url = Some("http://127.0.0.1:{port}/".into());
+        process.process.health_check_retries = 20;
+        let listen = format!("127.0.0.1:{}", free_port());
+        process.proxy = Some(ProxyConfig {
+            listen: listen.clone(),
+            ports: [free_port(), free_port()],
+            drain_timeout: 1,
+        });
+        let ports = process.proxy.as_ref().unwrap().ports;
+        let config = ws.write_config(vec![process.clone()])?;
+
+        // 测试中在进程内运行代理, 不启动单独的代理进程
+        let listener = tokio::net::TcpListener::bind(&listen).await?;
+        let tcp_proxy = TcpProxy::new(ws.workspace.clone(), "web".into());
+        tokio::spawn(async move { tcp_proxy.serve_listener(listener).await });
+
+        assert_eq!(start_active(&ws.workspace, &config, &process, false, false).await?, ports[0]);
+        assert!(fetch(&listen).await?.starts_with("HTTP/1.0 200"));
+        let blue = ws.state(&proxy::instance_name("web", ports[0])).pid.unwrap();
+
+        // 切换后流量到新实例, 旧实例被停止
+        assert_eq!(switch(&ws.workspace, &config, &process, false, false).await?, ports[1]);
+        assert_eq!(proxy::active_port(&ws.workspace, "web"), Some(ports[1]));
+        assert!(fetch(&listen).await?.starts_with("HTTP/1.0 200"));
+        assert!(!backend::native().exists(blue));
+
+        // 新实例健康检查失败时保持原来的目标
+        let mut broken = process.clone();
+        broken.args = vec!["-c".into(), "exit 1".into()];
+        broken.process.health_check_retries = 2;
+        assert!(switch(&ws.workspace, &config, &broken, false, false).await.is_err());
+        assert_eq!(proxy::active_port(&ws.workspace, "web"), Some(ports[1]));
+        assert!(fetch(&listen).await?.starts_with("HTTP/1.0 200"));
+
+        for port in ports {
+            stop_instance(&ws.workspace, &process, port).await?;
+        }
+        Ok(())
+    }
+}
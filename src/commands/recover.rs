@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::Result;
+use log::{info, warn};
+use serde_json::json;
+use crate::commands::run::start_transient;
+use crate::commands::start::start_process;
+use crate::config::{Config, ProcessConfig};
+use crate::crash::{CrashRecord, OutputTail};
+use crate::events::{self, EventKind};
+use crate::hooks::{self, HookContext, HookEvent};
+use crate::notify::{Notification, NotifyEvent, Notifier};
+use crate::output::{self, OutputFormat};
+use crate::process::ProcessManager;
+use crate::process::backend;
+use crate::process::daemon::ProcessManagerDaemonExt;
+use crate::state::ProcessState;
+use crate::workspace::Workspace;
+
+/// 对一个进程的恢复结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recovery {
+    /// 监控进程仍在运行, 不需要恢复
+    Supervised,
+    /// 没有监控进程记录(没有开启自动重启)或进程配置不存在
+    Skipped(String),
+    /// 进程仍在运行, 已由新的监控进程接管
+    Adopted { pid: i32 },
+    /// 进程已退出, 按重启策略重新启动
+    Restarted { restart_count: u32 },
+    /// 进程已退出, 启动时没有开启自动重启, 不再启动
+    Stopped,
+    /// 进程已退出且重启次数已达上限
+    LimitExceeded { restart_count: u32, max_restarts: u32 },
+}
+
+impl Recovery {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Recovery::Supervised => "supervised",
+            Recovery::Skipped(_) => "skipped",
+            Recovery::Adopted { .. } => "adopted",
+            Recovery::Restarted { .. } => "restarted",
+            Recovery::Stopped => "stopped",
+            Recovery::LimitExceeded { .. } => "limit_exceeded",
+        }
+    }
+
+    pub fn detail(&self) -> String {
+        match self {
+            Recovery::Supervised => "监控进程正在运行".to_string(),
+            Recovery::Skipped(reason) => reason.clone(),
+            Recovery::Adopted { pid } => format!("已接管运行中的进程, PID: {}", pid),
+            Recovery::Restarted { restart_count } => format!("进程已退出, 已重新启动(第{}次重启)", restart_count),
+            Recovery::Stopped => "进程已退出, 没有开启自动重启, 不再启动".to_string(),
+            Recovery::LimitExceeded { restart_count, max_restarts } => {
+                format!("进程已退出, 重启次数已达上限({}/{}), 不再重启", restart_count, max_restarts)
+            }
+        }
+    }
+}
+
+/// 扫描所有进程状态, 恢复监控进程已经退出的进程
+///
+/// 监控进程崩溃或被杀死后应用仍在运行, 但没有人负责重启。仍在运行且身份校验通过的进程
+/// 由新的守护进程接管; 已经退出的进程记为崩溃, 按 `max_restarts` 重新启动
+pub async fn handle_recover(
+    workspace: &Workspace,
+    config: &Config,
+    name: Option<String>,
+    output: OutputFormat,
+) -> Result<()> {
+    let names = match name {
+        Some(name) => vec![name],
+        None => {
+            let mut names = Vec::new();
+            let processes_dir = workspace.get_processes_dir();
+            if processes_dir.exists() {
+                for entry in std::fs::read_dir(processes_dir)? {
+                    let path = entry?.path();
+                    if path.is_dir() {
+                        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                            names.push(name.to_string());
+                        }
+                    }
+                }
+            }
+            names.sort();
+            names
+        }
+    };
+
+    // 一个进程恢复失败不影响其他进程
+    let mut results = Vec::new();
+    let mut failed = 0;
+    for name in names {
+        match recover_process(workspace, config, &name).await {
+            Ok(recovery) => {
+                info!("进程 {}: {}", name, recovery.detail());
+                if !output.is_json() {
+                    println!("进程 {}: {}", name, recovery.detail());
+                }
+                results.push(json!({
+                    "process": name,
+                    "status": recovery.name(),
+                    "detail": recovery.detail(),
+                }));
+            }
+            Err(e) => {
+                warn!("恢复进程 {} 失败: {:#}", name, e);
+                if !output.is_json() {
+                    println!("进程 {} 恢复失败: {:#}", name, e);
+                }
+                failed += 1;
+                results.push(json!({ "process": name, "status": "failed", "error": format!("{:#}", e) }));
+            }
+        }
+    }
+
+    if output.is_json() {
+        output::print_result("recover", json!({ "processes": results }));
+    }
+    if failed > 0 {
+        anyhow::bail!("{}个进程恢复失败", failed);
+    }
+    Ok(())
+}
+
+/// 恢复单个进程
+pub async fn recover_process(workspace: &Workspace, config: &Config, name: &str) -> Result<Recovery> {
+    let Ok(state) = ProcessState::load(workspace, name) else {
+        return Ok(Recovery::Skipped("没有进程状态".into()));
+    };
+    let Some(monitor_pid) = state.monitor_pid else {
+        return Ok(Recovery::Skipped("没有监控进程".into()));
+    };
+
+    let backend = backend::native();
+    let alive = |pid: i32, identity: Option<&crate::process::identity::ProcessIdentity>| {
+        backend.exists(pid) && identity.is_none_or(|identity| identity.verify(pid))
+    };
+    if alive(monitor_pid, state.monitor_identity.as_ref()) {
+        return Ok(Recovery::Supervised);
+    }
+    warn!("进程 {} 的监控进程 {} 已退出", name, monitor_pid);
+
+    let Some(definition) = config.resolve_process_config(workspace, name) else {
+        return Ok(Recovery::Skipped("未找到进程配置".into()));
+    };
+    if definition.proxy.is_some() {
+        return Ok(Recovery::Skipped("蓝绿切换的进程请使用restart".into()));
+    }
+    // 按启动时的设置恢复, 旧的状态文件没有记录时使用配置
+    let auto_restart = state.auto_restart.unwrap_or(definition.auto_restart);
+    if state.daemon == Some(false) {
+        return Ok(Recovery::Skipped("进程不是以守护进程方式启动的".into()));
+    }
+    let env_vars = process_env(config, &definition, &state.working_dir);
+
+    if let Some(pid) = state.pid.filter(|pid| alive(*pid, state.identity.as_ref())) {
+        let mut manager = manager(workspace, config, &definition, auto_restart);
+        manager.set_env_vars(env_vars);
+        manager.state = state;
+        manager.adopt_daemon(pid).await?;
+        return Ok(Recovery::Adopted { pid });
+    }
+
+    // 进程在没有监控的情况下退出, 退出状态已无法获得, 记为一次崩溃
+    mark_crashed(workspace, config, &definition, &state, &env_vars).await;
+
+    if !auto_restart {
+        ProcessState::update(workspace, name, |state| state.update_stopped_state())?;
+        return Ok(Recovery::Stopped);
+    }
+
+    let restart_count = state.restart_count;
+    if restart_count >= definition.max_restarts {
+        events::record(workspace, name, EventKind::LimitExceeded {
+            restart_count,
+            max_restarts: definition.max_restarts,
+        });
+        ProcessState::update(workspace, name, |state| state.update_stopped_state())?;
+        return Ok(Recovery::LimitExceeded { restart_count, max_restarts: definition.max_restarts });
+    }
+
+    let state = ProcessState::update(workspace, name, |state| {
+        state.update_stopped_state();
+        state.increment_restart_count();
+    })?;
+    if config.processes.contains_key(name) {
+        start_process(workspace, config, name, &definition, true, true).await?;
+    } else {
+        start_transient(workspace, config, &definition, true, true).await?;
+    }
+    Ok(Recovery::Restarted { restart_count: state.restart_count })
+}
+
+/// 按配置创建进程管理器, 与启动时的设置一致
+fn manager<'a>(
+    workspace: &'a Workspace,
+    config: &Config,
+    definition: &ProcessConfig,
+    auto_restart: bool,
+) -> ProcessManager<'a> {
+    let mut manager = ProcessManager::with_config(
+        workspace,
+        definition.name.clone(),
+        definition.process.clone(),
+    );
+    manager.set_auto_restart(auto_restart);
+    manager.set_max_restarts(definition.max_restarts);
+    manager.set_hooks(definition.hooks.clone());
+    manager.set_notifier(Notifier::new(config.global.notifications.clone()));
+    manager.set_log_sinks(definition.log.sinks.clone());
+    manager.set_runtime(definition.runtime());
+    manager.set_sockets(definition.sockets.clone());
+    manager.set_pid_file(definition.pid_file.clone());
+    manager.set_limits(definition.limits.clone());
+    manager.set_sandbox(definition.sandbox.clone());
+    manager
+}
+
+/// 进程的环境变量, 包括运行时设置的变量
+fn process_env(config: &Config, definition: &ProcessConfig, working_dir: &Path) -> HashMap<String, String> {
+    let mut env = definition.env.clone();
+    for (key, value) in &config.global.env {
+        env.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+    match definition.runtime().resolve(&definition.program, &definition.args, working_dir, env.clone()) {
+        Ok(resolved) => resolved.env,
+        Err(e) => {
+            warn!("解析进程 {} 的运行时失败: {:#}", definition.name, e);
+            env
+        }
+    }
+}
+
+/// 记录进程在没有监控时退出: 退出事件、崩溃记录、通知和on_crash钩子
+async fn mark_crashed(
+    workspace: &Workspace,
+    config: &Config,
+    definition: &ProcessConfig,
+    state: &ProcessState,
+    env_vars: &HashMap<String, String>,
+) {
+    let name = &definition.name;
+    #[cfg(unix)]
+    let status = crate::process::daemon::unknown_exit_status();
+    #[cfg(windows)]
+    let status = {
+        use std::os::windows::process::ExitStatusExt;
+        std::process::ExitStatus::from_raw(1)
+    };
+
+    events::record(workspace, name, EventKind::exited(&status));
+    let record = CrashRecord::capture(
+        name,
+        state.pid,
+        &status,
+        std::time::Duration::ZERO,
+        state.restart_count,
+        env_vars,
+        &OutputTail::new(0),
+    );
+    if let Err(e) = record.save(workspace, definition.process.max_crash_records) {
+        warn!("保存崩溃记录失败: {}", e);
+    }
+    Notifier::new(config.global.notifications.clone()).notify(
+        &Notification::new(NotifyEvent::Crash, name, "进程在监控进程退出后停止运行")
+            .with_restart_count(state.restart_count),
+    ).await;
+    let ctx = HookContext {
+        workspace,
+        process_name: name,
+        working_dir: &state.working_dir,
+        env_vars,
+        exit_status: Some(&status),
+    };
+    if let Err(e) = hooks::run_hook(&definition.hooks, HookEvent::OnCrash, &ctx).await {
+        warn!("on_crash钩子失败: {}", e);
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use nix::sys::signal::{kill, Signal};
+    use nix::unistd::Pid;
+    use crate::test_support::{wait_until, Fixture, TestWorkspace};
+
+    #[tokio::test]
+    async fn test_recover_adopts_orphan_and_restarts_on_crash() -> Result<()> {
+        let ws = TestWorkspace::new();
+        let mut process = Fixture::Sleep.process_config("web");
+        process.auto_restart = true;
+        let config = ws.write_config(vec![process])?;
+        let backend = backend::native();
+        std::fs::create_dir_all(ws.workspace.get_process_working_dir("web"))?;
+
+        start_process(&ws.workspace, &config, "web", &config.processes["web"], true, true).await?;
+        assert!(wait_until(Duration::from_secs(5), || {
+            ProcessState::load(&ws.workspace, "web").is_ok_and(|state| state.pid.is_some() && state.monitor_pid.is_some())
+        }).await);
+        assert_eq!(recover_process(&ws.workspace, &config, "web").await?, Recovery::Supervised);
+
+        let state = ws.state("web");
+        let (pid, monitor_pid) = (state.pid.unwrap(), state.monitor_pid.unwrap());
+        kill(Pid::from_raw(monitor_pid), Signal::SIGKILL)?;
+        assert!(wait_until(Duration::from_secs(5), || !backend.exists(monitor_pid)).await);
+
+        assert_eq!(recover_process(&ws.workspace, &config, "web").await?, Recovery::Adopted { pid });
+        assert!(wait_until(Duration::from_secs(5), || {
+            ws.events("web").iter().any(|kind| matches!(kind, EventKind::Adopted { pid: p, .. } if *p == pid))
+        }).await);
+        assert_ne!(ws.state("web").monitor_pid, Some(monitor_pid));
+
+        // 接管后进程崩溃由新的监控进程重启
+        kill(Pid::from_raw(pid), Signal::SIGKILL)?;
+        assert!(wait_until(Duration::from_secs(10), || {
+            ws.state("web").pid.is_some_and(|new_pid| new_pid != pid && backend.exists(new_pid))
+        }).await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_marks_dead_process_crashed() -> Result<()> {
+        let ws = TestWorkspace::new();
+        let mut process = Fixture::Sleep.process_config("web");
+        process.auto_restart = true;
+        process.max_restarts = 0;
+        let config = ws.write_config(vec![process])?;
+
+        // 监控进程和应用都已退出, 只剩旧版本的状态文件(没有记录重启策略, 使用配置)
+        let state = ProcessState {
+            pid: Some(i32::MAX),
+            monitor_pid: Some(i32::MAX),
+            program: "sh".into(),
+            working_dir: ws.working_dir("web"),
+            ..Default::default()
+        };
+        ws.workspace.ensure_process_dirs("web")?;
+        state.save(&ws.workspace, "web")?;
+
+        assert_eq!(
+            recover_process(&ws.workspace, &config, "web").await?,
+            Recovery::LimitExceeded { restart_count: 0, max_restarts: 0 },
+        );
+        let events = ws.events("web");
+        assert!(events.iter().any(|kind| matches!(kind, EventKind::Exited { .. })));
+        assert!(events.iter().any(|kind| matches!(kind, EventKind::LimitExceeded { .. })));
+        assert_eq!(ws.state("web").monitor_pid, None);
+        assert!(!CrashRecord::list(&ws.workspace, "web")?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_continues_after_failure() -> Result<()> {
+        let ws = TestWorkspace::new();
+        let mut broken = Fixture::Sleep.process_config("broken");
+        broken.auto_restart = true;
+        broken.limits.memory_max = Some("lots".into());
+        let mut web = Fixture::Sleep.process_config("web");
+        web.auto_restart = true;
+        web.max_restarts = 0;
+        let config = ws.write_config(vec![broken, web])?;
+
+        // 两个进程的监控进程和应用都已退出, broken无法重新启动
+        for name in ["broken", "web"] {
+            let state = ProcessState {
+                pid: Some(i32::MAX),
+                monitor_pid: Some(i32::MAX),
+                program: "sh".into(),
+                working_dir: ws.working_dir(name),
+                ..Default::default()
+            };
+            ws.workspace.ensure_process_dirs(name)?;
+            state.save(&ws.workspace, name)?;
+        }
+
+        let err = handle_recover(&ws.workspace, &config, None, OutputFormat::Json).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("1个进程恢复失败"));
+        assert!(ws.events("web").iter().any(|kind| matches!(kind, EventKind::LimitExceeded { .. })));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_keeps_process_without_auto_restart_down() -> Result<()> {
+        let ws = TestWorkspace::new();
+        let mut process = Fixture::Sleep.process_config("web");
+        process.auto_restart = true;
+        let config = ws.write_config(vec![process])?;
+
+        // 以 --daemon 启动但没有开启自动重启, 以启动时记录的设置为准
+        let state = ProcessState {
+            pid: Some(i32::MAX),
+            monitor_pid: Some(i32::MAX),
+            program: "sh".into(),
+            working_dir: ws.working_dir("web"),
+            auto_restart: Some(false),
+            daemon: Some(true),
+            ..Default::default()
+        };
+        ws.workspace.ensure_process_dirs("web")?;
+        state.save(&ws.workspace, "web")?;
+
+        assert_eq!(recover_process(&ws.workspace, &config, "web").await?, Recovery::Stopped);
+        let state = ws.state("web");
+        assert_eq!((state.pid, state.monitor_pid), (None, None));
+        assert_eq!(state.restart_count, 0);
+        assert!(!ws.events("web").iter().any(|kind| matches!(kind, EventKind::Started { .. })));
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::Duration;
+use anyhow::{Result, Context};
+use log::{info, warn};
+use serde_json::json;
+use crate::events::{self, EventKind, ProcessEvent};
+use crate::handoff::Handoff;
+use crate::logger::Logger;
+use crate::notify::Notifier;
+use crate::output::{self, OutputFormat};
+use crate::process::ProcessManager;
+use crate::process::backend;
+use crate::process::daemon::ProcessManagerDaemonExt;
+use crate::state::ProcessState;
+use crate::workspace::Workspace;
+
+/// 让正在运行的监控进程exec为当前程序, 被监控的进程不重启
+///
+/// 不指定进程名时升级工作区中所有带监控进程的进程
+pub async fn handle_self_reexec(
+    workspace: &Workspace,
+    name: Option<String>,
+    timeout: Duration,
+    output: OutputFormat,
+) -> Result<()> {
+    if cfg!(not(unix)) {
+        anyhow::bail!("self-reexec 只支持Unix系统");
+    }
+
+    let names = match name {
+        Some(name) => vec![name],
+        None => {
+            let mut names = Vec::new();
+            let processes_dir = workspace.get_processes_dir();
+            if processes_dir.exists() {
+                for entry in std::fs::read_dir(processes_dir)? {
+                    let path = entry?.path();
+                    if path.is_dir() {
+                        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                            names.push(name.to_string());
+                        }
+                    }
+                }
+            }
+            names.sort();
+            names
+        }
+    };
+
+    let exe = std::env::current_exe().context("无法获取当前程序路径")?;
+    let mut results = Vec::new();
+    let mut failed = 0;
+    for name in names {
+        match reexec_monitor(workspace, &name, &exe, timeout).await {
+            Ok(Some(pid)) => {
+                info!("进程 {} 的监控进程已升级, PID: {}", name, pid);
+                if !output.is_json() {
+                    println!("进程 {} 的监控进程已升级, PID: {}", name, pid);
+                }
+                results.push(json!({ "process": name, "status": "reexecuted", "monitor_pid": pid }));
+            }
+            Ok(None) => {
+                info!("进程 {} 没有运行中的监控进程, 跳过", name);
+                results.push(json!({ "process": name, "status": "skipped" }));
+            }
+            Err(e) => {
+                warn!("升级进程 {} 的监控进程失败: {:#}", name, e);
+                if !output.is_json() {
+                    println!("进程 {} 的监控进程升级失败: {:#}", name, e);
+                }
+                failed += 1;
+                results.push(json!({ "process": name, "status": "failed", "error": format!("{:#}", e) }));
+            }
+        }
+    }
+
+    if output.is_json() {
+        output::print_result("self-reexec", json!({ "processes": results }));
+    }
+    if failed > 0 {
+        anyhow::bail!("{}个监控进程升级失败", failed);
+    }
+    Ok(())
+}
+
+/// 向进程的监控进程发送升级请求并等待新程序接管, 没有运行中的监控进程时返回None
+#[cfg(unix)]
+async fn reexec_monitor(
+    workspace: &Workspace,
+    name: &str,
+    exe: &std::path::Path,
+    timeout: Duration,
+) -> Result<Option<i32>> {
+    use nix::sys::signal::{kill, Signal};
+    use nix::unistd::Pid;
+
+    let Ok(state) = ProcessState::load(workspace, name) else {
+        return Ok(None);
+    };
+    let Some(monitor_pid) = state.monitor_pid else {
+        return Ok(None);
+    };
+    let alive = backend::native().exists(monitor_pid)
+        && state.monitor_identity.as_ref().is_none_or(|identity| identity.verify(monitor_pid));
+    if !alive {
+        return Ok(None);
+    }
+    if !crate::handoff::handles_upgrade_signal(monitor_pid) {
+        anyhow::bail!("监控进程 {} 不支持在线升级(由旧版本启动或正在升级), 请使用restart", monitor_pid);
+    }
+
+    let since = chrono::Local::now();
+    Handoff::request(workspace, name, exe)?;
+    info!("向监控进程 {} 发送升级信号", monitor_pid);
+    kill(Pid::from_raw(monitor_pid), Signal::SIGUSR2).context("发送升级信号失败")?;
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let reexecuted = ProcessEvent::list(workspace, name, Some(since))?
+            .iter()
+            .any(|event| matches!(event.kind, EventKind::Reexecuted { monitor_pid: pid } if pid == monitor_pid));
+        if reexecuted {
+            return Ok(Some(monitor_pid));
+        }
+        if !backend::native().exists(monitor_pid) {
+            anyhow::bail!("监控进程 {} 在升级过程中退出", monitor_pid);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            let _ = std::fs::remove_file(Handoff::request_path(workspace, name));
+            anyhow::bail!("等待监控进程 {} 升级超时({:?}), 详见系统日志", monitor_pid, timeout);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[cfg(not(unix))]
+async fn reexec_monitor(
+    _workspace: &Workspace,
+    _name: &str,
+    _exe: &std::path::Path,
+    _timeout: Duration,
+) -> Result<Option<i32>> {
+    anyhow::bail!("self-reexec 只支持Unix系统")
+}
+
+/// exec后由新程序执行: 读取交接文件, 接管进程并继续监控
+pub async fn handle_resume(workspace: &Workspace, process_name: String, logger: Logger) -> Result<()> {
+    let handoff = Handoff::take(workspace, &process_name)?;
+    // exec后收割者属性仍然保留, 但回收僵尸进程的任务需要重新启动
+    #[cfg(target_os = "linux")]
+    crate::process::subreaper::start();
+    info!("接管进程 {} 的监控, PID: {}", process_name, handoff.pid);
+
+    let mut manager = ProcessManager::with_config(workspace, process_name.clone(), handoff.config.clone());
+    manager.set_auto_restart(handoff.auto_restart);
+    manager.max_restarts = handoff.max_restarts;
+    manager.set_hooks(handoff.hooks.clone());
+    manager.set_notifier(Notifier::new(handoff.notifications.clone()));
+    manager.set_env_vars(handoff.env_vars.clone());
+    manager.set_pid_file(handoff.pid_file.clone());
+    manager.set_limits(handoff.limits.clone());
+    manager.set_sandbox(handoff.sandbox.clone());
+    logger.attach_process_sinks(&process_name, &handoff.log_sinks);
+    manager.set_log_sinks(handoff.log_sinks.clone());
+    #[cfg(unix)]
+    {
+        // SAFETY: 套接字由旧程序在exec前保留, 只在这里接管一次
+        let sockets = unsafe { crate::sockets::ListenSockets::from_raw_fds(&handoff.socket_fds) };
+        manager.listen_sockets = std::sync::Arc::new(sockets);
+    }
+
+    // exec不改变PID, 但命令行已变化, 重新记录监控进程身份
+    let monitor_pid = std::process::id() as i32;
+    manager.state = ProcessState::update(workspace, &process_name, |state| {
+        state.set_monitor_pid(monitor_pid);
+    })?;
+    events::record(workspace, &process_name, EventKind::Reexecuted { monitor_pid });
+
+    manager.resume_monitoring(handoff).await
+}
@@ -0,0 +1,41 @@
+use anyhow::Result;
+use log::info;
+use crate::commands::{handle_start, handle_stop};
+use crate::commands::proxy;
+use crate::commands::run::start_transient;
+use crate::config::Config;
+use crate::error::Failure;
+use crate::workspace::Workspace;
+
+/// 停止并重新启动进程, 同时支持配置文件中的进程和run命令启动的临时进程
+///
+/// 配置了proxy的进程使用蓝绿切换, 新实例健康后才停止旧实例
+pub async fn handle_restart(
+    workspace: &Workspace,
+    config: &Config,
+    name: String,
+    daemon: bool,
+    auto_restart: bool,
+) -> Result<()> {
+    let Some(definition) = config.resolve_process_config(workspace, &name) else {
+        return Err(Failure::NotFound(format!("未找到进程配置: {}", name)).into());
+    };
+
+    if definition.proxy.is_some() {
+        info!("正在切换进程: {}", name);
+        proxy::restart_proxied(workspace, config, &definition, daemon, auto_restart).await?;
+        return Ok(());
+    }
+
+    if workspace.get_process_state_file(&name).exists() {
+        info!("正在停止进程: {}", name);
+        handle_stop(workspace, config, Some(name.clone())).await?;
+    }
+
+    info!("正在重新启动进程: {}", name);
+    if config.processes.contains_key(&name) {
+        handle_start(workspace, config, Some(name), true, daemon, auto_restart).await
+    } else {
+        start_transient(workspace, config, &definition, daemon, auto_restart).await
+    }
+}
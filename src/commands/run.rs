@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{Result, Context};
+use clap::ValueEnum;
+use log::info;
+use crate::commands::add::{self, NewProcess};
+use crate::config::{Config, ProcessConfig};
+use crate::error::Failure;
+use crate::notify::Notifier;
+use crate::process::ProcessManager;
+use crate::state::ProcessState;
+use crate::workspace::Workspace;
+
+/// 临时进程的重启策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum RestartPolicy {
+    /// 不自动重启
+    #[default]
+    No,
+    /// 异常退出时自动重启(以守护进程方式运行)
+    OnFailure,
+}
+
+/// run命令的参数
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub name: String,
+    pub command: Vec<String>,
+    pub restart: RestartPolicy,
+    pub health: Option<String>,
+    pub port: Option<u16>,
+    /// KEY=VALUE形式的环境变量
+    pub env: Vec<String>,
+    pub daemon: bool,
+    /// 启动成功后写入配置文件
+    pub save: bool,
+}
+
+/// 不需要配置文件, 直接运行并托管一个命令
+///
+/// 进程定义保存在状态文件中, list/logs/stop/restart 都能像配置文件中的进程一样使用
+pub async fn handle_run(
+    workspace: &Workspace,
+    config: &Config,
+    config_path: &Path,
+    options: RunOptions,
+) -> Result<ProcessConfig> {
+    let (program, args) = options.command.split_first()
+        .ok_or_else(|| Failure::ConfigInvalid("缺少要运行的命令, 请写在 -- 之后".into()))?;
+
+    let mut env = HashMap::new();
+    for item in &options.env {
+        let (key, value) = item.split_once('=')
+            .ok_or_else(|| Failure::ConfigInvalid(format!("环境变量格式无效: {}, 应为 KEY=VALUE", item)))?;
+        env.insert(key.to_string(), value.to_string());
+    }
+
+    let process = NewProcess {
+        name: options.name.clone(),
+        program: program.clone(),
+        args: args.to_vec(),
+        port: options.port,
+        health_check_url: options.health.clone(),
+        auto_restart: options.restart == RestartPolicy::OnFailure,
+        working_dir: Some(std::env::current_dir().context("无法获取当前目录")?),
+        env,
+    };
+    if config.processes.contains_key(&process.name) {
+        return Err(Failure::ConfigInvalid(format!(
+            "进程 {} 已在配置文件中定义, 请使用 fuckrun start -n {}",
+            process.name, process.name
+        )).into());
+    }
+    let definition = process.validate(config)?;
+
+    // 启动失败时也保存定义, 以便通过 restart 重试; 同名进程已在运行时保留它原来的定义
+    let started = start_transient(workspace, config, &definition, options.daemon, false).await;
+    let already_running = started.as_ref().err()
+        .and_then(Failure::find)
+        .is_some_and(|failure| matches!(failure, Failure::AlreadyRunning(_)));
+    if !already_running {
+        ProcessState::update(workspace, &definition.name, |state| {
+            state.transient = Some(definition.clone());
+        })?;
+    }
+    started?;
+
+    if options.save {
+        add::append_process(config_path, config.clone(), &process)?;
+        ProcessState::update(workspace, &definition.name, |state| state.transient = None)?;
+        info!("已将进程 {} 保存到配置文件 {:?}", definition.name, config_path);
+    }
+    Ok(definition)
+}
+
+/// 按临时进程定义启动进程
+///
+/// 自动重启依赖守护进程中的监控循环, 定义或参数要求自动重启时总是以守护进程方式运行
+pub(crate) async fn start_transient(
+    workspace: &Workspace,
+    config: &Config,
+    definition: &ProcessConfig,
+    daemon: bool,
+    auto_restart: bool,
+) -> Result<()> {
+    let auto_restart = auto_restart || definition.auto_restart;
+
+    let mut env = definition.env.clone();
+    for (key, value) in &config.global.env {
+        env.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+
+    let mut manager = ProcessManager::with_config(
+        workspace,
+        definition.name.clone(),
+        definition.process.clone(),
+    );
+    manager.set_auto_restart(auto_restart);
+    manager.set_max_restarts(definition.max_restarts);
+    manager.set_hooks(definition.hooks.clone());
+    manager.set_notifier(Notifier::new(config.global.notifications.clone()));
+    manager.set_daemon_mode(daemon || auto_restart);
+    manager.set_log_sinks(definition.log.sinks.clone());
+    manager.set_runtime(definition.runtime());
+    manager.set_sockets(definition.sockets.clone());
+    manager.set_pid_file(definition.pid_file.clone());
+    manager.set_limits(definition.limits.clone());
+    manager.set_sandbox(definition.sandbox.clone());
+
+    info!("启动临时进程: {}", definition.name);
+    manager.start(
+        &definition.program,
+        &definition.args,
+        &definition.working_dir,
+        definition.health_check_url.as_deref(),
+        Some(&env),
+    ).await
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::commands::{handle_restart, handle_stop};
+    use crate::process::backend;
+    use crate::test_support::{Fixture, TestWorkspace};
+
+    fn options(name: &str, fixture: &Fixture) -> RunOptions {
+        let mut command = vec![fixture.program().to_string()];
+        command.extend(fixture.args());
+        RunOptions {
+            name: name.into(),
+            command,
+            env: vec!["GREETING=hello".into()],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_transient_stop_restart_and_save() -> Result<()> {
+        let ws = TestWorkspace::new();
+        let config = Config::default();
+        let config_path = ws.root().join("fuckrun.yaml");
+
+        let definition = handle_run(&ws.workspace, &config, &config_path, options("adhoc", &Fixture::Sleep)).await?;
+        assert_eq!(definition.env["GREETING"], "hello");
+        let state = ws.state("adhoc");
+        assert_eq!(state.transient.as_ref().map(|t| t.program.as_str()), Some("sh"));
+        let first = state.pid.unwrap();
+        assert!(backend::native().exists(first));
+
+        // 同名进程已在运行时失败, 不替换它的定义
+        let other = RunOptions { command: vec!["sh".into(), "-c".into(), "exit 0".into()], ..options("adhoc", &Fixture::Sleep) };
+        let err = handle_run(&ws.workspace, &config, &config_path, other).await.unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 4);
+        assert_eq!(ws.state("adhoc").transient.as_ref().map(|t| t.args.clone()), Some(Fixture::Sleep.args()));
+
+        // 配置文件中没有该进程时使用状态中的定义
+        handle_restart(&ws.workspace, &config, "adhoc".into(), false, false).await?;
+        let second = ws.state("adhoc").pid.unwrap();
+        assert_ne!(first, second);
+        assert!(ws.state("adhoc").transient.is_some());
+
+        handle_stop(&ws.workspace, &config, Some("adhoc".into())).await?;
+        assert!(!backend::native().exists(second));
+
+        // 与配置文件中的进程重名时拒绝
+        let config = ws.write_config(vec![Fixture::Sleep.process_config("web")])?;
+        let err = handle_run(&ws.workspace, &config, &config_path, options("web", &Fixture::Sleep)).await.unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 6);
+
+        // --save 写入配置文件后不再是临时进程
+        let saved = RunOptions { save: true, ..options("kept", &Fixture::Sleep) };
+        handle_run(&ws.workspace, &Config::default(), &config_path, saved).await?;
+        assert!(ws.state("kept").transient.is_none());
+        let written = Config::from_file(&config_path)?;
+        assert_eq!(written.processes["kept"].env["GREETING"], "hello");
+        handle_stop(&ws.workspace, &written, Some("kept".into())).await?;
+        Ok(())
+    }
+}
@@ -1,104 +1,130 @@
 use anyhow::Result;
 use log::info;
 use crate::process::ProcessManager;
-use crate::config::Config;
+use crate::config::{Config, ProcessConfig};
 use crate::workspace::Workspace;
+use crate::error::Failure;
+use crate::notify::Notifier;
+
+/// 不指定进程名称时启动的进程
+pub const DEFAULT_PROCESS: &str = "default";
+
+/// 命令指定的进程名称, 不指定时使用配置中名为default的进程
+pub fn process_name_or_default(config: &Config, name: Option<String>) -> Result<String> {
+    match name {
+        Some(name) => Ok(name),
+        None if config.processes.contains_key(DEFAULT_PROCESS) => Ok(DEFAULT_PROCESS.to_string()),
+        None => anyhow::bail!(Failure::NotFound(format!(
+            "未指定进程名称, 配置中也没有名为{}的进程", DEFAULT_PROCESS
+        ))),
+    }
+}
 
 pub async fn handle_start(
     workspace: &Workspace,
     config: &Config,
     name: Option<String>,
-    python: Option<String>,
-    port: Option<u16>,
     detach: bool,
     daemon: bool,
     auto_restart: bool,
 ) -> Result<()> {
-    if let Some(name) = name {
-        info!("启动进程: {}", name);
-        // 从配置文件获取进程配置
-        match config.get_process_config(&name) {
-            Some(mut process_config) => {
-                // 检查Python解释器
-                if cfg!(windows) && process_config.program.contains("python.exe") {
-                    info!("检测到python.exe，使用py启动器替代");
-                    process_config.program = String::from("py");
-                }
-
-                let mut manager = ProcessManager::with_config(
-                    workspace,
-                    name.clone(),
-                    process_config.process.clone()
-                );
-
-                // 设置自动重启
-                manager.set_auto_restart(auto_restart);
-                
-                // 设置守护进程模式
-                manager.set_daemon_mode(daemon);
-
-                // 启动进程
-                let working_dir = workspace.get_app_dir()
-                    .join(&name);
-             
-                manager.start(
-                    &process_config.program,
-                    &process_config.args,
-                    &working_dir,
-                    process_config.health_check_url.as_deref(),
-                    Some(&process_config.env),
-                ).await?;
-
-                if detach {
-                    info!("进程已启动，主进程即将退出");
-                    return Ok(());
-                }
-
-                info!("进程启动成功");
-            }
-            None => {
-                anyhow::bail!("未找到进程配置: {}", name);
+    let name = process_name_or_default(config, name)?;
+    info!("启动进程: {}", name);
+    // 从配置文件获取进程配置
+    match config.get_process_config(&name) {
+        Some(process_config) if process_config.proxy.is_some() => {
+            crate::commands::proxy::start_proxied(workspace, config, &process_config, daemon, auto_restart).await?;
+        }
+        Some(process_config) => {
+            start_process(workspace, config, &name, &process_config, daemon, auto_restart).await?;
+
+            if detach {
+                info!("进程已启动，主进程即将退出");
+                return Ok(());
             }
+
+            info!("进程启动成功");
         }
-    } else {
-        // 使用默认配置启动
-        info!("使用默认配置启动进程");
-        let mut manager = ProcessManager::with_config(
-            workspace,
-            "default".to_string(),
-            config.global.process.clone()
-        );
-        
-        // 设置自动重启
-        manager.set_auto_restart(auto_restart);
-        
-        // 设置守护进程模式
-        manager.set_daemon_mode(daemon);
-
-        // 启动进程
-        let program = python.unwrap_or_else(|| "python".to_string());
-        // 检查Python解释器
-        let program = if cfg!(windows) && program.contains("python.exe") {
-            String::from("py")
-        } else {
-            program
-        };
-        let args = vec!["-m".to_string(), "http.server".to_string(), port.unwrap_or(8000).to_string()];
-        
-        manager.start(
-            &program,
-            &args,
-            &workspace.get_root_dir().to_path_buf(),
-            None,
-            None,
-        ).await?;
-
-        if detach {
-            info!("进程已启动，主进程即将退出");
-            return Ok(());
+        None => {
+            anyhow::bail!(Failure::NotFound(format!("未找到进程配置: {}", name)));
         }
-
-        info!("进程启动成功");
     }
     Ok(())
-} 
\ No newline at end of file
+}
+
+/// 按配置启动进程
+///
+/// 进程以 `process_config.name` 管理, 工作目录和虚拟环境使用 `name` 对应的部署目录,
+/// 蓝绿切换时两个实例共用同一个部署目录
+pub(crate) async fn start_process(
+    workspace: &Workspace,
+    config: &Config,
+    name: &str,
+    process_config: &ProcessConfig,
+    daemon: bool,
+    auto_restart: bool,
+) -> Result<()> {
+    let mut manager = ProcessManager::with_config(
+        workspace,
+        process_config.name.clone(),
+        process_config.process.clone()
+    );
+
+    // 设置自动重启
+    manager.set_auto_restart(auto_restart);
+    manager.set_max_restarts(process_config.max_restarts);
+
+    // 设置生命周期钩子和状态通知
+    manager.set_hooks(process_config.hooks.clone());
+    manager.set_notifier(Notifier::new(config.global.notifications.clone()));
+
+    // 设置守护进程模式
+    manager.set_daemon_mode(daemon);
+
+    // 设置进程日志投递目标和运行时
+    manager.set_log_sinks(process_config.log.sinks.clone());
+    manager.set_runtime(process_config.runtime());
+    manager.set_sockets(process_config.sockets.clone());
+    manager.set_pid_file(process_config.pid_file.clone());
+    manager.set_limits(process_config.limits.clone());
+    manager.set_sandbox(process_config.sandbox.clone());
+
+    // 启动进程
+    let working_dir = workspace.get_process_working_dir(name);
+
+    // 配置了虚拟环境时先创建并安装依赖
+    if let Some(venv) = process_config.venv() {
+        crate::venv::prepare(workspace, name, &working_dir, venv, false).await?;
+    }
+
+    manager.start(
+        &process_config.program,
+        &process_config.args,
+        &working_dir,
+        process_config.health_check_url.as_deref(),
+        Some(&process_config.env),
+    ).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Fixture, TestWorkspace};
+
+    #[tokio::test]
+    async fn test_start_without_name_uses_default_entry() -> Result<()> {
+        let env = TestWorkspace::new();
+        let config = env.write_config(vec![Fixture::Sleep.process_config("web")])?;
+        // 没有default进程时不再启动内置的默认程序
+        let err = handle_start(&env.workspace, &config, None, true, false, false).await.unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 3);
+        assert!(env.events("default").is_empty());
+
+        let config = env.write_config(vec![Fixture::Sleep.process_config(DEFAULT_PROCESS)])?;
+        std::fs::create_dir_all(env.workspace.get_process_working_dir(DEFAULT_PROCESS))?;
+        handle_start(&env.workspace, &config, None, true, false, false).await?;
+        let pid = env.state(DEFAULT_PROCESS).pid.expect("default进程应已启动");
+        crate::process::backend::native().kill_tree(pid, None)?;
+        Ok(())
+    }
+}
@@ -3,38 +3,63 @@ use log::info;
 use crate::process::ProcessManager;
 use crate::config::Config;
 use crate::workspace::Workspace;
+use crate::error::Failure;
+use crate::commands::start::process_name_or_default;
 
 pub async fn handle_stop(
     workspace: &Workspace,
     config: &Config,
     name: Option<String>,
 ) -> Result<()> {
-    if let Some(name) = name {
-        info!("正在停止进程: {}", name);
-        // 从配置文件获取进程配置
-        match config.get_process_config(&name) {
-            Some(process_config) => {
-                let manager = ProcessManager::with_config(
-                    workspace,
-                    name.clone(),
-                    process_config.process.clone()
-                );
-                manager.stop().await?;
-            }
-            None => {
-                anyhow::bail!("未找到进程配置: {}", name);
-            }
+    let name = process_name_or_default(config, name)?;
+    info!("正在停止进程: {}", name);
+    // 从配置文件获取进程配置
+    match config.resolve_process_config(workspace, &name) {
+        Some(process_config) if process_config.proxy.is_some() => {
+            crate::commands::proxy::stop_proxied(workspace, &process_config).await?;
+        }
+        Some(process_config) => {
+            let mut manager = ProcessManager::with_config(
+                workspace,
+                name.clone(),
+                process_config.process.clone()
+            );
+            // pre_stop/post_stop钩子使用进程的环境变量
+            manager.set_env_vars(process_config.env.clone());
+            manager.set_hooks(process_config.hooks.clone());
+            manager.stop().await?;
+        }
+        None => {
+            anyhow::bail!(Failure::NotFound(format!("未找到进程配置: {}", name)));
         }
-    } else {
-        // 使用默认配置
-        info!("停止默认进程");
-        let process_name = "default".to_string();
-        let manager = ProcessManager::with_config(
-            workspace,
-            process_name,
-            config.global.process.clone()
-        );
-        manager.stop().await?;
     }
     Ok(())
-} 
\ No newline at end of file
+} 
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::commands::start::{handle_start, DEFAULT_PROCESS};
+    use crate::commands::status::handle_status;
+    use crate::test_support::{Fixture, TestWorkspace};
+
+    #[tokio::test]
+    async fn test_stop_and_status_without_name_use_default_entry() -> Result<()> {
+        let env = TestWorkspace::new();
+        let config = env.write_config(vec![Fixture::Sleep.process_config("web")])?;
+        let err = handle_status(&env.workspace, &config, None).await.unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 3);
+        let err = handle_stop(&env.workspace, &config, None).await.unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 3);
+
+        let config = env.write_config(vec![Fixture::Sleep.process_config(DEFAULT_PROCESS)])?;
+        std::fs::create_dir_all(env.workspace.get_process_working_dir(DEFAULT_PROCESS))?;
+        handle_start(&env.workspace, &config, None, true, false, false).await?;
+        assert!(handle_status(&env.workspace, &config, None).await?);
+
+        handle_stop(&env.workspace, &config, None).await?;
+        assert!(!handle_status(&env.workspace, &config, None).await?);
+        assert_eq!(env.state(DEFAULT_PROCESS).pid, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::Path;
+use anyhow::Result;
+use serde_json::json;
+use crate::config::Config;
+use crate::output::{self, OutputFormat};
+use crate::workspace::{Workspace, WorkspaceSource};
+
+pub fn handle_workspace_info(
+    workspace: &Workspace,
+    source: &WorkspaceSource,
+    config: Option<&Path>,
+    output: OutputFormat,
+) -> Result<()> {
+    let config_path = match config {
+        Some(path) => path.to_path_buf(),
+        None => Config::find_config_file(None, workspace),
+    };
+    let initialized = workspace.get_fuckrun_dir().is_dir();
+
+    if output.is_json() {
+        output::print_result("workspace", json!({
+            "root": workspace.get_root_dir(),
+            "source": source.kind(),
+            "reason": source.reason(),
+            "fuckrun_dir": workspace.get_fuckrun_dir(),
+            "app_dir": workspace.get_app_dir(),
+            "config": config_path,
+            "config_exists": config_path.exists(),
+            "initialized": initialized,
+        }));
+        return Ok(());
+    }
+
+    println!("工作区根目录: {}", workspace.get_root_dir().display());
+    println!("选择原因: {}", source.reason());
+    println!("FuckRun目录: {}{}", workspace.get_fuckrun_dir().display(), if initialized { "" } else { " (尚未创建)" });
+    println!("应用目录: {}", workspace.get_app_dir().display());
+    println!("配置文件: {}{}", config_path.display(), if config_path.exists() { "" } else { " (不存在)" });
+    Ok(())
+}
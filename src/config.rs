@@ -4,6 +4,17 @@ use anyhow::{Result, Context};
 use std::fs;
 use std::collections::HashMap;
 use crate::types::{ProcessConfig as TypesProcessConfig, FsConfig, StateConfig};
+use crate::sinks::SinkConfig;
+use crate::hooks::HooksConfig;
+use crate::notify::NotificationsConfig;
+use crate::metrics::MetricsConfig;
+use crate::error::Failure;
+use crate::runtime::RuntimeConfig;
+use crate::venv::{PythonConfig, VenvConfig};
+use crate::proxy::ProxyConfig;
+use crate::sockets::SocketConfig;
+use crate::process::cgroup::LimitsConfig;
+use crate::process::sandbox::SandboxConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessConfig {
@@ -40,6 +51,77 @@ pub struct ProcessConfig {
     /// 进程管理配置
     #[serde(default)]
     pub process: TypesProcessConfig,
+    /// 生命周期钩子
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    /// 运行时, 不设置时根据program推断
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<RuntimeConfig>,
+    /// Python虚拟环境和依赖管理
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python: Option<PythonConfig>,
+    /// 蓝绿切换代理, 设置后通过内置TCP代理对外提供服务
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+    /// 由FuckRun预先绑定并通过 `LISTEN_FDS` 传给进程的监听套接字
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sockets: Vec<SocketConfig>,
+    /// 程序自己fork到后台时写入真实PID的文件(相对于工作目录), 设置后监控和停止该PID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid_file: Option<PathBuf>,
+    /// 通过cgroup v2限制整个进程树的资源上限
+    #[serde(default, skip_serializing_if = "LimitsConfig::is_empty")]
+    pub limits: LimitsConfig,
+    /// 用Linux命名空间隔离进程, 适合运行不受信任的脚本
+    #[serde(default, skip_serializing_if = "SandboxConfig::is_empty")]
+    pub sandbox: SandboxConfig,
+}
+
+impl ProcessConfig {
+    /// 进程使用的运行时, 没有配置时根据program推断
+    ///
+    /// 配置了 `python.venv` 时使用该虚拟环境
+    pub fn runtime(&self) -> RuntimeConfig {
+        let venv = self.venv().map(|venv| venv.path.clone());
+        match (self.runtime.clone(), venv) {
+            (Some(RuntimeConfig::Python { venv: None }) | None, Some(venv)) => RuntimeConfig::Python { venv: Some(venv) },
+            (Some(runtime), _) => runtime,
+            (None, None) => RuntimeConfig::infer(&self.program),
+        }
+    }
+
+    /// 需要自动管理的虚拟环境
+    pub fn venv(&self) -> Option<&VenvConfig> {
+        self.python.as_ref()?.venv.as_ref()
+    }
+}
+
+impl Default for ProcessConfig {
+    /// 与配置文件中省略各字段时的默认值相同
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            program: String::new(),
+            args: Vec::new(),
+            working_dir: default_working_dir(),
+            env: HashMap::new(),
+            auto_restart: false,
+            start_delay: 0,
+            max_restarts: default_max_restarts(),
+            depends_on: Vec::new(),
+            health_check_url: None,
+            log: LogConfig::default(),
+            process: TypesProcessConfig::default(),
+            hooks: HooksConfig::default(),
+            runtime: None,
+            python: None,
+            proxy: None,
+            sockets: Vec::new(),
+            pid_file: None,
+            limits: LimitsConfig::default(),
+            sandbox: SandboxConfig::default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -55,6 +137,9 @@ pub struct LogConfig {
     /// 保留的日志文件数量
     #[serde(default = "default_max_files")]
     pub max_files: u32,
+    /// 日志投递目标(syslog/journald/tcp/http)
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -85,6 +170,12 @@ pub struct GlobalConfig {
     /// 进程管理配置
     #[serde(default)]
     pub process: TypesProcessConfig,
+    /// 状态变化通知
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    /// Prometheus指标导出
+    #[serde(default)]
+    pub metrics: MetricsConfig,
 }
 
 impl Config {
@@ -109,7 +200,7 @@ impl Config {
                     }
                 }
             }
-            return Err(anyhow::anyhow!("配置文件不存在: {}", path.display()));
+            return Err(Failure::ConfigInvalid(format!("配置文件不存在: {}", path.display())).into());
         }
         
         log::info!("配置文件存在，准备读取");
@@ -117,9 +208,11 @@ impl Config {
             .context("读取配置文件失败")?;
         
         let config = if path.extension().map_or(false, |ext| ext == "json") {
-            serde_json::from_str(&content).context("解析JSON配置文件失败")?
+            serde_json::from_str(&content)
+                .context(Failure::ConfigInvalid("解析JSON配置文件失败".into()))?
         } else {
-            serde_yaml::from_str(&content).context("解析YAML配置文件失败")?
+            serde_yaml::from_str(&content)
+                .context(Failure::ConfigInvalid("解析YAML配置文件失败".into()))?
         };
 
         Ok(config)
@@ -171,6 +264,13 @@ impl Config {
         Some(config)
     }
 
+    /// 获取进程配置, 配置文件中没有时使用run命令保存在状态中的临时定义
+    pub fn resolve_process_config(&self, workspace: &crate::workspace::Workspace, name: &str) -> Option<ProcessConfig> {
+        self.get_process_config(name).or_else(|| {
+            crate::state::ProcessState::load(workspace, name).ok()?.transient
+        })
+    }
+
     /// 查找配置文件
     pub fn find_config_file(process_name: Option<&str>, workspace: &crate::workspace::Workspace) -> PathBuf {
         if let Some(name) = process_name {
@@ -190,7 +290,11 @@ impl Config {
             }
         }
 
-        // 3. 使用根目录配置
+        // 3. 使用根目录配置, fuckrun.yaml优先
+        let marker = workspace.get_root_dir().join(crate::workspace::MARKER_FILE);
+        if marker.exists() {
+            return marker;
+        }
         workspace.get_root_dir().join("config.yaml")
     }
 
@@ -240,10 +344,13 @@ mod tests {
                     level: "debug".to_string(),
                     max_size: 200,
                     max_files: 10,
+                    sinks: vec![],
                 },
                 fs: FsConfig::default(),
                 state: StateConfig::default(),
                 process: TypesProcessConfig::default(),
+                notifications: NotificationsConfig::default(),
+                metrics: MetricsConfig::default(),
             },
             processes: {
                 let mut map = HashMap::new();
@@ -251,20 +358,16 @@ mod tests {
                     name: "web".to_string(),
                     program: "python".to_string(),
                     args: vec!["app.py".to_string()],
-                    working_dir: PathBuf::from("."),
-                    env: HashMap::new(),
                     auto_restart: true,
-                    start_delay: 0,
-                    max_restarts: 3,
-                    depends_on: vec![],
                     health_check_url: Some("http://localhost:8000/health".to_string()),
                     log: LogConfig {
                         file: None,
                         level: "info".to_string(),
                         max_size: 100,
                         max_files: 5,
+                        sinks: vec![],
                     },
-                    process: TypesProcessConfig::default(),
+                    ..Default::default()
                 });
                 map
             },
@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{Result, Context};
+use chrono::{DateTime, Local};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use crate::hash::StableHasher;
+use crate::workspace::Workspace;
+
+/// 一行进程输出
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TailLine {
+    /// stdout/stderr
+    pub stream: String,
+    pub line: String,
+}
+
+/// 最近若干行进程输出的环形缓冲区
+#[derive(Debug, Clone)]
+pub struct OutputTail {
+    lines: Arc<Mutex<VecDeque<TailLine>>>,
+    capacity: usize,
+}
+
+impl OutputTail {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// 记录一行输出, 超出容量时丢弃最旧的一行
+    pub fn push(&self, stream: &str, line: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Ok(mut lines) = self.lines.lock() {
+            if lines.len() >= self.capacity {
+                lines.pop_front();
+            }
+            lines.push_back(TailLine {
+                stream: stream.to_string(),
+                line: line.to_string(),
+            });
+        }
+    }
+
+    /// 获取当前缓冲的全部输出
+    pub fn snapshot(&self) -> Vec<TailLine> {
+        self.lines.lock()
+            .map(|lines| lines.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 清空缓冲区(进程重启后使用)
+    pub fn clear(&self) {
+        if let Ok(mut lines) = self.lines.lock() {
+            lines.clear();
+        }
+    }
+}
+
+/// 进程异常退出时保存的现场记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashRecord {
+    /// 记录ID(同时是文件名)
+    pub id: String,
+    pub timestamp: DateTime<Local>,
+    pub process_name: String,
+    pub pid: Option<i32>,
+    /// 退出码
+    pub exit_code: Option<i32>,
+    /// 导致退出的信号
+    pub signal: Option<i32>,
+    /// 本次运行时长(秒)
+    pub uptime_secs: u64,
+    /// 退出时的重启次数
+    pub restart_count: u32,
+    /// 运行环境变量的哈希, 用于判断两次崩溃的环境是否一致
+    pub env_hash: String,
+    /// 退出前的最后若干行输出
+    pub output: Vec<TailLine>,
+    /// 是否因超出cgroup内存上限被OOM终止
+    #[serde(default)]
+    pub oom_killed: bool,
+}
+
+impl CrashRecord {
+    /// 根据退出状态生成崩溃记录
+    pub fn capture(
+        process_name: &str,
+        pid: Option<i32>,
+        status: &ExitStatus,
+        uptime: Duration,
+        restart_count: u32,
+        env_vars: &HashMap<String, String>,
+        tail: &OutputTail,
+    ) -> Self {
+        let timestamp = Local::now();
+        #[cfg(unix)]
+        let signal = {
+            use std::os::unix::process::ExitStatusExt;
+            status.signal()
+        };
+        #[cfg(windows)]
+        let signal = None;
+
+        Self {
+            id: format!("{}-{}", timestamp.format("%Y%m%d-%H%M%S%.3f"), pid.unwrap_or(0)),
+            timestamp,
+            process_name: process_name.to_string(),
+            pid,
+            exit_code: status.code(),
+            signal,
+            uptime_secs: uptime.as_secs(),
+            restart_count,
+            env_hash: env_hash(env_vars),
+            output: tail.snapshot(),
+            oom_killed: false,
+        }
+    }
+
+    /// 保存记录, 并只保留最近max_records条
+    pub fn save(&self, workspace: &Workspace, max_records: usize) -> Result<()> {
+        let crash_dir = workspace.get_process_crash_dir(&self.process_name);
+        fs::create_dir_all(&crash_dir).context("创建崩溃记录目录失败")?;
+
+        let path = crash_dir.join(format!("{}.json", self.id));
+        let content = serde_json::to_string_pretty(self).context("序列化崩溃记录失败")?;
+        fs::write(&path, content).context("保存崩溃记录失败")?;
+        info!("崩溃记录已保存: {:?}", path);
+
+        let records = Self::list(workspace, &self.process_name)?;
+        if records.len() > max_records {
+            for old in &records[..records.len() - max_records] {
+                let old_path = crash_dir.join(format!("{}.json", old.id));
+                if let Err(e) = fs::remove_file(&old_path) {
+                    warn!("删除旧崩溃记录失败 {:?}: {}", old_path, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 列出进程的所有崩溃记录(按时间从旧到新)
+    pub fn list(workspace: &Workspace, process_name: &str) -> Result<Vec<Self>> {
+        let crash_dir = workspace.get_process_crash_dir(process_name);
+        if !crash_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut records = Vec::new();
+        for entry in fs::read_dir(&crash_dir).context("读取崩溃记录目录失败")? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                match fs::read_to_string(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|c| serde_json::from_str::<Self>(&c).map_err(anyhow::Error::from))
+                {
+                    Ok(record) => records.push(record),
+                    Err(e) => warn!("无法解析崩溃记录 {:?}: {}", path, e),
+                }
+            }
+        }
+        records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+        Ok(records)
+    }
+
+    /// 按ID加载崩溃记录, ID支持唯一前缀
+    pub fn load(workspace: &Workspace, process_name: &str, id: &str) -> Result<Self> {
+        let records = Self::list(workspace, process_name)?;
+        let mut matches = records.into_iter().filter(|r| r.id.starts_with(id));
+        let record = matches.next()
+            .with_context(|| format!("未找到崩溃记录: {}", id))?;
+        if matches.next().is_some() {
+            anyhow::bail!("崩溃记录ID不唯一: {}", id);
+        }
+        Ok(record)
+    }
+
+    /// 退出原因描述
+    pub fn reason(&self) -> String {
+        let reason = match (self.exit_code, self.signal) {
+            (_, Some(signal)) => format!("signal {}", signal),
+            (Some(code), None) => format!("exit {}", code),
+            (None, None) => "unknown".to_string(),
+        };
+        if self.oom_killed {
+            format!("out of memory ({})", reason)
+        } else {
+            reason
+        }
+    }
+}
+
+/// 计算进程实际运行环境(继承的环境变量加上配置的环境变量)的哈希
+pub fn env_hash(env_vars: &HashMap<String, String>) -> String {
+    let mut env: BTreeMap<String, String> = std::env::vars().collect();
+    for (key, value) in env_vars {
+        env.insert(key.clone(), value.clone());
+    }
+    let mut hasher = StableHasher::new();
+    for (key, value) in &env {
+        hasher.write(key.as_bytes()).write(value.as_bytes());
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_tail_is_bounded() {
+        let tail = OutputTail::new(3);
+        for i in 0..5 {
+            tail.push("stderr", &format!("line {}", i));
+        }
+        let lines = tail.snapshot();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].line, "line 2");
+        assert_eq!(lines[2].line, "line 4");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_crash_record_roundtrip_and_retention() -> Result<()> {
+        use std::os::unix::process::ExitStatusExt;
+
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        let tail = OutputTail::new(10);
+        tail.push("stderr", "Traceback (most recent call last):");
+
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let status = ExitStatus::from_raw(1 << 8);
+            let mut record = CrashRecord::capture(
+                "web", Some(100 + i), &status, Duration::from_secs(42), i as u32, &HashMap::new(), &tail,
+            );
+            record.id = format!("crash-{}", i);
+            record.save(&workspace, 2)?;
+            ids.push(record.id);
+        }
+
+        let records = CrashRecord::list(&workspace, "web")?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "crash-1");
+
+        let record = CrashRecord::load(&workspace, "web", "crash-2")?;
+        assert_eq!(record.exit_code, Some(1));
+        assert_eq!(record.reason(), "exit 1");
+        assert_eq!(record.uptime_secs, 42);
+        assert_eq!(record.output[0].line, "Traceback (most recent call last):");
+
+        let killed = CrashRecord { signal: Some(9), oom_killed: true, ..record };
+        assert_eq!(killed.reason(), "out of memory (signal 9)");
+        Ok(())
+    }
+}
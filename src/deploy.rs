@@ -0,0 +1,274 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use log::{info, warn};
+use crate::error::Failure;
+use crate::fs::FsManager;
+use crate::workspace::Workspace;
+
+/// 支持直接解压的压缩包后缀
+const TARBALL_SUFFIXES: [&str; 6] = [".tar", ".tar.gz", ".tgz", ".tar.bz2", ".tar.xz", ".tar.zst"];
+
+/// 进程的发布版本
+///
+/// 每个版本保存在 `deployments/<名称>/releases/<版本>/`, `current` 链接指向正在使用的版本。
+/// 版本名是发布时间, 按名称排序即按发布顺序排序
+pub struct Releases {
+    deploy_dir: PathBuf,
+}
+
+impl Releases {
+    pub fn new(workspace: &Workspace, process_name: &str) -> Self {
+        Self {
+            deploy_dir: workspace.get_process_deploy_dir(process_name),
+        }
+    }
+
+    pub fn releases_dir(&self) -> PathBuf {
+        self.deploy_dir.join("releases")
+    }
+
+    pub fn current_link(&self) -> PathBuf {
+        self.deploy_dir.join("current")
+    }
+
+    /// 所有版本, 从旧到新
+    pub fn list(&self) -> Result<Vec<String>> {
+        let dir = self.releases_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut releases: Vec<String> = fs::read_dir(&dir)
+            .context("读取版本目录失败")?
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            // 以.开头的是还没准备好的临时目录
+            .filter(|name| !name.starts_with('.'))
+            .collect();
+        releases.sort();
+        Ok(releases)
+    }
+
+    /// 当前使用的版本
+    pub fn current(&self) -> Option<String> {
+        let target = fs::read_link(self.current_link()).ok()?;
+        Some(target.file_name()?.to_string_lossy().into_owned())
+    }
+
+    /// 从目录复制或从tar包解压出一个新版本, 返回版本名
+    ///
+    /// 先写入临时目录再重命名, 失败时不会留下不完整的版本
+    pub fn create(&self, source: &Path) -> Result<String> {
+        if !source.exists() {
+            return Err(Failure::NotFound(format!("发布来源不存在: {}", source.display())).into());
+        }
+        let releases_dir = self.releases_dir();
+        fs::create_dir_all(&releases_dir).context("创建版本目录失败")?;
+
+        let timestamp = chrono::Local::now().format("%Y%m%d%H%M%S").to_string();
+        let mut release = timestamp.clone();
+        let mut suffix = 1;
+        while releases_dir.join(&release).exists() {
+            release = format!("{}-{:02}", timestamp, suffix);
+            suffix += 1;
+        }
+
+        let staging = releases_dir.join(format!(".{}.tmp", release));
+        if staging.exists() {
+            fs::remove_dir_all(&staging).context("清理临时目录失败")?;
+        }
+        let result = if source.is_dir() {
+            info!("复制 {:?} 到版本 {}", source, release);
+            copy_dir(source, &staging)
+        } else if is_tarball(source) {
+            info!("解压 {:?} 到版本 {}", source, release);
+            extract_tarball(source, &staging)
+        } else {
+            Err(Failure::ConfigInvalid(format!(
+                "不支持的发布来源: {}, 需要是目录或tar包({})",
+                source.display(),
+                TARBALL_SUFFIXES.join("/")
+            )).into())
+        };
+        if let Err(e) = result {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        fs::rename(&staging, releases_dir.join(&release)).context("保存版本失败")?;
+        Ok(release)
+    }
+
+    /// 把current链接切换到指定版本
+    ///
+    /// Unix下先创建临时链接再重命名覆盖, 切换是原子的
+    pub fn activate(&self, release: &str) -> Result<()> {
+        if !self.releases_dir().join(release).is_dir() {
+            return Err(Failure::NotFound(format!("版本不存在: {}", release)).into());
+        }
+        let target = Path::new("releases").join(release);
+        let link = self.current_link();
+
+        #[cfg(unix)]
+        {
+            let staging = self.deploy_dir.join(format!(".current.{}", std::process::id()));
+            let _ = fs::remove_file(&staging);
+            std::os::unix::fs::symlink(&target, &staging).context("创建版本链接失败")?;
+            fs::rename(&staging, &link).context("切换版本链接失败")?;
+        }
+
+        #[cfg(windows)]
+        {
+            if fs::symlink_metadata(&link).is_ok() {
+                fs::remove_dir(&link).context("删除旧的版本链接失败")?;
+            }
+            std::os::windows::fs::symlink_dir(&target, &link).context("创建版本链接失败")?;
+        }
+
+        info!("当前版本已切换为 {}", release);
+        Ok(())
+    }
+
+    /// 删除当前版本链接, 进程重新在部署目录中运行
+    pub fn deactivate(&self) -> Result<()> {
+        let link = self.current_link();
+        if fs::symlink_metadata(&link).is_err() {
+            return Ok(());
+        }
+        #[cfg(unix)]
+        fs::remove_file(&link).context("删除版本链接失败")?;
+        #[cfg(windows)]
+        fs::remove_dir(&link).context("删除版本链接失败")?;
+        info!("已删除当前版本链接");
+        Ok(())
+    }
+
+    /// 只保留最新的keep个版本, 当前版本总是保留, 返回删除的版本
+    pub async fn prune(&self, keep: usize, fs_manager: &FsManager) -> Result<Vec<String>> {
+        let releases = self.list()?;
+        let current = self.current();
+        let expired = releases.len().saturating_sub(keep);
+        let mut removed = Vec::new();
+        for release in releases.into_iter().take(expired) {
+            if current.as_deref() == Some(release.as_str()) {
+                continue;
+            }
+            info!("删除旧版本: {}", release);
+            if let Err(e) = fs_manager.force_remove_dir_all(self.releases_dir().join(&release)).await {
+                warn!("删除旧版本 {} 失败: {}", release, e);
+                continue;
+            }
+            removed.push(release);
+        }
+        Ok(removed)
+    }
+}
+
+fn is_tarball(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    TARBALL_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// 递归复制目录, 保留符号链接
+fn copy_dir(source: &Path, target: &Path) -> Result<()> {
+    fs::create_dir_all(target).with_context(|| format!("创建目录 {:?} 失败", target))?;
+    for entry in fs::read_dir(source).with_context(|| format!("读取目录 {:?} 失败", source))? {
+        let entry = entry?;
+        let from = entry.path();
+        let to = target.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&from, &to)?;
+        } else if file_type.is_symlink() {
+            let link = fs::read_link(&from)?;
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&link, &to)
+                .with_context(|| format!("复制链接 {:?} 失败", from))?;
+            #[cfg(windows)]
+            fs::copy(&from, &to).with_context(|| format!("复制文件 {:?} 失败", from))?;
+        } else {
+            fs::copy(&from, &to).with_context(|| format!("复制文件 {:?} 失败", from))?;
+        }
+    }
+    Ok(())
+}
+
+/// 用系统的tar命令解压, 压缩包只有一个顶层目录时使用该目录的内容
+fn extract_tarball(tarball: &Path, target: &Path) -> Result<()> {
+    let unpack = target.with_extension("unpack");
+    if unpack.exists() {
+        fs::remove_dir_all(&unpack)?;
+    }
+    fs::create_dir_all(&unpack).context("创建解压目录失败")?;
+    let output = std::process::Command::new("tar")
+        .arg("-xf")
+        .arg(tarball)
+        .arg("-C")
+        .arg(&unpack)
+        .output()
+        .context("执行tar命令失败")?;
+    if !output.status.success() {
+        let _ = fs::remove_dir_all(&unpack);
+        anyhow::bail!("解压失败({}): {}", output.status, String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let entries: Vec<_> = fs::read_dir(&unpack)?.flatten().collect();
+    let root = match entries.as_slice() {
+        [single] if single.path().is_dir() => single.path(),
+        _ => unpack.clone(),
+    };
+    fs::rename(&root, target).context("保存解压内容失败")?;
+    if unpack.exists() {
+        fs::remove_dir_all(&unpack)?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_activate_and_prune() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        let releases = Releases::new(&workspace, "web");
+
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join("static"))?;
+        fs::write(source.join("app.py"), "v1")?;
+        std::os::unix::fs::symlink("app.py", source.join("main.py"))?;
+
+        let first = releases.create(&source)?;
+        releases.activate(&first)?;
+        assert_eq!(releases.current().as_deref(), Some(first.as_str()));
+        let working_dir = workspace.get_process_working_dir("web");
+        assert_eq!(fs::read_to_string(working_dir.join("main.py"))?, "v1");
+        assert!(working_dir.join("static").is_dir());
+
+        // tar包只有一个顶层目录时使用该目录的内容
+        fs::write(source.join("app.py"), "v2")?;
+        let tarball = dir.path().join("app.tar.gz");
+        let status = std::process::Command::new("tar")
+            .arg("-czf").arg(&tarball).arg("-C").arg(dir.path()).arg("src")
+            .status()?;
+        assert!(status.success());
+        let second = releases.create(&tarball)?;
+        assert!(second > first);
+        releases.activate(&second)?;
+        assert_eq!(fs::read_to_string(working_dir.join("app.py"))?, "v2");
+
+        let third = releases.create(&source)?;
+        assert_eq!(releases.list()?, vec![first.clone(), second.clone(), third.clone()]);
+
+        // 当前版本即使超出保留数量也不删除
+        releases.activate(&first)?;
+        let removed = releases.prune(1, &FsManager::default()).await?;
+        assert_eq!(removed, vec![second]);
+        assert_eq!(releases.list()?, vec![first, third]);
+
+        assert!(releases.create(&dir.path().join("missing")).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+
+/// 需要调用方区分处理的失败类型, 每种类型对应固定的退出码
+///
+/// 其他错误统一以退出码1退出, 参数错误由clap以退出码2退出
+#[derive(Debug)]
+pub enum Failure {
+    /// 进程配置、日志或记录不存在
+    NotFound(String),
+    /// 进程已在运行
+    AlreadyRunning(String),
+    /// 启动时健康检查失败
+    HealthCheckFailed(String),
+    /// 配置文件不存在或无效
+    ConfigInvalid(String),
+}
+
+impl Failure {
+    /// 失败类型名称, 用于JSON输出
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Failure::NotFound(_) => "not_found",
+            Failure::AlreadyRunning(_) => "already_running",
+            Failure::HealthCheckFailed(_) => "health_check_failed",
+            Failure::ConfigInvalid(_) => "config_invalid",
+        }
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Failure::NotFound(_) => 3,
+            Failure::AlreadyRunning(_) => 4,
+            Failure::HealthCheckFailed(_) => 5,
+            Failure::ConfigInvalid(_) => 6,
+        }
+    }
+
+    /// 在错误链中查找失败类型
+    pub fn find(err: &anyhow::Error) -> Option<&Failure> {
+        err.downcast_ref::<Failure>()
+            .or_else(|| err.chain().find_map(|e| e.downcast_ref::<Failure>()))
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::NotFound(message)
+            | Failure::AlreadyRunning(message)
+            | Failure::HealthCheckFailed(message)
+            | Failure::ConfigInvalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// 错误对应的退出码
+pub fn exit_code(err: &anyhow::Error) -> u8 {
+    Failure::find(err).map(Failure::exit_code).unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_through_context() {
+        let err = anyhow::Error::new(Failure::NotFound("未找到进程配置: web".into()))
+            .context("启动失败");
+        assert_eq!(exit_code(&err), 3);
+
+        let err = Err::<(), _>(anyhow::anyhow!("invalid type"))
+            .context(Failure::ConfigInvalid("解析YAML配置文件失败".into()))
+            .unwrap_err();
+        assert_eq!(exit_code(&err), 6);
+        assert_eq!(err.to_string(), "解析YAML配置文件失败");
+
+        assert_eq!(exit_code(&anyhow::anyhow!("其他错误")), 1);
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::process::ExitStatus;
+use anyhow::{Result, Context};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use log::warn;
+use serde::{Serialize, Deserialize};
+use crate::workspace::Workspace;
+
+/// 进程生命周期事件类型
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    /// 进程已启动
+    Started { pid: i32 },
+    /// 初始化完成(健康检查通过)
+    Ready,
+    /// 健康检查失败
+    Unhealthy { reason: String },
+    /// 进程退出
+    Exited { code: Option<i32>, signal: Option<i32> },
+    /// 进程被自动重启
+    Restarted { pid: i32, restart_count: u32 },
+    /// 自动重启时进程没能启动
+    RestartFailed { error: String },
+    /// 用户主动停止
+    Stopped,
+    /// 重启次数超过上限, 不再重启
+    LimitExceeded { restart_count: u32, max_restarts: u32 },
+    /// 切换到新发布的版本
+    Deployed { release: String },
+    /// 回滚到之前的版本
+    RolledBack { from: Option<String>, to: String },
+    /// 监控进程已exec为新程序并接管进程
+    Reexecuted { monitor_pid: i32 },
+    /// 原监控进程退出后, 新的监控进程接管了仍在运行的进程
+    Adopted { pid: i32, monitor_pid: i32 },
+    /// 启动的程序fork后退出, 改为监控它留下的后台进程
+    Daemonized { launcher: i32, pid: i32 },
+    /// 进程树超出内存上限, 被内核OOM终止
+    OomKilled { memory_max: Option<u64> },
+}
+
+impl EventKind {
+    /// 根据退出状态生成退出事件
+    pub fn exited(status: &ExitStatus) -> Self {
+        #[cfg(unix)]
+        let signal = {
+            use std::os::unix::process::ExitStatusExt;
+            status.signal()
+        };
+        #[cfg(windows)]
+        let signal = None;
+        EventKind::Exited { code: status.code(), signal }
+    }
+
+    /// 事件名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Started { .. } => "started",
+            EventKind::Ready => "ready",
+            EventKind::Unhealthy { .. } => "unhealthy",
+            EventKind::Exited { .. } => "exited",
+            EventKind::Restarted { .. } => "restarted",
+            EventKind::RestartFailed { .. } => "restart_failed",
+            EventKind::Stopped => "stopped",
+            EventKind::LimitExceeded { .. } => "limit_exceeded",
+            EventKind::Deployed { .. } => "deployed",
+            EventKind::RolledBack { .. } => "rolled_back",
+            EventKind::Reexecuted { .. } => "reexecuted",
+            EventKind::Adopted { .. } => "adopted",
+            EventKind::Daemonized { .. } => "daemonized",
+            EventKind::OomKilled { .. } => "oom_killed",
+        }
+    }
+
+    /// 事件详情
+    pub fn detail(&self) -> String {
+        match self {
+            EventKind::Started { pid } => format!("pid {}", pid),
+            EventKind::Ready | EventKind::Stopped => String::new(),
+            EventKind::Unhealthy { reason } => reason.clone(),
+            EventKind::Exited { code, signal } => match (code, signal) {
+                (_, Some(signal)) => format!("signal {}", signal),
+                (Some(code), None) => format!("exit {}", code),
+                (None, None) => "unknown".to_string(),
+            },
+            EventKind::Restarted { pid, restart_count } => {
+                format!("pid {}, restart #{}", pid, restart_count)
+            }
+            EventKind::RestartFailed { error } => error.clone(),
+            EventKind::LimitExceeded { restart_count, max_restarts } => {
+                format!("{}/{} restarts", restart_count, max_restarts)
+            }
+            EventKind::Deployed { release } => format!("release {}", release),
+            EventKind::RolledBack { from, to } => match from {
+                Some(from) => format!("{} -> {}", from, to),
+                None => format!("-> {}", to),
+            },
+            EventKind::Reexecuted { monitor_pid } => format!("monitor pid {}", monitor_pid),
+            EventKind::Adopted { pid, monitor_pid } => format!("pid {}, monitor pid {}", pid, monitor_pid),
+            EventKind::Daemonized { launcher, pid } => format!("pid {}, launcher pid {}", pid, launcher),
+            EventKind::OomKilled { memory_max } => match memory_max {
+                Some(bytes) => format!("memory.max {}MB", bytes / 1024 / 1024),
+                None => String::new(),
+            },
+        }
+    }
+
+    /// 是否为一次故障(异常退出)
+    pub fn is_failure(&self) -> bool {
+        matches!(self, EventKind::Exited { code, signal } if signal.is_some() || *code != Some(0))
+    }
+
+    /// 是否开始了一次新的运行
+    fn is_start(&self) -> bool {
+        matches!(self, EventKind::Started { .. } | EventKind::Restarted { .. })
+    }
+}
+
+/// 进程生命周期事件, 以一行一个JSON的形式追加到processes/<name>/events.jsonl
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessEvent {
+    pub timestamp: DateTime<Local>,
+    pub process_name: String,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl ProcessEvent {
+    pub fn new(process_name: &str, kind: EventKind) -> Self {
+        Self {
+            timestamp: Local::now(),
+            process_name: process_name.to_string(),
+            kind,
+        }
+    }
+
+    /// 追加事件到进程的事件日志
+    pub fn append(&self, workspace: &Workspace) -> Result<()> {
+        workspace.ensure_process_dirs(&self.process_name)?;
+        let mut line = serde_json::to_string(self).context("序列化事件失败")?;
+        line.push('\n');
+
+        // 以追加模式单次写入整行, 多个进程同时写入也不会交错
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(workspace.get_process_events_file(&self.process_name))
+            .context("打开事件日志失败")?;
+        file.write_all(line.as_bytes()).context("写入事件日志失败")?;
+        Ok(())
+    }
+
+    /// 读取进程的事件, 可选只返回since之后的事件
+    pub fn list(
+        workspace: &Workspace,
+        process_name: &str,
+        since: Option<DateTime<Local>>,
+    ) -> Result<Vec<Self>> {
+        let path = workspace.get_process_events_file(process_name);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = fs::File::open(&path).context("打开事件日志失败")?;
+        let mut events = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.context("读取事件日志失败")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Self>(&line) {
+                Ok(event) => {
+                    if since.is_none_or(|since| event.timestamp >= since) {
+                        events.push(event);
+                    }
+                }
+                Err(e) => warn!("跳过无法解析的事件: {}", e),
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// 记录一个事件, 失败时只打印警告, 不影响进程管理流程
+pub fn record(workspace: &Workspace, process_name: &str, kind: EventKind) {
+    if let Err(e) = ProcessEvent::new(process_name, kind).append(workspace) {
+        warn!("记录进程事件失败: {}", e);
+    }
+}
+
+/// 根据事件历史计算的运行统计
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventStats {
+    /// 最近一次启动(含自动重启)的时间
+    pub last_start: Option<DateTime<Local>>,
+    /// 当前这次运行的时长(秒), 进程未运行时为None
+    pub uptime_secs: Option<u64>,
+    /// 平均故障间隔(秒): 累计运行时长 / 故障次数, 没有故障时为None
+    pub mtbf_secs: Option<u64>,
+    /// 故障次数
+    pub failures: u32,
+}
+
+impl EventStats {
+    pub fn from_events(events: &[ProcessEvent], now: DateTime<Local>) -> Self {
+        let mut stats = Self::default();
+        let mut running_since: Option<DateTime<Local>> = None;
+        let mut total_run = Duration::zero();
+
+        for event in events {
+            if event.kind.is_start() {
+                // 上一次运行没有记录退出事件时, 以新的启动时间作为结束
+                if let Some(start) = running_since {
+                    total_run += event.timestamp - start;
+                }
+                running_since = Some(event.timestamp);
+                stats.last_start = Some(event.timestamp);
+                continue;
+            }
+
+            if event.kind.is_failure() {
+                stats.failures += 1;
+            }
+            if matches!(event.kind, EventKind::Exited { .. } | EventKind::Stopped) {
+                if let Some(start) = running_since.take() {
+                    total_run += event.timestamp - start;
+                }
+            }
+        }
+
+        if let Some(start) = running_since {
+            let current = now - start;
+            total_run += current;
+            stats.uptime_secs = Some(current.num_seconds().max(0) as u64);
+        }
+        if stats.failures > 0 {
+            stats.mtbf_secs = Some(total_run.num_seconds().max(0) as u64 / stats.failures as u64);
+        }
+        stats
+    }
+}
+
+/// 解析--since参数
+///
+/// 支持相对时长(30s/10m/2h/7d)、日期(2024-01-01)、本地时间(2024-01-01 12:00:00)和RFC 3339格式
+pub fn parse_since(value: &str) -> Result<DateTime<Local>> {
+    let value = value.trim();
+    if let Some(unit) = value.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        if let Ok(amount) = value[..value.len() - 1].parse::<i64>() {
+            let duration = match unit {
+                's' => Duration::seconds(amount),
+                'm' => Duration::minutes(amount),
+                'h' => Duration::hours(amount),
+                'd' => Duration::days(amount),
+                _ => anyhow::bail!("无效的时间单位: {}", unit),
+            };
+            return Ok(Local::now() - duration);
+        }
+    }
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Local));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        })
+        .with_context(|| format!("无法解析时间: {}", value))?;
+    Local.from_local_datetime(&naive)
+        .earliest()
+        .with_context(|| format!("无效的本地时间: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_at(secs: i64, kind: EventKind) -> ProcessEvent {
+        ProcessEvent {
+            timestamp: Local.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+            process_name: "web".to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_append_and_filter_events() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        event_at(0, EventKind::Started { pid: 1 }).append(&workspace)?;
+        event_at(10, EventKind::Exited { code: Some(1), signal: None }).append(&workspace)?;
+
+        let events = ProcessEvent::list(&workspace, "web", None)?;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].kind, EventKind::Exited { code: Some(1), signal: None });
+
+        let since = Local.timestamp_opt(1_700_000_005, 0).unwrap();
+        assert_eq!(ProcessEvent::list(&workspace, "web", Some(since))?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_uptime_and_mtbf() {
+        let events = vec![
+            event_at(0, EventKind::Started { pid: 1 }),
+            event_at(5, EventKind::Ready),
+            event_at(100, EventKind::Exited { code: None, signal: Some(9) }),
+            event_at(103, EventKind::Restarted { pid: 2, restart_count: 1 }),
+            event_at(203, EventKind::Exited { code: Some(1), signal: None }),
+            event_at(206, EventKind::Restarted { pid: 3, restart_count: 2 }),
+        ];
+        let now = Local.timestamp_opt(1_700_000_000 + 256, 0).unwrap();
+        let stats = EventStats::from_events(&events, now);
+        assert_eq!(stats.failures, 2);
+        assert_eq!(stats.uptime_secs, Some(50));
+        assert_eq!(stats.last_start, Some(events[5].timestamp));
+        // (100 + 100 + 50) / 2
+        assert_eq!(stats.mtbf_secs, Some(125));
+    }
+
+    #[test]
+    fn test_parse_since() -> Result<()> {
+        let before = Local::now() - Duration::hours(2);
+        assert!((parse_since("2h")? - before).num_seconds().abs() <= 1);
+        assert_eq!(parse_since("2024-01-02")?.format("%Y-%m-%d %H:%M").to_string(), "2024-01-02 00:00");
+        assert!(parse_since("2024-01-02T03:04:05Z").is_ok());
+        assert!(parse_since("yesterday").is_err());
+        Ok(())
+    }
+}
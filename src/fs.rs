@@ -48,35 +48,6 @@ impl FsManager {
         Self { config }
     }
 
-    /// 强制删除文件，处理各种特殊情况
-    pub async fn force_remove_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let path = path.as_ref();
-        
-        // 先尝试终止使用此文件的进程
-        self.kill_file_processes(path).await?;
-        
-        // 清理Git相关的锁定
-        self.cleanup_git_locks(path).await?;
-        
-        let mut last_error = None;
-
-        for i in 0..self.config.max_retries {
-            match self.try_remove_file(path).await {
-                Ok(_) => {
-                    info!("成功删除文件: {:?}", path);
-                    return Ok(());
-                }
-                Err(e) => {
-                    warn!("第{}次删除失败: {}", i + 1, e);
-                    last_error = Some(e);
-                    sleep(self.config.retry_delay()).await;
-                }
-            }
-        }
-
-        Err(last_error.unwrap_or_else(|| anyhow!("无法删除文件")))
-    }
-
     /// 强制删除目录，处理各种特殊情况
     pub async fn force_remove_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let path = path.as_ref();
@@ -106,9 +77,12 @@ impl FsManager {
         Err(last_error.unwrap_or_else(|| anyhow!("无法删除目录")))
     }
 
-    /// 终止使用指定文件的所有进程
-    async fn kill_file_processes(&self, path: &Path) -> Result<()> {
-        let mut processes = self.find_locking_processes(path).await?;
+    /// 终止使用其中任一文件的所有进程
+    async fn kill_file_processes(&self, paths: &[PathBuf]) -> Result<()> {
+        let mut processes = self.find_locking_processes(paths).await?;
+        if processes.is_empty() {
+            return Ok(());
+        }
         
         // 按优先级排序（优先级数字越大，实际优先级越低）
         processes.sort_by_key(|p| p.priority.value());
@@ -123,142 +97,48 @@ impl FsManager {
         Ok(())
     }
 
-    /// 查找锁定文件的所有进程
-    async fn find_locking_processes(&self, path: &Path) -> Result<Vec<ProcessInfo>> {
+    /// 查找锁定这些文件的所有进程
+    async fn find_locking_processes(&self, paths: &[PathBuf]) -> Result<Vec<ProcessInfo>> {
+        let backend = crate::process::backend::native();
         let mut processes = HashSet::new();
 
+        // Windows上Git进程经常锁住仓库中的文件
         #[cfg(windows)]
-        {
-            // 1. 查找Python进程
-            let output = std::process::Command::new("tasklist")
-                .args(["/FI", "IMAGENAME eq python.exe", "/NH", "/FO", "CSV"])
-                .output()
-                .context("查找Python进程失败")?;
-            
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            for line in output_str.lines() {
-                if let Some(pid) = line.split(',').nth(1) {
-                    let pid = pid.trim_matches('"').parse::<u32>()?;
-                    processes.insert(ProcessInfo {
-                        pid,
-                        name: "python.exe".to_string(),
-                        priority: ProcessPriority::Application,
-                    });
-                }
-            }
-
-            // 2. 查找Git进程
-            let git_processes = ["git.exe", "git-lfs.exe"];
-            for git_proc in git_processes.iter() {
-                let output = std::process::Command::new("tasklist")
-                    .args(["/FI", &format!("IMAGENAME eq {}", git_proc), "/NH", "/FO", "CSV"])
-                    .output()
-                    .context("查找Git进程失败")?;
-                
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                for line in output_str.lines() {
-                    if let Some(pid) = line.split(',').nth(1) {
-                        let pid = pid.trim_matches('"').parse::<u32>()?;
-                        processes.insert(ProcessInfo {
-                            pid,
-                            name: git_proc.to_string(),
-                            priority: ProcessPriority::Application,
-                        });
-                    }
-                }
-            }
-
-            // 3. 使用handle.exe查找系统进程（如果可用）
-            if let Ok(output) = std::process::Command::new("handle.exe")
-                .arg(path.to_str().unwrap())
-                .output()
-            {
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                for line in output_str.lines() {
-                    if let Some(pid) = line.split_whitespace().nth(2) {
-                        if let Ok(pid) = pid.parse::<u32>() {
-                            // 获取进程名
-                            if let Ok(proc_output) = std::process::Command::new("tasklist")
-                                .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
-                                .output()
-                            {
-                                let proc_str = String::from_utf8_lossy(&proc_output.stdout);
-                                if let Some(name) = proc_str.lines().next().and_then(|l| l.split(',').next()) {
-                                    let name = name.trim_matches('"').to_string();
-                                    let priority = if name.eq_ignore_ascii_case("explorer.exe") 
-                                        || name.eq_ignore_ascii_case("system") {
-                                        ProcessPriority::System
-                                    } else {
-                                        ProcessPriority::Application
-                                    };
-                                    processes.insert(ProcessInfo {
-                                        pid,
-                                        name,
-                                        priority,
-                                    });
-                                }
-                            }
-                        }
-                    }
-                }
+        for git_proc in ["git.exe", "git-lfs.exe"] {
+            for pid in backend.processes_named(git_proc) {
+                processes.insert(ProcessInfo {
+                    pid: pid as u32,
+                    name: git_proc.to_string(),
+                    priority: ProcessPriority::Application,
+                });
             }
         }
 
-        #[cfg(unix)]
-        {
-            use std::process::Command;
-            // 使用lsof查找所有使用文件的进程
-            if let Ok(output) = Command::new("lsof")
-                .arg(path.to_str().unwrap())
-                .output()
-            {
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                for line in output_str.lines().skip(1) {  // 跳过标题行
-                    let fields: Vec<&str> = line.split_whitespace().collect();
-                    if fields.len() >= 2 {
-                        if let Ok(pid) = fields[1].parse::<u32>() {
-                            let name = fields[0].to_string();
-                            let priority = if name.contains("python") || name.contains("git") {
-                                ProcessPriority::Application
-                            } else if name.eq_ignore_ascii_case("explorer") 
-                                || name.eq_ignore_ascii_case("system") {
-                                ProcessPriority::System
-                            } else {
-                                ProcessPriority::Temporary
-                            };
-                            processes.insert(ProcessInfo {
-                                pid,
-                                name,
-                                priority,
-                            });
-                        }
-                    }
-                }
-            }
+        for (pid, name) in backend.file_users(paths) {
+            let priority = if name.eq_ignore_ascii_case("explorer")
+                || name.eq_ignore_ascii_case("explorer.exe")
+                || name.eq_ignore_ascii_case("system") {
+                ProcessPriority::System
+            } else if cfg!(windows) || name.contains("git") {
+                ProcessPriority::Application
+            } else {
+                ProcessPriority::Temporary
+            };
+            processes.insert(ProcessInfo {
+                pid: pid as u32,
+                name,
+                priority,
+            });
         }
 
         Ok(processes.into_iter().collect())
     }
 
-    /// 终止指定的进程
+    /// 强制终止指定的进程及其子进程
     async fn kill_process(&self, pid: u32) -> Result<()> {
-        #[cfg(windows)]
-        {
-            std::process::Command::new("taskkill")
-                .args(["/PID", &pid.to_string(), "/T", "/F"])
-                .output()
-                .context("终止进程失败")?;
-        }
-
-        #[cfg(unix)]
-        {
-            use nix::sys::signal::{kill, Signal};
-            use nix::unistd::Pid;
-            kill(Pid::from_raw(pid as i32), Signal::SIGTERM)
-                .context("终止进程失败")?;
-        }
-
-        Ok(())
+        crate::process::backend::native()
+            .kill_tree(pid as i32, None)
+            .context("终止进程失败")
     }
 
     /// 清理Git相关的锁定
@@ -309,45 +189,17 @@ impl FsManager {
         })
     }
 
-    /// 终止使用指定目录的所有进程
+    /// 终止使用指定目录中文件的所有进程
     async fn kill_directory_processes(&self, path: &Path) -> Result<()> {
-        // 遍历目录中的所有Python文件
+        // 目录中的所有文件, 不限定运行时, 一次查找所有使用它们的进程
+        let mut files = Vec::new();
         for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.extension().map_or(false, |ext| ext == "py") {
-                self.kill_file_processes(&path).await?;
+            let path = entry?.path();
+            if path.is_file() {
+                files.push(path);
             }
         }
-        Ok(())
-    }
-
-    async fn try_remove_file(&self, path: &Path) -> Result<()> {
-        // 1. 尝试修改文件属性
-        self.remove_readonly(path)?;
-        
-        // 2. 尝试强制删除
-        #[cfg(windows)]
-        {
-            // 在Windows上使用特权API
-            use std::process::Command;
-            Command::new("cmd")
-                .args(["/C", "del", "/F", "/A", path.to_str().unwrap()])
-                .output()
-                .context("执行del命令失败")?;
-        }
-
-        #[cfg(unix)]
-        {
-            // 在Unix上使用chmod
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(path)?.permissions();
-            perms.set_mode(self.config.default_file_mode);
-            fs::set_permissions(path, perms)?;
-            fs::remove_file(path).context("删除文件失败")?;
-        }
-
-        Ok(())
+        self.kill_file_processes(&files).await
     }
 
     async fn try_remove_dir_all(&self, path: &Path) -> Result<()> {
@@ -403,4 +255,39 @@ impl FsManager {
         }
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_force_remove_dir_all_kills_file_users_once() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manager = FsManager::new(FsConfig { exit_wait_ms: 2000, ..Default::default() });
+
+        // 没有进程使用其中的文件时不等待进程退出
+        let idle = dir.path().join("idle");
+        fs::create_dir_all(&idle)?;
+        for name in ["a", "b", "c"] {
+            fs::write(idle.join(name), name)?;
+        }
+        let started = Instant::now();
+        manager.force_remove_dir_all(&idle).await?;
+        assert!(started.elapsed() < Duration::from_millis(2000));
+        assert!(!idle.exists());
+
+        let busy = dir.path().join("busy");
+        fs::create_dir_all(&busy)?;
+        fs::write(busy.join("a"), "a")?;
+        fs::write(busy.join("b"), "b")?;
+        let mut holder = std::process::Command::new("sleep")
+            .arg("60")
+            .stdin(fs::File::open(busy.join("b"))?)
+            .spawn()?;
+        manager.force_remove_dir_all(&busy).await?;
+        assert!(holder.wait()?.code().is_none());
+        assert!(!busy.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
+use crate::crash::TailLine;
+use crate::hooks::HooksConfig;
+use crate::notify::NotificationsConfig;
+use crate::process::cgroup::LimitsConfig;
+use crate::process::sandbox::SandboxConfig;
+use crate::sinks::SinkConfig;
+use crate::types::ProcessConfig;
+use crate::workspace::Workspace;
+
+/// 当前交接文件格式版本
+pub const HANDOFF_VERSION: u32 = 1;
+
+/// 监控进程升级时交给新程序的进程表
+///
+/// 旧监控进程收到升级信号后写入交接文件, 去掉需要保留的文件描述符上的 `FD_CLOEXEC`,
+/// 然后exec新程序。exec不改变PID, 被监控的进程仍是新程序的子进程, 不需要重启
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Handoff {
+    pub version: u32,
+    pub process_name: String,
+    /// 被监控进程的PID
+    pub pid: i32,
+    /// 被监控进程不是监控进程的子进程(原监控进程退出后由recover接管)
+    #[serde(default)]
+    pub orphan: bool,
+    /// 被监控进程本次启动的时间, 用于计算崩溃记录中的运行时长
+    pub started_at: DateTime<Local>,
+    /// 故障重启后还在等待进程稳定运行
+    pub awaiting_recovery: bool,
+    pub auto_restart: bool,
+    pub max_restarts: Option<u32>,
+    pub config: ProcessConfig,
+    pub env_vars: HashMap<String, String>,
+    pub hooks: HooksConfig,
+    pub notifications: NotificationsConfig,
+    pub log_sinks: Vec<SinkConfig>,
+    /// 进程标准输出管道的读端
+    pub stdout_fd: Option<i32>,
+    /// 进程标准错误管道的读端
+    pub stderr_fd: Option<i32>,
+    /// 预先绑定的监听套接字, 按配置顺序排列
+    pub socket_fds: Vec<i32>,
+    /// 最近的进程输出, 用于崩溃记录
+    pub output_tail: Vec<TailLine>,
+    /// 程序写入真实PID的文件, 重启后从中读取新的PID
+    #[serde(default)]
+    pub pid_file: Option<PathBuf>,
+    /// 重启时写入进程cgroup的资源上限
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// 重启时使用的沙箱设置
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+}
+
+impl Handoff {
+    /// 交接文件路径
+    pub fn path(workspace: &Workspace, process_name: &str) -> PathBuf {
+        workspace.get_process_dir(process_name).join("handoff.json")
+    }
+
+    /// 升级请求文件路径, 内容为新程序的路径
+    pub fn request_path(workspace: &Workspace, process_name: &str) -> PathBuf {
+        workspace.get_process_dir(process_name).join("reexec.request")
+    }
+
+    pub fn save(&self, workspace: &Workspace) -> Result<()> {
+        let path = Self::path(workspace, &self.process_name);
+        let content = serde_json::to_string(self).context("序列化交接信息失败")?;
+        fs::write(&path, content).with_context(|| format!("写入交接文件失败: {:?}", path))
+    }
+
+    /// 读取并删除交接文件, 交接文件只能使用一次
+    pub fn take(workspace: &Workspace, process_name: &str) -> Result<Self> {
+        let path = Self::path(workspace, process_name);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("读取交接文件失败: {:?}", path))?;
+        let _ = fs::remove_file(&path);
+        let handoff: Self = serde_json::from_str(&content).context("解析交接文件失败")?;
+        if handoff.version > HANDOFF_VERSION {
+            anyhow::bail!("交接文件版本({})高于当前支持的版本({})", handoff.version, HANDOFF_VERSION);
+        }
+        Ok(handoff)
+    }
+
+    /// 写入升级请求, 指定exec时使用的程序
+    pub fn request(workspace: &Workspace, process_name: &str, exe: &Path) -> Result<()> {
+        let path = Self::request_path(workspace, process_name);
+        fs::write(&path, exe.to_string_lossy().as_bytes())
+            .with_context(|| format!("写入升级请求失败: {:?}", path))
+    }
+
+    /// 读取并删除升级请求, 没有请求时使用当前程序
+    ///
+    /// 程序文件被替换后 `current_exe` 指向已删除的旧文件, 所以由发起升级的新程序写入自己的路径
+    pub fn take_request(workspace: &Workspace, process_name: &str) -> Result<PathBuf> {
+        let path = Self::request_path(workspace, process_name);
+        match fs::read_to_string(&path) {
+            Ok(exe) => {
+                let _ = fs::remove_file(&path);
+                Ok(PathBuf::from(exe.trim()))
+            }
+            Err(_) => std::env::current_exe().context("无法获取当前程序路径"),
+        }
+    }
+}
+
+/// 等待升级信号(Unix下为SIGUSR2)
+pub struct UpgradeSignal {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl UpgradeSignal {
+    pub fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let signal = match signal(SignalKind::user_defined2()) {
+                Ok(signal) => Some(signal),
+                Err(e) => {
+                    log::warn!("监听升级信号失败, 无法在线升级: {}", e);
+                    None
+                }
+            };
+            Self { signal }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    /// 收到升级信号时返回, 不支持的平台上永远等待
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = &mut self.signal {
+            signal.recv().await;
+            return;
+        }
+        std::future::pending::<()>().await
+    }
+}
+
+/// 监控进程是否已注册升级信号的处理函数
+///
+/// 旧版本的监控进程没有处理SIGUSR2, 收到信号会直接退出; exec后新程序注册处理函数之前也是如此
+#[cfg(target_os = "linux")]
+pub fn handles_upgrade_signal(pid: i32) -> bool {
+    let Ok(status) = fs::read_to_string(format!("/proc/{}/status", pid)) else {
+        return false;
+    };
+    status.lines()
+        .find_map(|line| line.strip_prefix("SigCgt:"))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+        .is_some_and(|mask| mask & (1 << (nix::sys::signal::Signal::SIGUSR2 as u64 - 1)) != 0)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn handles_upgrade_signal(_pid: i32) -> bool {
+    true
+}
+
+/// 让文件描述符在exec后保留, 描述符已关闭或不是预期类型时返回false
+#[cfg(unix)]
+pub fn inherit_fd(fd: i32, socket: bool) -> bool {
+    use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+    use nix::sys::stat::{fstat, SFlag};
+
+    // 描述符可能已随输出读取任务关闭并被复用, 先检查类型
+    let Ok(stat) = fstat(fd) else {
+        return false;
+    };
+    let kind = SFlag::from_bits_truncate(stat.st_mode) & SFlag::S_IFMT;
+    let expected = if socket { SFlag::S_IFSOCK } else { SFlag::S_IFIFO };
+    if kind != expected {
+        return false;
+    }
+    fcntl(fd, FcntlArg::F_SETFD(FdFlag::empty())).is_ok()
+}
+
+/// 恢复文件描述符的 `FD_CLOEXEC`, exec失败后避免泄漏给之后启动的子进程
+#[cfg(unix)]
+pub fn close_on_exec(fd: i32) {
+    use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+    let _ = fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handoff_is_taken_once() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        workspace.ensure_process_dirs("web")?;
+
+        let handoff = Handoff {
+            version: HANDOFF_VERSION,
+            process_name: "web".into(),
+            pid: 42,
+            orphan: false,
+            started_at: Local::now(),
+            awaiting_recovery: true,
+            auto_restart: true,
+            max_restarts: Some(3),
+            config: ProcessConfig::default(),
+            env_vars: HashMap::from([("PORT".to_string(), "8000".to_string())]),
+            hooks: HooksConfig::default(),
+            notifications: NotificationsConfig::default(),
+            log_sinks: Vec::new(),
+            stdout_fd: Some(7),
+            stderr_fd: None,
+            socket_fds: vec![9],
+            output_tail: Vec::new(),
+            pid_file: None,
+            limits: Default::default(),
+            sandbox: Default::default(),
+        };
+        handoff.save(&workspace)?;
+
+        let taken = Handoff::take(&workspace, "web")?;
+        assert_eq!(taken.pid, 42);
+        assert_eq!(taken.stdout_fd, Some(7));
+        assert_eq!(taken.env_vars["PORT"], "8000");
+        assert!(Handoff::take(&workspace, "web").is_err());
+
+        // 没有升级请求时使用当前程序
+        Handoff::request(&workspace, "web", Path::new("/opt/fuckrun/bin/fuckrun"))?;
+        assert_eq!(Handoff::take_request(&workspace, "web")?, PathBuf::from("/opt/fuckrun/bin/fuckrun"));
+        assert_eq!(Handoff::take_request(&workspace, "web")?, std::env::current_exe()?);
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_detects_upgrade_signal_handler() {
+        let pid = std::process::id() as i32;
+        let _signal = UpgradeSignal::new();
+        assert!(handles_upgrade_signal(pid));
+        assert!(!handles_upgrade_signal(i32::MAX));
+    }
+}
@@ -0,0 +1,60 @@
+/// 跨Rust版本稳定的FNV-1a哈希, 用于需要保存下来再比较的哈希值
+///
+/// 标准库的 `DefaultHasher` 算法可能随Rust版本变化, 不能用于持久化的值;
+/// 这里也不经过 `Hash` trait, 各字段的字节按固定格式写入
+#[derive(Debug, Clone, Copy)]
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 写入一个字段, 以长度为前缀, 相邻字段的边界不同时结果也不同
+    pub fn write(&mut self, bytes: &[u8]) -> &mut Self {
+        self.update(&(bytes.len() as u64).to_le_bytes());
+        self.update(bytes);
+        self
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// 32位FNV-1a哈希
+pub fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5u32, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x01000193))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashes_are_stable() {
+        assert_eq!(fnv1a(b""), 0x811c9dc5);
+        assert_eq!(fnv1a(b"a"), 0xe40c292c);
+
+        // 保存的哈希值依赖这些结果, 不能改变
+        let mut hasher = StableHasher::new();
+        hasher.write(b"KEY").write(b"value");
+        assert_eq!(format!("{:016x}", hasher.finish()), "6ba7ae68fcf57949");
+
+        let mut other = StableHasher::new();
+        other.write(b"KEYv").write(b"alue");
+        assert_ne!(hasher.finish(), other.finish());
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use anyhow::{Result, Context};
+use log::{info, warn, error};
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use crate::logger::Logger;
+use crate::workspace::Workspace;
+
+/// 生命周期钩子触发时机
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PreStart,
+    PostStart,
+    PreStop,
+    PostStop,
+    OnCrash,
+}
+
+impl HookEvent {
+    /// 事件名称, 同时作为FUCKRUN_EVENT的值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::PreStart => "pre_start",
+            HookEvent::PostStart => "post_start",
+            HookEvent::PreStop => "pre_stop",
+            HookEvent::PostStop => "post_stop",
+            HookEvent::OnCrash => "on_crash",
+        }
+    }
+}
+
+/// 单个钩子命令
+///
+/// 可以直接写成字符串, 也可以写成带超时的对象:
+/// `pre_start: "./migrate.sh"` 或 `pre_start: { command: "./migrate.sh", timeout_secs: 300 }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HookCommand {
+    Command(String),
+    Detailed {
+        command: String,
+        /// 超时时间(秒), 不设置时使用hooks.timeout_secs
+        #[serde(default)]
+        timeout_secs: Option<u64>,
+    },
+}
+
+impl HookCommand {
+    pub fn command(&self) -> &str {
+        match self {
+            HookCommand::Command(command) => command,
+            HookCommand::Detailed { command, .. } => command,
+        }
+    }
+
+    fn timeout_secs(&self) -> Option<u64> {
+        match self {
+            HookCommand::Command(_) => None,
+            HookCommand::Detailed { timeout_secs, .. } => *timeout_secs,
+        }
+    }
+}
+
+/// 生命周期钩子配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// 启动前执行(如数据库迁移)
+    #[serde(default)]
+    pub pre_start: Option<HookCommand>,
+    /// 启动完成后执行
+    #[serde(default)]
+    pub post_start: Option<HookCommand>,
+    /// 停止前执行
+    #[serde(default)]
+    pub pre_stop: Option<HookCommand>,
+    /// 停止后执行
+    #[serde(default)]
+    pub post_stop: Option<HookCommand>,
+    /// 进程异常退出时执行
+    #[serde(default)]
+    pub on_crash: Option<HookCommand>,
+    /// 默认超时时间(秒)
+    #[serde(default = "default_hook_timeout_secs")]
+    pub timeout_secs: u64,
+    /// pre_start钩子失败时是否中止启动
+    #[serde(default = "default_abort_on_pre_start_failure")]
+    pub abort_on_pre_start_failure: bool,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            pre_start: None,
+            post_start: None,
+            pre_stop: None,
+            post_stop: None,
+            on_crash: None,
+            timeout_secs: default_hook_timeout_secs(),
+            abort_on_pre_start_failure: default_abort_on_pre_start_failure(),
+        }
+    }
+}
+
+fn default_hook_timeout_secs() -> u64 {
+    30
+}
+
+fn default_abort_on_pre_start_failure() -> bool {
+    true
+}
+
+impl HooksConfig {
+    pub fn get(&self, event: HookEvent) -> Option<&HookCommand> {
+        match event {
+            HookEvent::PreStart => self.pre_start.as_ref(),
+            HookEvent::PostStart => self.post_start.as_ref(),
+            HookEvent::PreStop => self.pre_stop.as_ref(),
+            HookEvent::PostStop => self.post_stop.as_ref(),
+            HookEvent::OnCrash => self.on_crash.as_ref(),
+        }
+    }
+}
+
+/// 钩子执行环境
+pub struct HookContext<'a> {
+    pub workspace: &'a Workspace,
+    pub process_name: &'a str,
+    pub working_dir: &'a Path,
+    /// 进程的环境变量, 钩子同样继承
+    pub env_vars: &'a HashMap<String, String>,
+    /// 进程的退出状态(on_crash时可用)
+    pub exit_status: Option<&'a ExitStatus>,
+}
+
+/// 执行指定事件的钩子, 没有配置时直接返回
+///
+/// 钩子通过shell执行, 输出写入进程的stdout/stderr日志; 非零退出或超时返回错误
+pub async fn run_hook(hooks: &HooksConfig, event: HookEvent, ctx: &HookContext<'_>) -> Result<()> {
+    let Some(hook) = hooks.get(event) else {
+        return Ok(());
+    };
+    let timeout = Duration::from_secs(hook.timeout_secs().unwrap_or(hooks.timeout_secs));
+    info!("执行{}钩子: {}", event.as_str(), hook.command());
+
+    #[cfg(unix)]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(hook.command());
+        cmd
+    };
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(hook.command());
+        cmd
+    };
+
+    if ctx.working_dir.exists() {
+        cmd.current_dir(ctx.working_dir);
+    }
+    cmd.envs(ctx.env_vars)
+       .env("FUCKRUN_EVENT", event.as_str())
+       .env("FUCKRUN_PROCESS", ctx.process_name)
+       .stdin(Stdio::null())
+       .stdout(Stdio::piped())
+       .stderr(Stdio::piped())
+       .kill_on_drop(true);
+    if let Some(status) = ctx.exit_status {
+        if let Some(code) = status.code() {
+            cmd.env("FUCKRUN_EXIT_CODE", code.to_string());
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                cmd.env("FUCKRUN_EXIT_SIGNAL", signal.to_string());
+            }
+        }
+    }
+
+    let mut child = cmd.spawn()
+        .with_context(|| format!("启动{}钩子失败", event.as_str()))?;
+
+    // 钩子输出写入进程日志, 每行带上钩子名称前缀
+    let logger = Logger::new(ctx.workspace.clone());
+    let prefix = format!("[hook:{}]", event.as_str());
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        let (logger, prefix, name) = (logger.clone(), prefix.clone(), ctx.process_name.to_string());
+        readers.push(tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Err(e) = logger.write_stdout(&name, &format!("{} {}", prefix, line)) {
+                    error!("写入钩子输出失败: {}", e);
+                }
+            }
+        }));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let (logger, prefix, name) = (logger.clone(), prefix.clone(), ctx.process_name.to_string());
+        readers.push(tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Err(e) = logger.write_stderr(&name, &format!("{} {}", prefix, line)) {
+                    error!("写入钩子输出失败: {}", e);
+                }
+            }
+        }));
+    }
+
+    let status = match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(status) => status.with_context(|| format!("等待{}钩子失败", event.as_str()))?,
+        Err(_) => {
+            warn!("{}钩子执行超时({:?}), 终止钩子", event.as_str(), timeout);
+            let _ = child.kill().await;
+            for reader in readers {
+                reader.abort();
+            }
+            anyhow::bail!("{}钩子执行超时({:?})", event.as_str(), timeout);
+        }
+    };
+    // 钩子启动的后台进程可能继续持有输出管道, 只等待有限时间
+    for reader in readers {
+        let _ = tokio::time::timeout(Duration::from_secs(1), reader).await;
+    }
+
+    if !status.success() {
+        anyhow::bail!("{}钩子执行失败: {}", event.as_str(), status);
+    }
+    info!("{}钩子执行完成", event.as_str());
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn context<'a>(
+        workspace: &'a Workspace,
+        env_vars: &'a HashMap<String, String>,
+        exit_status: Option<&'a ExitStatus>,
+    ) -> HookContext<'a> {
+        HookContext {
+            workspace,
+            process_name: "web",
+            working_dir: workspace.get_root_dir(),
+            env_vars,
+            exit_status,
+        }
+    }
+
+    #[test]
+    fn test_hook_config_forms() -> Result<()> {
+        let hooks: HooksConfig = serde_yaml::from_str(
+            "pre_start: ./migrate.sh\non_crash: { command: ./notify.sh, timeout_secs: 5 }\n",
+        )?;
+        assert_eq!(hooks.get(HookEvent::PreStart).map(|h| h.command()), Some("./migrate.sh"));
+        assert_eq!(hooks.get(HookEvent::OnCrash).and_then(|h| h.timeout_secs()), Some(5));
+        assert_eq!(hooks.timeout_secs, 30);
+        assert!(hooks.abort_on_pre_start_failure);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hook_env_and_output() -> Result<()> {
+        use std::os::unix::process::ExitStatusExt;
+
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        let hooks = HooksConfig {
+            on_crash: Some(HookCommand::Command(
+                "echo $FUCKRUN_EVENT $FUCKRUN_PROCESS $FUCKRUN_EXIT_CODE $APP_ENV".into(),
+            )),
+            ..Default::default()
+        };
+        let env_vars = HashMap::from([("APP_ENV".to_string(), "prod".to_string())]);
+        let status = ExitStatus::from_raw(3 << 8);
+        run_hook(&hooks, HookEvent::OnCrash, &context(&workspace, &env_vars, Some(&status))).await?;
+
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let stdout = std::fs::read_to_string(
+            workspace.get_process_log_dir("web").join(today).join("stdout.log"),
+        )?;
+        assert_eq!(stdout.trim(), "[hook:on_crash] on_crash web 3 prod");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hook_failure_and_timeout() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        let env_vars = HashMap::new();
+        let hooks = HooksConfig {
+            pre_start: Some(HookCommand::Command("exit 1".into())),
+            pre_stop: Some(HookCommand::Detailed { command: "sleep 5".into(), timeout_secs: Some(1) }),
+            ..Default::default()
+        };
+        assert!(run_hook(&hooks, HookEvent::PreStart, &context(&workspace, &env_vars, None)).await.is_err());
+
+        let started = std::time::Instant::now();
+        assert!(run_hook(&hooks, HookEvent::PreStop, &context(&workspace, &env_vars, None)).await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(4));
+        // 没有配置的钩子直接成功
+        assert!(run_hook(&hooks, HookEvent::PostStop, &context(&workspace, &env_vars, None)).await.is_ok());
+        Ok(())
+    }
+}
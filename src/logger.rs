@@ -1,10 +1,14 @@
 use std::path::{Path, PathBuf};
 use std::io::Write;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use anyhow::Result;
-use log::{LevelFilter, info, error};
+use log::{Level, LevelFilter, Record, info, error};
 use log4rs::{
+    append::Append,
     append::rolling_file::RollingFileAppender,
-    append::console::ConsoleAppender,
+    append::console::{ConsoleAppender, Target},
     config::{Appender, Config, Root},
     encode::pattern::PatternEncoder,
 };
@@ -13,11 +17,44 @@ use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWin
 use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
 use chrono::Local;
 use crate::workspace::Workspace;
+use crate::sinks::{LogRecord, SinkConfig, SinkSet};
+
+type SharedSinks = Arc<RwLock<Option<SinkSet>>>;
+
+/// 进程输出使用的日志target, 这些行由进程自己的投递目标发送, 不再转发到主程序的投递目标
+pub const OUTPUT_TARGET: &str = "fuckrun::output";
 
 /// 日志管理器
 #[derive(Clone)]
 pub struct Logger {
     workspace: Workspace,
+    /// 主程序日志的投递目标
+    global_sinks: SharedSinks,
+    /// 各进程输出的投递目标
+    process_sinks: Arc<RwLock<HashMap<String, SinkSet>>>,
+}
+
+/// 把主程序日志转发到投递目标的log4rs appender
+#[derive(Debug)]
+struct SinkAppender {
+    sinks: SharedSinks,
+}
+
+impl Append for SinkAppender {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        // 投递线程自身的日志不再转发, 避免循环; 进程输出已经单独投递过
+        if record.target().starts_with("fuckrun::sinks") || record.target() == OUTPUT_TARGET {
+            return Ok(());
+        }
+        if let Ok(sinks) = self.sinks.read() {
+            if let Some(sinks) = sinks.as_ref() {
+                sinks.emit(&LogRecord::new(record.level(), "fuckrun", None, &record.args().to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&self) {}
 }
 
 impl Logger {
@@ -25,6 +62,52 @@ impl Logger {
     pub fn new(workspace: Workspace) -> Self {
         Self {
             workspace,
+            global_sinks: Arc::new(RwLock::new(None)),
+            process_sinks: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 为主程序日志配置投递目标
+    pub fn attach_sinks(&self, configs: &[SinkConfig]) {
+        if configs.is_empty() {
+            return;
+        }
+        if let Ok(mut sinks) = self.global_sinks.write() {
+            *sinks = Some(SinkSet::from_configs(configs));
+        }
+    }
+
+    /// 为进程输出配置投递目标
+    pub fn attach_process_sinks(&self, process_name: &str, configs: &[SinkConfig]) {
+        if configs.is_empty() {
+            return;
+        }
+        if let Ok(mut sinks) = self.process_sinks.write() {
+            sinks.insert(process_name.to_string(), SinkSet::from_configs(configs));
+        }
+    }
+
+    /// 关闭所有投递目标, 尽量发送完缓冲的日志
+    pub fn shutdown_sinks(&self) {
+        let timeout = Duration::from_secs(3);
+        if let Ok(mut sinks) = self.process_sinks.write() {
+            for (_, set) in sinks.drain() {
+                set.shutdown(timeout);
+            }
+        }
+        if let Ok(mut sinks) = self.global_sinks.write() {
+            if let Some(set) = sinks.take() {
+                set.shutdown(timeout);
+            }
+        }
+    }
+
+    /// 把进程输出投递到该进程的投递目标
+    fn emit_process_line(&self, process_name: &str, stream: &str, line: &str) {
+        if let Ok(sinks) = self.process_sinks.read() {
+            if let Some(set) = sinks.get(process_name) {
+                set.emit(&LogRecord::new(Level::Info, process_name, Some(stream), line));
+            }
         }
     }
 
@@ -51,8 +134,9 @@ impl Logger {
         let main_log_dir = self.get_main_log_dir();
         std::fs::create_dir_all(&main_log_dir)?;
 
-        // 配置控制台输出
-        let stdout = ConsoleAppender::builder()
+        // 配置控制台输出, 写入stderr以免和命令输出混在一起
+        let console = ConsoleAppender::builder()
+            .target(Target::Stderr)
             .encoder(Box::new(PatternEncoder::new("[{l}] {f}:{L} - {m}{n}")))
             .build();
 
@@ -70,13 +154,20 @@ impl Logger {
             .append(true)  // 追加模式
             .build(main_log, Box::new(main_policy))?;
 
+        // 投递目标在配置加载后通过attach_sinks挂载
+        let sink_appender = SinkAppender {
+            sinks: self.global_sinks.clone(),
+        };
+
         // 创建日志配置
         let config = Config::builder()
-            .appender(Appender::builder().build("stdout", Box::new(stdout)))
+            .appender(Appender::builder().build("console", Box::new(console)))
             .appender(Appender::builder().build("main", Box::new(main_appender)))
+            .appender(Appender::builder().build("sinks", Box::new(sink_appender)))
             .build(Root::builder()
-                .appender("stdout")
+                .appender("console")
                 .appender("main")
+                .appender("sinks")
                 .build(LevelFilter::Info))?;
 
         // 应用配置
@@ -133,8 +224,9 @@ impl Logger {
             .append(true)
             .open(stdout_log)?
             .write_all(format!("{}\n", line).as_bytes())?;
-            
-        info!("[{}][stdout] {}", process_name, line);
+
+        self.emit_process_line(process_name, "stdout", line);
+        info!(target: OUTPUT_TARGET, "[{}][stdout] {}", process_name, line);
         Ok(())
     }
 
@@ -154,8 +246,49 @@ impl Logger {
             .append(true)
             .open(stderr_log)?
             .write_all(format!("{}\n", line).as_bytes())?;
-            
-        info!("[{}][stderr] {}", process_name, line);
+
+        self.emit_process_line(process_name, "stderr", line);
+        info!(target: OUTPUT_TARGET, "[{}][stderr] {}", process_name, line);
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::SyslogTransport;
+    use std::net::UdpSocket;
+
+    #[test]
+    fn test_sink_appender_skips_process_output() {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let appender = SinkAppender {
+            sinks: Arc::new(RwLock::new(Some(SinkSet::from_configs(&[SinkConfig::Syslog {
+                address: listener.local_addr().unwrap().to_string(),
+                transport: SyslogTransport::Udp,
+                facility: 16,
+                app_name: None,
+                buffer_size: 10,
+            }])))),
+        };
+
+        // 进程输出已经由进程的投递目标发送, 主程序投递目标只收到自己的日志
+        appender.append(&Record::builder()
+            .target(OUTPUT_TARGET)
+            .level(Level::Info)
+            .args(format_args!("[stdout] hello"))
+            .build()).unwrap();
+        appender.append(&Record::builder()
+            .target("fuckrun::process::manager")
+            .level(Level::Info)
+            .args(format_args!("进程已启动"))
+            .build()).unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = listener.recv(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf[..n]).ends_with("进程已启动"));
+        listener.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        assert!(listener.recv(&mut buf).is_err());
+    }
+}
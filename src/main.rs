@@ -7,12 +7,31 @@ mod state;
 mod logger;
 mod fs;
 mod types;
+mod sinks;
+mod crash;
+mod events;
+mod hooks;
+mod notify;
+mod runtime;
+mod venv;
+mod deploy;
+mod proxy;
+mod sockets;
+mod handoff;
+mod metrics;
+mod error;
+mod hash;
+mod output;
+#[cfg(test)]
+mod test_support;
 
+use std::process::ExitCode;
 use anyhow::{Result, Context};
 use log::{info, warn, error};
 use clap::Parser;
 use tokio::signal;
-use crate::cli::{Cli, Commands};
+use serde_json::json;
+use crate::cli::{Cli, Commands, WorkspaceAction};
 use crate::config::Config;
 use crate::workspace::Workspace;
 use crate::logger::Logger;
@@ -22,13 +41,47 @@ use crate::commands::status::handle_status;
 use crate::commands::monitor::handle_monitor;
 use crate::commands::logs::handle_logs;
 use crate::commands::list::{handle_list, ListFilter};
+use crate::state::ProcessState;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
-    
-    // 初始化工作区
-    let mut workspace = Workspace::new(cli.workspace.unwrap_or_else(|| std::path::PathBuf::from(".")));
+    let output = cli.output_format();
+    let command = cli.command.name();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if output.is_json() {
+                output::print_error(command, &e);
+            } else {
+                eprintln!("Error: {:?}", e);
+            }
+            ExitCode::from(error::exit_code(&e))
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let output = cli.output_format();
+
+    // init 在指定目录或当前目录创建工作区, 不需要查找
+    if let Commands::Init { force } = cli.command {
+        let root = match cli.workspace {
+            Some(root) => root,
+            None => std::env::current_dir().context("无法获取当前目录")?,
+        };
+        return commands::handle_init(&root, force, output);
+    }
+
+    // 查找并初始化工作区
+    let (mut workspace, source) = Workspace::discover(cli.workspace.as_deref())?;
+
+    // workspace info 只显示查找结果, 不创建目录
+    if let Commands::Workspace { action: WorkspaceAction::Info } = cli.command {
+        return commands::handle_workspace_info(&workspace, &source, cli.config.as_deref(), output);
+    }
+
     workspace.init()?;
     
     // 如果是 list 命令,直接执行不需要加载配置
@@ -45,6 +98,7 @@ async fn main() -> Result<()> {
         json,
         watch,
     } = cli.command {
+        warn_deprecated_json(json);
         let filter = ListFilter {
             name,
             status,
@@ -55,7 +109,28 @@ async fn main() -> Result<()> {
             min_mem: min_mem.map(|m| m * 1024 * 1024),  // 转换MB为字节
             max_mem: max_mem.map(|m| m * 1024 * 1024),  // 转换MB为字节
         };
-        return handle_list(&workspace, app_dir, Some(filter), json, watch).await;
+        return handle_list(&workspace, app_dir, Some(filter), watch, output).await;
+    }
+
+    // add 命令直接修改配置文件
+    if let Commands::Add { name, program, port, health_check_url, auto_restart, args } = cli.command {
+        let config_path = cli.config.unwrap_or_else(|| Config::find_config_file(None, &workspace));
+        let process = commands::add::NewProcess {
+            name, program, args, port, health_check_url, auto_restart, ..Default::default()
+        };
+        return commands::handle_add(&workspace, &config_path, process, output);
+    }
+
+    // crashes 命令只读取崩溃记录,同样不需要加载配置
+    if let Commands::Crashes { name, id, last, json } = cli.command {
+        warn_deprecated_json(json);
+        return commands::handle_crashes(&workspace, name, id, last, output).await;
+    }
+
+    // events 命令只读取事件日志,同样不需要加载配置
+    if let Commands::Events { name, since, json } = cli.command {
+        warn_deprecated_json(json);
+        return commands::handle_events(&workspace, name, since, output).await;
     }
 
     // 如果指定了app_dir，更新workspace的app_dir
@@ -75,10 +150,23 @@ async fn main() -> Result<()> {
     
     info!("程序启动");
 
-    // 检查是否是Monitor命令
-    let is_monitor = matches!(cli.command, Commands::Monitor { .. });
+    // 检查是否是Monitor、Proxy或Resume命令
+    let is_monitor = matches!(
+        cli.command,
+        Commands::Monitor { .. } | Commands::Proxy { .. } | Commands::Resume { .. }
+    );
+
+    // run --save 写入的配置文件: 指定的配置文件, 否则使用已有的配置文件或新建 fuckrun.yaml
+    let save_path = cli.config.clone().unwrap_or_else(|| {
+        let path = Config::find_config_file(None, &workspace);
+        if path.exists() {
+            path
+        } else {
+            workspace.get_root_dir().join(workspace::MARKER_FILE)
+        }
+    });
 
-    // 加载配置文件(Monitor命令不需要全局配置)
+    // 加载配置文件(Monitor、Proxy和Resume命令不需要全局配置)
     let config = if is_monitor {
         Config::default()
     } else if let Some(config_path) = cli.config {
@@ -90,15 +178,33 @@ async fn main() -> Result<()> {
             Commands::Start { name, .. } |
             Commands::Stop { name, .. } |
             Commands::Status { name, .. } => name.as_deref(),
-            Commands::Logs { name, .. } => Some(name.as_str()),
+            Commands::Logs { name, .. } |
+            Commands::Run { name, .. } |
+            Commands::Restart { name, .. } |
+            Commands::Prepare { name, .. } |
+            Commands::Deploy { name, .. } |
+            Commands::Rollback { name, .. } => Some(name.as_str()),
             _ => None
         };
         
         let config_path = Config::find_config_file(process_name, &workspace);
-        info!("使用配置文件: {:?}", config_path);
-        Config::from_file(config_path)?
+        // run 和临时进程不需要配置文件
+        let transient = process_name.is_some_and(|name| {
+            ProcessState::load(&workspace, name).is_ok_and(|state| state.transient.is_some())
+        });
+        if !config_path.exists() && (matches!(cli.command, Commands::Run { .. }) || transient) {
+            info!("配置文件不存在, 使用默认配置: {:?}", config_path);
+            Config::default()
+        } else {
+            info!("使用配置文件: {:?}", config_path);
+            Config::from_file(config_path)?
+        }
     };
 
+    // 挂载主程序日志投递目标
+    logger.attach_sinks(&config.global.log.sinks);
+    let sink_logger = logger.clone();
+
     // 设置Ctrl+C处理
     let ctrl_c = async {
         signal::ctrl_c().await.expect("无法监听Ctrl+C信号");
@@ -109,66 +215,159 @@ async fn main() -> Result<()> {
     // 主逻辑
     let work = async {
         match cli.command {
-            Commands::Start { name, python, port, detach, daemon, auto_restart, app_dir: _ } => {
-                let name_for_status = name.clone();
-                if let Some(n) = &name_for_status {
-                    println!("STATUS:STARTING:{}", n);
+            Commands::Start { name, port, detach, daemon, auto_restart, app_dir: _ } => {
+                if port.is_some() {
+                    warn!("--port 参数已弃用, 端口在进程配置中设置");
                 }
+                let process_name = name.clone().unwrap_or_else(|| commands::start::DEFAULT_PROCESS.to_string());
                 commands::handle_start(
                     &workspace,
                     &config,
                     name,
-                    Some(python.to_string()),
-                    port,
                     detach,
                     daemon,
                     auto_restart,
                 ).await?;
-                
-                if let Some(n) = &name_for_status {
-                    if let Ok(state) = crate::state::ProcessState::load(&workspace, n) {
-                        if let Some(pid) = state.pid {
-                            println!("STATUS:STARTED:{}:{}", n, pid);
-                        }
+
+                let pid = ProcessState::load(&workspace, &process_name).ok().and_then(|state| state.pid);
+                if output.is_json() {
+                    output::print_result("start", json!({
+                        "process": process_name,
+                        "status": "running",
+                        "pid": pid,
+                    }));
+                } else {
+                    match pid {
+                        Some(pid) => println!("进程 {} 已启动, PID: {}", process_name, pid),
+                        None => println!("进程 {} 已启动", process_name),
                     }
                 }
                 Ok(())
             }
             Commands::Stop { name, app_dir: _ } => {
-                let name_for_status = name.clone();
-                if let Some(n) = &name_for_status {
-                    println!("STATUS:STOPPING:{}", n);
-                }
+                let process_name = name.clone().unwrap_or_else(|| commands::start::DEFAULT_PROCESS.to_string());
                 commands::handle_stop(
                     &workspace,
                     &config,
                     name,
                 ).await?;
-                
-                if let Some(n) = &name_for_status {
-                    println!("STATUS:STOPPED:{}", n);
+
+                if output.is_json() {
+                    output::print_result("stop", json!({
+                        "process": process_name,
+                        "status": "stopped",
+                    }));
+                } else {
+                    println!("进程 {} 已停止", process_name);
                 }
                 Ok(())
             }
-            Commands::Status { name, python: _, port, app_dir: _ } => {
-                commands::handle_status(
+            Commands::Status { name, port, app_dir: _ } => {
+                if port.is_some() {
+                    warn!("--port 参数已弃用, 健康检查地址在进程配置中设置");
+                }
+                let process_name = name.clone().unwrap_or_else(|| commands::start::DEFAULT_PROCESS.to_string());
+                let running = commands::handle_status(
                     &workspace,
                     &config,
-                    name.clone(),
-                    port,
+                    name,
                 ).await?;
-                
-                if let Some(n) = &name {
-                    if let Ok(state) = crate::state::ProcessState::load(&workspace, n) {
-                        if let Some(pid) = state.pid {
-                            println!("STATUS:RUNNING:{}:{}", n, pid);
-                        } else {
-                            println!("STATUS:STOPPED:{}", n);
-                        }
+
+                let state = ProcessState::load(&workspace, &process_name).ok();
+                let pid = state.as_ref().and_then(|state| state.pid).filter(|_| running);
+                let status = if running {
+                    "running"
+                } else if state.is_some_and(|state| state.stale) {
+                    "stale"
+                } else {
+                    "stopped"
+                };
+                if output.is_json() {
+                    output::print_result("status", json!({
+                        "process": process_name,
+                        "running": running,
+                        "status": status,
+                        "pid": pid,
+                    }));
+                } else {
+                    match pid {
+                        Some(pid) => println!("进程 {} 状态: {}, PID: {}", process_name, status, pid),
+                        None => println!("进程 {} 状态: {}", process_name, status),
+                    }
+                }
+                Ok(())
+            }
+            Commands::Run { name, restart, health, port, env, daemon, save, command } => {
+                let options = commands::run::RunOptions {
+                    name, command, restart, health, port, env, daemon, save,
+                };
+                let definition = commands::handle_run(&workspace, &config, &save_path, options).await?;
+
+                let pid = ProcessState::load(&workspace, &definition.name).ok().and_then(|state| state.pid);
+                if output.is_json() {
+                    output::print_result("run", json!({
+                        "process": definition.name,
+                        "status": "running",
+                        "pid": pid,
+                        "saved": save,
+                    }));
+                } else {
+                    match pid {
+                        Some(pid) => println!("进程 {} 已启动, PID: {}", definition.name, pid),
+                        None => println!("进程 {} 已启动", definition.name),
+                    }
+                    if save {
+                        println!("已保存到配置文件: {}", save_path.display());
                     }
                 }
                 Ok(())
             }
+            Commands::Restart { name, daemon, auto_restart } => {
+                commands::handle_restart(&workspace, &config, name.clone(), daemon, auto_restart).await?;
+
+                let pid = ProcessState::load(&workspace, &name).ok().and_then(|state| state.pid);
+                if output.is_json() {
+                    output::print_result("restart", json!({
+                        "process": name,
+                        "status": "running",
+                        "pid": pid,
+                    }));
+                } else {
+                    match pid {
+                        Some(pid) => println!("进程 {} 已重启, PID: {}", name, pid),
+                        None => println!("进程 {} 已重启", name),
+                    }
+                }
+                Ok(())
+            }
+            Commands::Deploy { name, source, keep, daemon, auto_restart } => {
+                let mode = commands::deploy::RestartMode { daemon, auto_restart };
+                commands::handle_deploy(&workspace, &config, name, &source, keep, mode, output).await
+            }
+            Commands::Rollback { name, to, daemon, auto_restart } => {
+                let mode = commands::deploy::RestartMode { daemon, auto_restart };
+                commands::handle_rollback(&workspace, &config, name, to, mode, output).await
+            }
+            Commands::Proxy { name, listen } => {
+                commands::handle_proxy(&workspace, name, listen).await
+            }
+            Commands::SelfReexec { name, timeout } => {
+                commands::handle_self_reexec(
+                    &workspace,
+                    name,
+                    std::time::Duration::from_secs(timeout),
+                    output,
+                ).await
+            }
+            Commands::Recover { name } => {
+                commands::handle_recover(&workspace, &config, name, output).await
+            }
+            Commands::Resume { name } => {
+                commands::handle_resume(&workspace, name, logger).await
+            }
+            Commands::Prepare { name, force } => {
+                commands::handle_prepare(&workspace, &config, name, force, output).await
+            }
             Commands::Monitor { 
                 process_name, 
                 program, 
@@ -218,6 +417,7 @@ async fn main() -> Result<()> {
                     follow,
                     log_type,
                     date,
+                    output,
                 ).await
             }
             Commands::SystemLogs { follow, date } => {
@@ -227,7 +427,17 @@ async fn main() -> Result<()> {
                     date,
                 ).await
             }
-            Commands::List { .. } => {
+            Commands::Metrics { listen, once } => {
+                commands::handle_metrics(
+                    &workspace,
+                    &config,
+                    listen,
+                    once,
+                    output,
+                ).await
+            }
+            Commands::List { .. } | Commands::Crashes { .. } | Commands::Events { .. } |
+            Commands::Workspace { .. } | Commands::Init { .. } | Commands::Add { .. } => {
                 // 已在前面处理
                 Ok(())
             }
@@ -235,11 +445,22 @@ async fn main() -> Result<()> {
     };
 
     // 等待工作完成或Ctrl+C
-    tokio::select! {
+    let result = tokio::select! {
         result = work => result,
         _ = ctrl_c => {
             info!("正在退出...");
             Ok(())
         }
+    };
+
+    // 退出前尽量发送完缓冲的日志
+    sink_logger.shutdown_sinks();
+    result
+}
+
+/// 各命令自己的 --json 参数已被全局的 --output json 取代
+fn warn_deprecated_json(json: bool) {
+    if json {
+        eprintln!("警告: --json 参数已弃用, 请使用 --output json");
     }
 }
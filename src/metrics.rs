@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use crate::commands::list::get_process_list;
+use crate::events::{EventKind, ProcessEvent};
+use crate::process::backend::{self, ProcessBackend};
+use crate::state::ProcessState;
+use crate::workspace::Workspace;
+
+/// 指标导出配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// 监听地址
+    #[serde(default = "default_metrics_listen")]
+    pub listen: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            listen: default_metrics_listen(),
+        }
+    }
+}
+
+fn default_metrics_listen() -> String {
+    "127.0.0.1:9464".to_string()
+}
+
+/// 单个进程的指标
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProcessMetrics {
+    pub name: String,
+    pub up: bool,
+    pub restarts: u32,
+    /// 最后一次退出的退出码, 被信号终止时为128+信号值
+    pub last_exit_code: Option<i32>,
+    pub uptime_seconds: u64,
+    pub cpu_seconds: Option<f64>,
+    pub resident_memory_bytes: u64,
+    pub open_fds: Option<u64>,
+    /// 最近一次健康检查耗时(秒)
+    pub health_check_duration_seconds: Option<f64>,
+    pub health_check_failures: u64,
+}
+
+/// Prometheus指标导出器
+///
+/// 进程数据来自 `list` 命令同样的采集逻辑, 健康检查失败次数保存在导出器内存中
+#[derive(Clone)]
+pub struct MetricsExporter {
+    workspace: Workspace,
+    client: reqwest::Client,
+    backend: Arc<dyn ProcessBackend>,
+    health_failures: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl MetricsExporter {
+    pub fn new(workspace: Workspace, health_check_timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(health_check_timeout)
+            .build()
+            .unwrap_or_default();
+        Self {
+            workspace,
+            client,
+            backend: backend::native(),
+            health_failures: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 采集所有进程的指标
+    pub async fn collect(&self) -> Result<Vec<ProcessMetrics>> {
+        if !self.workspace.get_processes_dir().exists() {
+            return Ok(Vec::new());
+        }
+        let process_list = get_process_list(&self.workspace, &None, &None).await?;
+
+        let mut metrics = Vec::new();
+        for info in process_list {
+            let up = info.status == "online";
+            let stats = info.pid.parse::<i32>().ok()
+                .filter(|_| up)
+                .and_then(|pid| self.backend.metrics(pid));
+            let mut process = ProcessMetrics {
+                up,
+                restarts: info.restarts,
+                last_exit_code: self.last_exit_code(&info.name),
+                uptime_seconds: info.uptime_seconds,
+                resident_memory_bytes: info.mem_bytes,
+                cpu_seconds: stats.as_ref().map(|stats| stats.cpu_seconds),
+                open_fds: stats.and_then(|stats| stats.open_fds),
+                ..Default::default()
+            };
+
+            if up {
+                let url = ProcessState::load(&self.workspace, &info.name)
+                    .ok()
+                    .and_then(|state| state.health_check_url);
+                if let Some(url) = url {
+                    let started = Instant::now();
+                    let healthy = matches!(
+                        self.client.get(&url).send().await,
+                        Ok(response) if response.status().is_success()
+                    );
+                    process.health_check_duration_seconds = Some(started.elapsed().as_secs_f64());
+                    if !healthy {
+                        warn!("进程 {} 健康检查失败: {}", info.name, url);
+                        *self.health_failures.lock().unwrap().entry(info.name.clone()).or_default() += 1;
+                    }
+                }
+            }
+            process.health_check_failures = self.health_failures.lock().unwrap()
+                .get(&info.name).copied().unwrap_or(0);
+            process.name = info.name;
+            metrics.push(process);
+        }
+        Ok(metrics)
+    }
+
+    /// 从事件日志中读取最后一次退出码
+    fn last_exit_code(&self, name: &str) -> Option<i32> {
+        let events = ProcessEvent::list(&self.workspace, name, None).ok()?;
+        events.iter().rev().find_map(|event| match event.kind {
+            EventKind::Exited { code, signal } => code.or(signal.map(|s| 128 + s)),
+            _ => None,
+        })
+    }
+
+    /// 在指定地址上提供 /metrics 接口, 直到出错退出
+    pub async fn serve(&self, listen: &str) -> Result<()> {
+        let listener = TcpListener::bind(listen).await
+            .with_context(|| format!("监听指标地址 {} 失败", listen))?;
+        info!("指标接口已启动: http://{}/metrics", listener.local_addr()?);
+        self.serve_listener(listener).await
+    }
+
+    async fn serve_listener(&self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, peer) = listener.accept().await.context("接受指标请求失败")?;
+            let exporter = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = exporter.handle_connection(stream).await {
+                    warn!("处理来自 {} 的指标请求失败: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> Result<()> {
+        // 只需要请求行, 读到请求头结束即可
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+            let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf)).await
+                .context("读取请求超时")??;
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        let request = String::from_utf8_lossy(&request);
+        let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+        let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+        let (status, content_type, body) = if method != "GET" {
+            ("405 Method Not Allowed", "text/plain", "method not allowed\n".to_string())
+        } else if path.split('?').next() == Some("/metrics") {
+            match self.collect().await {
+                Ok(metrics) => ("200 OK", "text/plain; version=0.0.4; charset=utf-8", render(&metrics)),
+                Err(e) => ("500 Internal Server Error", "text/plain", format!("{}\n", e)),
+            }
+        } else {
+            ("404 Not Found", "text/plain", "not found\n".to_string())
+        };
+
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, content_type, body.len(), body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+}
+
+/// 按Prometheus文本格式输出指标
+pub fn render(metrics: &[ProcessMetrics]) -> String {
+    let mut out = String::new();
+    let mut family = |name: &str, kind: &str, help: &str, value: &dyn Fn(&ProcessMetrics) -> Option<f64>| {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for process in metrics {
+            if let Some(v) = value(process) {
+                let _ = writeln!(out, "{}{{process=\"{}\"}} {}", name, escape_label(&process.name), v);
+            }
+        }
+    };
+
+    family("fuckrun_process_up", "gauge", "Whether the process is running (1) or not (0).",
+        &|p| Some(if p.up { 1.0 } else { 0.0 }));
+    family("fuckrun_process_restarts_total", "counter", "Number of automatic restarts.",
+        &|p| Some(p.restarts as f64));
+    family("fuckrun_process_last_exit_code", "gauge", "Exit code of the last exit, 128+signal if killed by a signal.",
+        &|p| p.last_exit_code.map(|c| c as f64));
+    family("fuckrun_process_uptime_seconds", "gauge", "Seconds since the process was last started.",
+        &|p| Some(p.uptime_seconds as f64));
+    family("fuckrun_process_cpu_seconds_total", "counter", "Total user and system CPU time in seconds.",
+        &|p| p.cpu_seconds);
+    family("fuckrun_process_resident_memory_bytes", "gauge", "Resident memory size in bytes.",
+        &|p| Some(p.resident_memory_bytes as f64));
+    family("fuckrun_process_open_fds", "gauge", "Number of open file descriptors.",
+        &|p| p.open_fds.map(|n| n as f64));
+    family("fuckrun_process_health_check_duration_seconds", "gauge", "Duration of the last health check in seconds.",
+        &|p| p.health_check_duration_seconds);
+    family("fuckrun_process_health_check_failures_total", "counter", "Failed health checks since the exporter started.",
+        &|p| Some(p.health_check_failures as f64));
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_exposition_format() {
+        let metrics = vec![
+            ProcessMetrics {
+                name: "web".into(),
+                up: true,
+                restarts: 2,
+                last_exit_code: Some(137),
+                uptime_seconds: 60,
+                cpu_seconds: Some(1.5),
+                resident_memory_bytes: 4096,
+                open_fds: Some(12),
+                health_check_duration_seconds: Some(0.25),
+                health_check_failures: 1,
+            },
+            ProcessMetrics { name: "we\"ird".into(), ..Default::default() },
+        ];
+        let text = render(&metrics);
+        assert!(text.contains("# TYPE fuckrun_process_restarts_total counter\n"));
+        assert!(text.contains("fuckrun_process_up{process=\"web\"} 1\n"));
+        assert!(text.contains("fuckrun_process_up{process=\"we\\\"ird\"} 0\n"));
+        assert!(text.contains("fuckrun_process_last_exit_code{process=\"web\"} 137\n"));
+        assert!(text.contains("fuckrun_process_cpu_seconds_total{process=\"web\"} 1.5\n"));
+        // 没有数据的指标不输出样本
+        assert!(!text.contains("fuckrun_process_open_fds{process=\"we\\\"ird\"}"));
+    }
+
+    #[tokio::test]
+    async fn test_serve_metrics_endpoint() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        let state = ProcessState { restart_count: 3, ..Default::default() };
+        state.save(&workspace, "web")?;
+        crate::events::record(&workspace, "web", EventKind::Exited { code: None, signal: Some(9) });
+
+        let exporter = MetricsExporter::new(workspace, Duration::from_secs(1));
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move { exporter.serve_listener(listener).await });
+
+        let body = reqwest::get(format!("http://{}/metrics", addr)).await?.text().await?;
+        assert!(body.contains("fuckrun_process_up{process=\"web\"} 0\n"));
+        assert!(body.contains("fuckrun_process_restarts_total{process=\"web\"} 3\n"));
+        assert!(body.contains("fuckrun_process_last_exit_code{process=\"web\"} 137\n"));
+
+        let response = reqwest::get(format!("http://{}/other", addr)).await?;
+        assert_eq!(response.status().as_u16(), 404);
+        Ok(())
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context};
+use chrono::{DateTime, Local};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// 通知事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    /// 进程异常退出
+    Crash,
+    /// 重启次数达到上限
+    RestartLimit,
+    /// 健康检查失败
+    Unhealthy,
+    /// 故障后恢复正常运行
+    Recovered,
+}
+
+impl NotifyEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotifyEvent::Crash => "crash",
+            NotifyEvent::RestartLimit => "restart_limit",
+            NotifyEvent::Unhealthy => "unhealthy",
+            NotifyEvent::Recovered => "recovered",
+        }
+    }
+}
+
+/// 通知目标
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifyTarget {
+    /// 以POST方式把JSON发送到webhook
+    Webhook {
+        url: String,
+        /// 请求体模板, 支持{{event}}、{{process}}、{{message}}、{{timestamp}}、
+        /// {{exit_code}}、{{signal}}、{{restart_count}}占位符; 不设置时发送完整的通知JSON
+        #[serde(default)]
+        template: Option<String>,
+        /// 额外的请求头
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        /// 只发送这些事件, 为空时发送全部事件
+        #[serde(default)]
+        events: Vec<NotifyEvent>,
+        #[serde(default = "default_notify_timeout_secs")]
+        timeout_secs: u64,
+    },
+    /// 执行本地命令, 通知JSON通过标准输入传入
+    Command {
+        command: String,
+        #[serde(default)]
+        events: Vec<NotifyEvent>,
+        #[serde(default = "default_notify_timeout_secs")]
+        timeout_secs: u64,
+    },
+}
+
+impl NotifyTarget {
+    fn accepts(&self, event: NotifyEvent) -> bool {
+        let events = match self {
+            NotifyTarget::Webhook { events, .. } => events,
+            NotifyTarget::Command { events, .. } => events,
+        };
+        events.is_empty() || events.contains(&event)
+    }
+}
+
+/// 通知配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationsConfig {
+    #[serde(default)]
+    pub targets: Vec<NotifyTarget>,
+    /// 同一进程的同一事件在此时间(秒)内只通知一次
+    #[serde(default = "default_debounce_secs")]
+    pub debounce_secs: u64,
+    /// 故障后重启的进程持续运行多久(秒)视为已恢复
+    #[serde(default = "default_recovered_after_secs")]
+    pub recovered_after_secs: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            debounce_secs: default_debounce_secs(),
+            recovered_after_secs: default_recovered_after_secs(),
+        }
+    }
+}
+
+fn default_notify_timeout_secs() -> u64 {
+    10
+}
+
+fn default_debounce_secs() -> u64 {
+    300
+}
+
+fn default_recovered_after_secs() -> u64 {
+    60
+}
+
+/// 一条通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub event: NotifyEvent,
+    pub process: String,
+    pub timestamp: DateTime<Local>,
+    pub message: String,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub restart_count: Option<u32>,
+}
+
+impl Notification {
+    pub fn new(event: NotifyEvent, process: &str, message: impl Into<String>) -> Self {
+        Self {
+            event,
+            process: process.to_string(),
+            timestamp: Local::now(),
+            message: message.into(),
+            exit_code: None,
+            signal: None,
+            restart_count: None,
+        }
+    }
+
+    /// 附带进程退出状态
+    pub fn with_exit_status(mut self, status: &ExitStatus) -> Self {
+        self.exit_code = status.code();
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            self.signal = status.signal();
+        }
+        self
+    }
+
+    pub fn with_restart_count(mut self, restart_count: u32) -> Self {
+        self.restart_count = Some(restart_count);
+        self
+    }
+
+    /// 按模板生成请求体, 占位符的值会做JSON字符串转义
+    pub fn render(&self, template: &str) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        let values = [
+            ("event", self.event.as_str().to_string()),
+            ("process", self.process.clone()),
+            ("message", self.message.clone()),
+            ("timestamp", self.timestamp.to_rfc3339()),
+            ("exit_code", optional(self.exit_code.map(|c| c.to_string()))),
+            ("signal", optional(self.signal.map(|s| s.to_string()))),
+            ("restart_count", optional(self.restart_count.map(|c| c.to_string()))),
+        ];
+
+        let mut body = template.to_string();
+        for (key, value) in values {
+            let escaped = serde_json::to_string(&value).unwrap_or_default();
+            body = body.replace(&format!("{{{{{}}}}}", key), &escaped[1..escaped.len() - 1]);
+        }
+        body
+    }
+}
+
+/// 通知发送器, 可在多个任务间共享
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    config: NotificationsConfig,
+    client: reqwest::Client,
+    /// (进程名, 事件) -> 上次发送时间
+    last_sent: Arc<Mutex<HashMap<(String, NotifyEvent), Instant>>>,
+}
+
+impl Notifier {
+    pub fn new(config: NotificationsConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            last_sent: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn config(&self) -> &NotificationsConfig {
+        &self.config
+    }
+
+    /// 发送通知到所有匹配的目标, 单个目标失败只记录警告
+    pub async fn notify(&self, notification: &Notification) {
+        if self.config.targets.is_empty() || self.debounced(notification) {
+            return;
+        }
+        info!("发送{}通知: {}", notification.event.as_str(), notification.message);
+
+        for target in &self.config.targets {
+            if !target.accepts(notification.event) {
+                continue;
+            }
+            if let Err(e) = self.send(target, notification).await {
+                warn!("发送通知失败: {}", e);
+            }
+        }
+    }
+
+    /// 在后台发送通知, 不阻塞调用方
+    pub fn notify_in_background(&self, notification: Notification) {
+        if self.config.targets.is_empty() {
+            return;
+        }
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            notifier.notify(&notification).await;
+        });
+    }
+
+    /// 检查是否需要去抖, 不需要时记录本次发送时间
+    fn debounced(&self, notification: &Notification) -> bool {
+        let key = (notification.process.clone(), notification.event);
+        let window = Duration::from_secs(self.config.debounce_secs);
+        let Ok(mut last_sent) = self.last_sent.lock() else {
+            return false;
+        };
+        if let Some(last) = last_sent.get(&key) {
+            if last.elapsed() < window {
+                info!("{}通知在去抖时间内, 跳过: {}", notification.event.as_str(), notification.process);
+                return true;
+            }
+        }
+        last_sent.insert(key, Instant::now());
+        false
+    }
+
+    async fn send(&self, target: &NotifyTarget, notification: &Notification) -> Result<()> {
+        match target {
+            NotifyTarget::Webhook { url, template, headers, timeout_secs, .. } => {
+                let body = match template {
+                    Some(template) => notification.render(template),
+                    None => serde_json::to_string(notification).context("序列化通知失败")?,
+                };
+                let mut request = self.client
+                    .post(url)
+                    .timeout(Duration::from_secs(*timeout_secs))
+                    .header("Content-Type", "application/json")
+                    .body(body);
+                for (key, value) in headers {
+                    request = request.header(key, value);
+                }
+                let response = request.send().await.context("请求webhook失败")?;
+                if !response.status().is_success() {
+                    anyhow::bail!("webhook返回错误状态: {}", response.status());
+                }
+            }
+            NotifyTarget::Command { command, timeout_secs, .. } => {
+                #[cfg(unix)]
+                let mut cmd = {
+                    let mut cmd = Command::new("sh");
+                    cmd.arg("-c").arg(command);
+                    cmd
+                };
+                #[cfg(windows)]
+                let mut cmd = {
+                    let mut cmd = Command::new("cmd");
+                    cmd.arg("/C").arg(command);
+                    cmd
+                };
+                cmd.env("FUCKRUN_NOTIFY_EVENT", notification.event.as_str())
+                   .env("FUCKRUN_PROCESS", &notification.process)
+                   .env("FUCKRUN_MESSAGE", &notification.message)
+                   .stdin(Stdio::piped())
+                   .stdout(Stdio::null())
+                   .stderr(Stdio::null())
+                   .kill_on_drop(true);
+
+                let mut child = cmd.spawn().context("启动通知命令失败")?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    let payload = serde_json::to_vec(notification).context("序列化通知失败")?;
+                    // 命令不读取标准输入时写入会失败, 忽略即可
+                    let _ = stdin.write_all(&payload).await;
+                }
+                let status = tokio::time::timeout(Duration::from_secs(*timeout_secs), child.wait())
+                    .await
+                    .context("通知命令执行超时")?
+                    .context("等待通知命令失败")?;
+                if !status.success() {
+                    anyhow::bail!("通知命令执行失败: {}", status);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// 接收所有HTTP请求, 按到达顺序记录请求内容
+    fn http_stub() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut buf).unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some(pos) = text.find("\r\n\r\n") {
+                        let length = text.lines()
+                            .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                            .unwrap_or(0);
+                        if request.len() >= pos + 4 + length {
+                            break;
+                        }
+                    }
+                }
+                if request.is_empty() {
+                    continue;
+                }
+                // 先记录再响应, 发送方返回时请求一定已经计数
+                received.lock().unwrap().push(String::from_utf8_lossy(&request).to_string());
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            }
+        });
+        (url, requests)
+    }
+
+    #[test]
+    fn test_render_template_escapes_values() {
+        let notification = Notification::new(NotifyEvent::Crash, "web", "exit \"3\"").with_restart_count(2);
+        let body = notification.render(r#"{"text": "{{process}}: {{message}} ({{restart_count}})"}"#);
+        let value: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(value["text"], "web: exit \"3\" (2)");
+    }
+
+    #[tokio::test]
+    async fn test_webhook_filter_and_debounce() {
+        let (url, requests) = http_stub();
+        let notifier = Notifier::new(NotificationsConfig {
+            targets: vec![NotifyTarget::Webhook {
+                url,
+                template: None,
+                headers: HashMap::from([("X-Token".to_string(), "secret".to_string())]),
+                events: vec![NotifyEvent::Crash],
+                timeout_secs: 5,
+            }],
+            ..Default::default()
+        });
+
+        // 不匹配的事件不会发送, 重复的事件被去抖
+        notifier.notify(&Notification::new(NotifyEvent::Recovered, "web", "ok")).await;
+        notifier.notify(&Notification::new(NotifyEvent::Crash, "web", "exit 3")).await;
+        notifier.notify(&Notification::new(NotifyEvent::Crash, "web", "exit 3")).await;
+
+        // 去抖窗口内只发送一次
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert!(request.starts_with("POST /hook"));
+        assert!(request.to_ascii_lowercase().contains("x-token: secret"));
+        let body: serde_json::Value = serde_json::from_str(&request[request.find("\r\n\r\n").unwrap() + 4..]).unwrap();
+        assert_eq!(body["event"], "crash");
+        assert_eq!(body["process"], "web");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let notifier = Notifier::new(NotificationsConfig {
+            targets: vec![NotifyTarget::Command {
+                command: format!("cat > {} && echo $FUCKRUN_NOTIFY_EVENT >> {}", out.display(), out.display()),
+                events: vec![],
+                timeout_secs: 5,
+            }],
+            debounce_secs: 0,
+            ..Default::default()
+        });
+        notifier.notify(&Notification::new(NotifyEvent::RestartLimit, "web", "3/3")).await;
+
+        let content = std::fs::read_to_string(out).unwrap();
+        assert!(content.contains("\"event\":\"restart_limit\""));
+        assert!(content.trim_end().ends_with("restart_limit"));
+    }
+}
@@ -0,0 +1,78 @@
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use crate::error::{self, Failure};
+
+/// JSON输出格式的版本, 字段有不兼容的变化时递增
+pub const OUTPUT_VERSION: u32 = 1;
+
+/// 命令输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// 面向人的文本输出
+    #[default]
+    Text,
+    /// 机器可读的JSON输出, 日志只写入stderr
+    Json,
+}
+
+impl OutputFormat {
+    pub fn is_json(&self) -> bool {
+        *self == OutputFormat::Json
+    }
+}
+
+/// 构造一个JSON文档: 固定包含version、type和command字段, 再合并body中的字段
+pub fn document(command: &str, kind: &str, body: Value) -> Value {
+    let mut doc = json!({
+        "version": OUTPUT_VERSION,
+        "type": kind,
+        "command": command,
+    });
+    if let (Some(doc), Value::Object(body)) = (doc.as_object_mut(), body) {
+        doc.extend(body);
+    }
+    doc
+}
+
+/// 输出命令的结果文档
+pub fn print_result(command: &str, body: Value) {
+    let mut doc = document(command, "result", body);
+    doc["ok"] = Value::Bool(true);
+    println!("{}", doc);
+}
+
+/// 输出失败的结果文档
+pub fn print_error(command: &str, err: &anyhow::Error) {
+    println!("{}", error_document(command, err));
+}
+
+fn error_document(command: &str, err: &anyhow::Error) -> Value {
+    document(command, "result", json!({
+        "ok": false,
+        "error": {
+            "kind": Failure::find(err).map(Failure::kind).unwrap_or("error"),
+            "message": format!("{:#}", err),
+            "exit_code": error::exit_code(err),
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_document() {
+        let err = anyhow::Error::new(Failure::AlreadyRunning("进程已在运行".into()));
+        let doc = error_document("start", &err);
+        assert_eq!(doc["version"], OUTPUT_VERSION);
+        assert_eq!(doc["type"], "result");
+        assert_eq!(doc["command"], "start");
+        assert_eq!(doc["ok"], false);
+        assert_eq!(doc["error"]["kind"], "already_running");
+        assert_eq!(doc["error"]["exit_code"], 4);
+
+        let doc = document("logs", "log", json!({ "line": "hello" }));
+        assert_eq!(doc["line"], "hello");
+    }
+}
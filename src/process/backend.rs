@@ -0,0 +1,830 @@
+/// process/backend.rs
+use std::path::PathBuf;
+use std::sync::Arc;
+use anyhow::Result;
+use super::identity::ProcessIdentity;
+
+/// 发送给进程的信号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// 请求进程退出(Unix下为SIGTERM)
+    Term,
+    /// 强制终止(Unix下为SIGKILL)
+    Kill,
+}
+
+/// 进程资源占用
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessStats {
+    /// 运行时长(秒)
+    pub uptime_secs: u64,
+    /// 累计CPU时间(用户态 + 内核态, 秒)
+    pub cpu_seconds: f64,
+    /// 常驻内存(字节)
+    pub rss_bytes: u64,
+    /// 打开的文件描述符(句柄)数, 平台不支持时为None
+    pub open_fds: Option<u64>,
+}
+
+impl ProcessStats {
+    /// 运行期间的平均CPU使用率, 与ps的%cpu含义相同
+    pub fn cpu_percent(&self) -> f64 {
+        if self.uptime_secs == 0 {
+            0.0
+        } else {
+            self.cpu_seconds / self.uptime_secs as f64 * 100.0
+        }
+    }
+}
+
+/// 平台相关的进程操作
+///
+/// 所有存活检查、终止、端口查找和资源统计都通过这个trait完成,
+/// 测试时可以替换为 `MockBackend`
+pub trait ProcessBackend: Send + Sync {
+    /// 进程是否存在(僵尸进程视为不存在)
+    fn exists(&self, pid: i32) -> bool;
+
+    /// 向进程发送信号
+    fn signal(&self, pid: i32, signal: Signal) -> Result<()>;
+
+    /// 只在PID仍是 `identity` 记录的进程时发送信号, PID已被其他进程复用时返回false
+    ///
+    /// 默认实现先校验再发送, 两步之间仍有很小的竞争窗口
+    fn signal_verified(&self, pid: i32, identity: Option<&ProcessIdentity>, signal: Signal) -> Result<bool> {
+        if identity.is_some_and(|identity| !identity.verify(pid)) {
+            return Ok(false);
+        }
+        self.signal(pid, signal)?;
+        Ok(true)
+    }
+
+    /// 强制终止进程及其所有子孙进程, PID已被其他进程复用时什么都不做
+    fn kill_tree(&self, pid: i32, identity: Option<&ProcessIdentity>) -> Result<()> {
+        // 先收集子孙进程, 父进程被终止后它们会被重新挂到init下
+        let tree = self.descendants(pid);
+        if !self.signal_verified(pid, identity, Signal::Kill)? {
+            return Ok(());
+        }
+        for child in tree {
+            // 子进程可能已经随父进程退出
+            let _ = self.signal(child, Signal::Kill);
+        }
+        Ok(())
+    }
+
+    /// 进程的直接子进程
+    fn children(&self, pid: i32) -> Vec<i32>;
+
+    /// 进程的所有子孙进程
+    fn descendants(&self, pid: i32) -> Vec<i32> {
+        let mut tree = Vec::new();
+        let mut queue = vec![pid];
+        while let Some(parent) = queue.pop() {
+            for child in self.children(parent) {
+                if child != pid && !tree.contains(&child) {
+                    tree.push(child);
+                    queue.push(child);
+                }
+            }
+        }
+        tree
+    }
+
+    /// 进程的资源占用, 进程不存在或无法读取时返回None
+    fn metrics(&self, pid: i32) -> Option<ProcessStats>;
+
+    /// 监听指定TCP端口的进程
+    fn port_owners(&self, port: u16) -> Vec<i32>;
+
+    /// 打开了其中任一文件的进程及其名称
+    fn file_users(&self, paths: &[PathBuf]) -> Vec<(i32, String)>;
+
+    /// 可执行文件名为 `name` 的所有进程(目前只在Windows上用于查找Git进程)
+    #[cfg_attr(not(windows), allow(dead_code))]
+    fn processes_named(&self, name: &str) -> Vec<i32>;
+}
+
+/// 当前平台的默认实现
+pub fn native() -> Arc<dyn ProcessBackend> {
+    #[cfg(target_os = "linux")]
+    {
+        Arc::new(LinuxBackend)
+    }
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        Arc::new(UnixBackend)
+    }
+    #[cfg(windows)]
+    {
+        Arc::new(WindowsBackend)
+    }
+}
+
+/// 基于/proc和nix的Linux实现
+#[cfg(target_os = "linux")]
+pub struct LinuxBackend;
+
+#[cfg(target_os = "linux")]
+impl LinuxBackend {
+    fn read_stat(pid: i32) -> Option<String> {
+        std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()
+    }
+
+    /// 所有进程的(pid, ppid)
+    fn process_table() -> Vec<(i32, i32)> {
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<i32>().ok())
+            .filter_map(|pid| {
+                let stat = Self::read_stat(pid)?;
+                let ppid = super::identity::stat_field(&stat, 4)?.parse().ok()?;
+                Some((pid, ppid))
+            })
+            .collect()
+    }
+
+    /// 进程名(/proc/<pid>/comm)
+    fn comm(pid: i32) -> Option<String> {
+        std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok().map(|comm| comm.trim_end().to_string())
+    }
+
+    fn nix_signal(signal: Signal) -> nix::sys::signal::Signal {
+        match signal {
+            Signal::Term => nix::sys::signal::Signal::SIGTERM,
+            Signal::Kill => nix::sys::signal::Signal::SIGKILL,
+        }
+    }
+
+    /// 进程的父进程PID
+    fn parent(pid: i32) -> Option<i32> {
+        super::identity::stat_field(&Self::read_stat(pid)?, 4)?.parse().ok()
+    }
+
+    /// 打开pidfd后再校验身份, 校验通过时pidfd一定指向记录的进程
+    ///
+    /// 进程不存在或PID已被复用时返回Ok(None), 内核不支持pidfd时返回错误
+    fn pin(pid: i32, identity: Option<&ProcessIdentity>) -> std::io::Result<Option<super::pidfd::PidFd>> {
+        let pidfd = match super::pidfd::PidFd::open(pid) {
+            Ok(pidfd) => pidfd,
+            Err(e) if e.raw_os_error() == Some(nix::libc::ESRCH) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if identity.is_some_and(|identity| !identity.verify(pid)) {
+            return Ok(None);
+        }
+        Ok(Some(pidfd))
+    }
+
+    /// 收集并固定子孙进程: 先打开pidfd, 再确认它仍是对应父进程的子进程
+    fn pin_descendants(&self, pid: i32) -> Vec<(i32, super::pidfd::PidFd)> {
+        let mut tree: Vec<(i32, super::pidfd::PidFd)> = Vec::new();
+        let mut queue = vec![pid];
+        while let Some(parent) = queue.pop() {
+            for child in self.children(parent) {
+                if child == pid || tree.iter().any(|(pinned, _)| *pinned == child) {
+                    continue;
+                }
+                let Ok(pidfd) = super::pidfd::PidFd::open(child) else {
+                    continue;
+                };
+                if Self::parent(child) == Some(parent) {
+                    tree.push((child, pidfd));
+                    queue.push(child);
+                }
+            }
+        }
+        tree
+    }
+
+    /// /proc/net/tcp{,6}中处于LISTEN状态的指定端口的socket inode
+    fn listening_inodes(port: u16) -> Vec<u64> {
+        let mut inodes = Vec::new();
+        for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+            let Ok(content) = std::fs::read_to_string(table) else {
+                continue;
+            };
+            for line in content.lines().skip(1) {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if fields.len() < 10 || fields[3] != "0A" {
+                    continue;
+                }
+                let local_port = fields[1].rsplit(':').next()
+                    .and_then(|p| u16::from_str_radix(p, 16).ok());
+                if local_port == Some(port) {
+                    if let Ok(inode) = fields[9].parse() {
+                        inodes.push(inode);
+                    }
+                }
+            }
+        }
+        inodes
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ProcessBackend for LinuxBackend {
+    fn exists(&self, pid: i32) -> bool {
+        use nix::errno::Errno;
+        use nix::sys::signal::kill;
+        use nix::unistd::Pid;
+
+        if pid <= 0 {
+            return false;
+        }
+        match kill(Pid::from_raw(pid), None) {
+            // 没有权限时进程同样存在
+            Ok(()) | Err(Errno::EPERM) => {}
+            Err(_) => return false,
+        }
+        Self::read_stat(pid)
+            .and_then(|stat| super::identity::stat_field(&stat, 3).map(|state| state != "Z"))
+            .unwrap_or(true)
+    }
+
+    fn signal(&self, pid: i32, signal: Signal) -> Result<()> {
+        use nix::sys::signal::kill;
+        use nix::unistd::Pid;
+
+        let signal = Self::nix_signal(signal);
+        kill(Pid::from_raw(pid), signal)
+            .map_err(|e| anyhow::anyhow!("发送{}信号到进程{}失败: {}", signal, pid, e))
+    }
+
+    fn signal_verified(&self, pid: i32, identity: Option<&ProcessIdentity>, signal: Signal) -> Result<bool> {
+        let pidfd = match Self::pin(pid, identity) {
+            Ok(Some(pidfd)) => pidfd,
+            Ok(None) => return Ok(false),
+            Err(e) => {
+                // 内核不支持pidfd(5.3以前), 退回到先校验再发送
+                log::debug!("打开进程{}的pidfd失败, 改用kill: {}", pid, e);
+                if identity.is_some_and(|identity| !identity.verify(pid)) {
+                    return Ok(false);
+                }
+                self.signal(pid, signal)?;
+                return Ok(true);
+            }
+        };
+        let signal = Self::nix_signal(signal);
+        match pidfd.send_signal(signal) {
+            Ok(()) => Ok(true),
+            Err(e) if e.raw_os_error() == Some(nix::libc::ESRCH) => Ok(false),
+            Err(e) => Err(anyhow::anyhow!("发送{}信号到进程{}失败: {}", signal, pid, e)),
+        }
+    }
+
+    fn kill_tree(&self, pid: i32, identity: Option<&ProcessIdentity>) -> Result<()> {
+        let root = match Self::pin(pid, identity) {
+            Ok(Some(root)) => root,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                log::debug!("打开进程{}的pidfd失败, 改用kill: {}", pid, e);
+                let tree = self.descendants(pid);
+                if !self.signal_verified(pid, identity, Signal::Kill)? {
+                    return Ok(());
+                }
+                for child in tree {
+                    let _ = self.signal(child, Signal::Kill);
+                }
+                return Ok(());
+            }
+        };
+        // 所有进程都通过pidfd终止, 收集之后退出的进程的PID即使被复用也不会误杀
+        let tree = self.pin_descendants(pid);
+        match root.send_signal(nix::sys::signal::Signal::SIGKILL) {
+            Ok(()) => {}
+            Err(e) if e.raw_os_error() == Some(nix::libc::ESRCH) => {}
+            Err(e) => anyhow::bail!("发送SIGKILL信号到进程{}失败: {}", pid, e),
+        }
+        for (_, child) in tree {
+            let _ = child.send_signal(nix::sys::signal::Signal::SIGKILL);
+        }
+        Ok(())
+    }
+
+    fn children(&self, pid: i32) -> Vec<i32> {
+        Self::process_table()
+            .into_iter()
+            .filter(|&(_, ppid)| ppid == pid)
+            .map(|(child, _)| child)
+            .collect()
+    }
+
+    fn metrics(&self, pid: i32) -> Option<ProcessStats> {
+        use nix::unistd::{sysconf, SysconfVar};
+        use super::identity::stat_field;
+
+        let stat = Self::read_stat(pid)?;
+        let ticks = sysconf(SysconfVar::CLK_TCK).ok()?? as f64;
+        let utime: u64 = stat_field(&stat, 14)?.parse().ok()?;
+        let stime: u64 = stat_field(&stat, 15)?.parse().ok()?;
+        let start_time: u64 = stat_field(&stat, 22)?.parse().ok()?;
+
+        let system_uptime: f64 = std::fs::read_to_string("/proc/uptime").ok()?
+            .split_whitespace().next()?
+            .parse().ok()?;
+        let uptime_secs = (system_uptime - start_time as f64 / ticks).max(0.0) as u64;
+
+        let rss_bytes = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()
+            .and_then(|status| {
+                let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+                line.split_whitespace().nth(1)?.parse::<u64>().ok()
+            })
+            .map(|kb| kb * 1024)
+            .unwrap_or(0);
+        let open_fds = std::fs::read_dir(format!("/proc/{}/fd", pid)).ok()
+            .map(|entries| entries.count() as u64);
+
+        Some(ProcessStats {
+            uptime_secs,
+            cpu_seconds: (utime + stime) as f64 / ticks,
+            rss_bytes,
+            open_fds,
+        })
+    }
+
+    fn port_owners(&self, port: u16) -> Vec<i32> {
+        let inodes = Self::listening_inodes(port);
+        if inodes.is_empty() {
+            return Vec::new();
+        }
+        let targets: Vec<String> = inodes.iter().map(|inode| format!("socket:[{}]", inode)).collect();
+
+        let mut owners = Vec::new();
+        for (pid, _) in Self::process_table() {
+            let Ok(fds) = std::fs::read_dir(format!("/proc/{}/fd", pid)) else {
+                continue;
+            };
+            let owns = fds.filter_map(|fd| std::fs::read_link(fd.ok()?.path()).ok())
+                .any(|link| targets.iter().any(|target| link.as_os_str() == target.as_str()));
+            if owns {
+                owners.push(pid);
+            }
+        }
+        owners
+    }
+
+    fn file_users(&self, paths: &[PathBuf]) -> Vec<(i32, String)> {
+        let targets: std::collections::HashSet<PathBuf> = paths.iter()
+            .map(|path| std::fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+            .collect();
+        let mut users = Vec::new();
+        if targets.is_empty() {
+            return users;
+        }
+        for (pid, _) in Self::process_table() {
+            let Ok(fds) = std::fs::read_dir(format!("/proc/{}/fd", pid)) else {
+                continue;
+            };
+            let uses = fds.filter_map(|fd| std::fs::read_link(fd.ok()?.path()).ok())
+                .any(|link| targets.contains(&link));
+            if uses {
+                users.push((pid, Self::comm(pid).unwrap_or_default()));
+            }
+        }
+        users
+    }
+
+    fn processes_named(&self, name: &str) -> Vec<i32> {
+        Self::process_table()
+            .into_iter()
+            .map(|(pid, _)| pid)
+            .filter(|pid| Self::comm(*pid).as_deref() == Some(name))
+            .collect()
+    }
+}
+
+/// 其他Unix平台(如macOS)没有/proc, 退回到ps和lsof
+#[cfg(all(unix, not(target_os = "linux")))]
+pub struct UnixBackend;
+
+#[cfg(all(unix, not(target_os = "linux")))]
+impl UnixBackend {
+    fn ps(args: &[&str]) -> String {
+        std::process::Command::new("ps")
+            .args(args)
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default()
+    }
+
+    fn process_table() -> Vec<(i32, i32)> {
+        Self::ps(&["-A", "-o", "pid=,ppid="])
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                Some((fields.next()?.parse().ok()?, fields.next()?.parse().ok()?))
+            })
+            .collect()
+    }
+}
+
+/// 解析ps的时间格式: [[dd-]hh:]mm:ss[.xx]
+#[cfg(all(unix, not(target_os = "linux")))]
+fn parse_ps_time(time: &str) -> f64 {
+    let (days, rest) = match time.split_once('-') {
+        Some((days, rest)) => (days.parse::<f64>().unwrap_or(0.0), rest),
+        None => (0.0, time),
+    };
+    let seconds = rest.split(':').fold(0.0, |acc, part| acc * 60.0 + part.parse::<f64>().unwrap_or(0.0));
+    days * 86400.0 + seconds
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+impl ProcessBackend for UnixBackend {
+    fn exists(&self, pid: i32) -> bool {
+        use nix::errno::Errno;
+        use nix::sys::signal::kill;
+        use nix::unistd::Pid;
+
+        if pid <= 0 {
+            return false;
+        }
+        matches!(kill(Pid::from_raw(pid), None), Ok(()) | Err(Errno::EPERM))
+            && !Self::ps(&["-p", &pid.to_string(), "-o", "stat="]).trim().starts_with('Z')
+    }
+
+    fn signal(&self, pid: i32, signal: Signal) -> Result<()> {
+        use nix::sys::signal::{kill, Signal as NixSignal};
+        use nix::unistd::Pid;
+
+        let signal = match signal {
+            Signal::Term => NixSignal::SIGTERM,
+            Signal::Kill => NixSignal::SIGKILL,
+        };
+        kill(Pid::from_raw(pid), signal)
+            .map_err(|e| anyhow::anyhow!("发送{}信号到进程{}失败: {}", signal, pid, e))
+    }
+
+    fn children(&self, pid: i32) -> Vec<i32> {
+        Self::process_table()
+            .into_iter()
+            .filter(|&(_, ppid)| ppid == pid)
+            .map(|(child, _)| child)
+            .collect()
+    }
+
+    fn metrics(&self, pid: i32) -> Option<ProcessStats> {
+        let output = Self::ps(&["-p", &pid.to_string(), "-o", "etime=,time=,rss="]);
+        let fields: Vec<&str> = output.split_whitespace().collect();
+        if fields.len() < 3 {
+            return None;
+        }
+        Some(ProcessStats {
+            uptime_secs: parse_ps_time(fields[0]) as u64,
+            cpu_seconds: parse_ps_time(fields[1]),
+            rss_bytes: fields[2].parse::<u64>().unwrap_or(0) * 1024,
+            open_fds: None,
+        })
+    }
+
+    fn port_owners(&self, port: u16) -> Vec<i32> {
+        std::process::Command::new("lsof")
+            .args(["-t", &format!("-iTCP:{}", port), "-sTCP:LISTEN"])
+            .output()
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .filter_map(|line| line.trim().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn file_users(&self, paths: &[PathBuf]) -> Vec<(i32, String)> {
+        if paths.is_empty() {
+            return Vec::new();
+        }
+        // -F pc 每个进程输出 p<pid> 和 c<进程名> 两行
+        let Ok(output) = std::process::Command::new("lsof").arg("-F").arg("pc").args(paths).output() else {
+            return Vec::new();
+        };
+        let mut users = Vec::new();
+        let mut pid = None;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some(value) = line.strip_prefix('p') {
+                pid = value.parse().ok();
+            } else if let (Some(name), Some(pid)) = (line.strip_prefix('c'), pid) {
+                users.push((pid, name.to_string()));
+            }
+        }
+        users
+    }
+
+    fn processes_named(&self, name: &str) -> Vec<i32> {
+        std::process::Command::new("pgrep")
+            .args(["-x", name])
+            .output()
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .filter_map(|line| line.trim().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Windows实现, 通过tasklist/taskkill/netstat/PowerShell完成
+#[cfg(windows)]
+pub struct WindowsBackend;
+
+#[cfg(windows)]
+impl WindowsBackend {
+    fn run(program: &str, args: &[&str]) -> Option<std::process::Output> {
+        std::process::Command::new(program).args(args).output().ok()
+    }
+
+    fn powershell(script: &str) -> String {
+        Self::run("powershell", &["-NoProfile", "-NonInteractive", "-Command", script])
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(windows)]
+impl ProcessBackend for WindowsBackend {
+    fn exists(&self, pid: i32) -> bool {
+        Self::run("tasklist", &["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+            .map(|output| {
+                let output = String::from_utf8_lossy(&output.stdout);
+                output.contains(&format!("\"{}\"", pid))
+            })
+            .unwrap_or(false)
+    }
+
+    fn signal(&self, pid: i32, signal: Signal) -> Result<()> {
+        let pid = pid.to_string();
+        let args: Vec<&str> = match signal {
+            Signal::Term => vec!["/PID", &pid],
+            Signal::Kill => vec!["/F", "/PID", &pid],
+        };
+        let output = std::process::Command::new("taskkill").args(&args).output()
+            .map_err(|e| anyhow::anyhow!("执行 taskkill 命令失败: {}", e))?;
+        if !output.status.success() {
+            anyhow::bail!("终止进程{}失败: {}", pid, String::from_utf8_lossy(&output.stderr));
+        }
+        Ok(())
+    }
+
+    fn kill_tree(&self, pid: i32, identity: Option<&ProcessIdentity>) -> Result<()> {
+        if identity.is_some_and(|identity| !identity.verify(pid)) {
+            return Ok(());
+        }
+        let output = std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .output()
+            .map_err(|e| anyhow::anyhow!("执行 taskkill 命令失败: {}", e))?;
+        if !output.status.success() {
+            anyhow::bail!("终止进程树{}失败: {}", pid, String::from_utf8_lossy(&output.stderr));
+        }
+        Ok(())
+    }
+
+    fn children(&self, pid: i32) -> Vec<i32> {
+        Self::powershell(&format!(
+            "Get-CimInstance Win32_Process -Filter \"ParentProcessId={}\" | ForEach-Object {{ $_.ProcessId }}",
+            pid
+        ))
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .collect()
+    }
+
+    fn metrics(&self, pid: i32) -> Option<ProcessStats> {
+        // 输出整数避免受区域设置的小数点格式影响
+        let output = Self::powershell(&format!(
+            "$p = Get-Process -Id {} -ErrorAction Stop; \
+             Write-Output ([long]((Get-Date) - $p.StartTime).TotalSeconds) \
+             ([long]$p.TotalProcessorTime.TotalMilliseconds) $p.WorkingSet64 $p.HandleCount",
+            pid
+        ));
+        let fields: Vec<u64> = output.split_whitespace().filter_map(|f| f.parse().ok()).collect();
+        if fields.len() < 4 {
+            return None;
+        }
+        Some(ProcessStats {
+            uptime_secs: fields[0],
+            cpu_seconds: fields[1] as f64 / 1000.0,
+            rss_bytes: fields[2],
+            open_fds: Some(fields[3]),
+        })
+    }
+
+    fn port_owners(&self, port: u16) -> Vec<i32> {
+        let Some(output) = Self::run("netstat", &["-ano", "-p", "TCP"]) else {
+            return Vec::new();
+        };
+        let mut owners = Vec::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() >= 5
+                && fields[3] == "LISTENING"
+                && fields[1].rsplit(':').next() == Some(&port.to_string())
+            {
+                if let Ok(pid) = fields[4].parse::<i32>() {
+                    if !owners.contains(&pid) {
+                        owners.push(pid);
+                    }
+                }
+            }
+        }
+        owners
+    }
+
+    fn file_users(&self, paths: &[PathBuf]) -> Vec<(i32, String)> {
+        // 需要Sysinternals的handle.exe, 每行格式为 "<进程名> pid: <pid> type: File ..."
+        let mut users = Vec::new();
+        for path in paths {
+            let Some(output) = Self::run("handle.exe", &[&path.to_string_lossy()]) else {
+                return users;
+            };
+            users.extend(String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| {
+                    let fields: Vec<&str> = line.split_whitespace().collect();
+                    let pid = fields.get(2)?.parse().ok()?;
+                    Some((pid, fields[0].to_string()))
+                }));
+        }
+        users
+    }
+
+    fn processes_named(&self, name: &str) -> Vec<i32> {
+        let Some(output) = Self::run("tasklist", &["/FI", &format!("IMAGENAME eq {}", name), "/NH", "/FO", "CSV"]) else {
+            return Vec::new();
+        };
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split(',').nth(1)?.trim_matches('"').parse().ok())
+            .collect()
+    }
+}
+
+/// 用于测试的内存实现, 不会操作真实进程
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockBackend {
+    inner: std::sync::Mutex<MockState>,
+}
+
+#[cfg(test)]
+#[derive(Default)]
+struct MockState {
+    /// pid -> (ppid, 是否忽略Term信号)
+    processes: std::collections::HashMap<i32, (i32, bool)>,
+    ports: std::collections::HashMap<u16, Vec<i32>>,
+    signals: Vec<(i32, Signal)>,
+}
+
+#[cfg(test)]
+impl MockBackend {
+    /// 添加一个运行中的进程
+    pub fn spawn(&self, pid: i32, ppid: i32) {
+        self.inner.lock().unwrap().processes.insert(pid, (ppid, false));
+    }
+
+    /// 让进程忽略Term信号, 只能被强制终止
+    pub fn ignore_term(&self, pid: i32) {
+        if let Some(process) = self.inner.lock().unwrap().processes.get_mut(&pid) {
+            process.1 = true;
+        }
+    }
+
+    pub fn listen(&self, port: u16, pid: i32) {
+        self.inner.lock().unwrap().ports.entry(port).or_default().push(pid);
+    }
+
+    /// 已发送的信号
+    pub fn signals(&self) -> Vec<(i32, Signal)> {
+        self.inner.lock().unwrap().signals.clone()
+    }
+
+    fn terminate(state: &mut MockState, pid: i32) {
+        state.processes.remove(&pid);
+        for owners in state.ports.values_mut() {
+            owners.retain(|owner| *owner != pid);
+        }
+    }
+}
+
+#[cfg(test)]
+impl ProcessBackend for MockBackend {
+    fn exists(&self, pid: i32) -> bool {
+        self.inner.lock().unwrap().processes.contains_key(&pid)
+    }
+
+    fn signal(&self, pid: i32, signal: Signal) -> Result<()> {
+        let mut state = self.inner.lock().unwrap();
+        let Some(&(_, ignore_term)) = state.processes.get(&pid) else {
+            anyhow::bail!("进程{}不存在", pid);
+        };
+        state.signals.push((pid, signal));
+        if signal == Signal::Kill || !ignore_term {
+            Self::terminate(&mut state, pid);
+        }
+        Ok(())
+    }
+
+    fn children(&self, pid: i32) -> Vec<i32> {
+        self.inner.lock().unwrap().processes.iter()
+            .filter(|(_, &(ppid, _))| ppid == pid)
+            .map(|(&child, _)| child)
+            .collect()
+    }
+
+    fn metrics(&self, pid: i32) -> Option<ProcessStats> {
+        self.exists(pid).then(ProcessStats::default)
+    }
+
+    fn port_owners(&self, port: u16) -> Vec<i32> {
+        self.inner.lock().unwrap().ports.get(&port).cloned().unwrap_or_default()
+    }
+
+    fn file_users(&self, _paths: &[PathBuf]) -> Vec<(i32, String)> {
+        Vec::new()
+    }
+
+    fn processes_named(&self, _name: &str) -> Vec<i32> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kill_tree_kills_descendants() {
+        let backend = MockBackend::default();
+        for (pid, ppid) in [(10, 1), (11, 10), (12, 11), (13, 10), (20, 1)] {
+            backend.spawn(pid, ppid);
+        }
+        backend.kill_tree(10, None).unwrap();
+        for pid in [10, 11, 12, 13] {
+            assert!(!backend.exists(pid));
+        }
+        assert!(backend.exists(20));
+        assert!(backend.signals().iter().all(|(_, signal)| *signal == Signal::Kill));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_linux_backend_real_process() {
+        let backend = LinuxBackend;
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id() as i32;
+        let me = std::process::id() as i32;
+
+        assert!(backend.exists(pid));
+        assert!(backend.children(me).contains(&pid));
+        let stats = backend.metrics(pid).expect("应能读取进程资源占用");
+        assert!(stats.rss_bytes > 0);
+        assert!(stats.open_fds.is_some());
+
+        backend.kill_tree(pid, ProcessIdentity::capture(pid).as_ref()).unwrap();
+        child.wait().unwrap();
+        assert!(!backend.exists(pid));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_linux_backend_skips_reused_pid() {
+        let backend = LinuxBackend;
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id() as i32;
+
+        // 记录的身份与当前进程不符, 视为PID已被复用, 不发送信号
+        let mut identity = ProcessIdentity::capture(pid).unwrap();
+        identity.start_time = identity.start_time.map(|time| time + 1);
+        assert!(!backend.signal_verified(pid, Some(&identity), Signal::Term).unwrap());
+        backend.kill_tree(pid, Some(&identity)).unwrap();
+        assert!(backend.exists(pid));
+
+        assert!(backend.signal_verified(pid, ProcessIdentity::capture(pid).as_ref(), Signal::Kill).unwrap());
+        child.wait().unwrap();
+        assert!(!backend.signal_verified(pid, None, Signal::Kill).unwrap());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_linux_port_owners() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(LinuxBackend.port_owners(port).contains(&(std::process::id() as i32)));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_linux_file_users() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("locked");
+        let _file = std::fs::File::create(&path).unwrap();
+        let me = std::process::id() as i32;
+        let users = LinuxBackend.file_users(&[dir.path().join("other"), path]);
+        assert!(users.iter().any(|(pid, name)| *pid == me && !name.is_empty()));
+        assert!(LinuxBackend.processes_named(&users[0].1).contains(&users[0].0));
+    }
+}
@@ -0,0 +1,518 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{Result, Context};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use tokio::time::sleep;
+use crate::error::Failure;
+use crate::hash;
+use crate::workspace::Workspace;
+use super::backend::{self, Signal};
+
+/// 指定cgroup根目录的环境变量, 没有root权限但有委派的cgroup时使用
+pub const CGROUP_ROOT_ENV: &str = "FUCKRUN_CGROUP_ROOT";
+
+/// 默认的cgroup根目录
+const DEFAULT_ROOT: &str = "/sys/fs/cgroup/fuckrun.slice";
+
+/// 需要在子cgroup中启用的控制器
+const CONTROLLERS: [&str; 4] = ["memory", "cpu", "io", "pids"];
+
+/// cpu.max的周期(微秒)
+const CPU_PERIOD_USEC: u64 = 100_000;
+
+/// 进程资源上限, 通过cgroup v2限制整个进程树
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LimitsConfig {
+    /// 内存上限(memory.max), 如 512M、2G 或字节数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_max: Option<String>,
+    /// 可使用的CPU核数(cpu.max), 如 0.5 表示半个核
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_max: Option<f64>,
+    /// 进程数上限(pids.max)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pids_max: Option<u64>,
+}
+
+impl LimitsConfig {
+    pub fn is_empty(&self) -> bool {
+        self.memory_max.is_none() && self.cpu_max.is_none() && self.pids_max.is_none()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(memory_max) = &self.memory_max {
+            if parse_size(memory_max).is_none() {
+                return Err(Failure::ConfigInvalid(format!("limits.memory_max 不是有效的大小: {}", memory_max)).into());
+            }
+        }
+        if let Some(cpu_max) = self.cpu_max {
+            if !(cpu_max > 0.0 && cpu_max.is_finite()) {
+                return Err(Failure::ConfigInvalid(format!("limits.cpu_max 必须大于0: {}", cpu_max)).into());
+            }
+        }
+        if self.pids_max == Some(0) {
+            return Err(Failure::ConfigInvalid("limits.pids_max 必须大于0".into()).into());
+        }
+        Ok(())
+    }
+
+    /// 内存上限(字节)
+    pub fn memory_max_bytes(&self) -> Option<u64> {
+        parse_size(self.memory_max.as_ref()?)
+    }
+
+    /// 要写入cgroup的控制文件和内容
+    fn files(&self) -> Vec<(&'static str, String)> {
+        let mut files = Vec::new();
+        if let Some(bytes) = self.memory_max_bytes() {
+            files.push(("memory.max", bytes.to_string()));
+        }
+        if let Some(cpu_max) = self.cpu_max {
+            let quota = ((cpu_max * CPU_PERIOD_USEC as f64).round() as u64).max(1000);
+            files.push(("cpu.max", format!("{} {}", quota, CPU_PERIOD_USEC)));
+        }
+        if let Some(pids_max) = self.pids_max {
+            files.push(("pids.max", pids_max.to_string()));
+        }
+        files
+    }
+}
+
+/// 解析 512M、1.5G、1024K、4096 这样的大小, 单位按1024进位
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_ascii_uppercase();
+    let value = value.strip_suffix("IB").or_else(|| value.strip_suffix('B')).unwrap_or(&value);
+    let (number, unit) = match value.chars().last()? {
+        'K' => (&value[..value.len() - 1], 1u64 << 10),
+        'M' => (&value[..value.len() - 1], 1 << 20),
+        'G' => (&value[..value.len() - 1], 1 << 30),
+        'T' => (&value[..value.len() - 1], 1 << 40),
+        _ => (value, 1),
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    if !(number > 0.0 && number.is_finite()) {
+        return None;
+    }
+    Some((number * unit as f64) as u64)
+}
+
+/// 整个进程树的资源占用, 对应控制器未启用的项为None
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CgroupStats {
+    /// 内存(memory.current, 字节)
+    pub memory_bytes: Option<u64>,
+    /// 累计CPU时间(cpu.stat的usage_usec, 微秒)
+    pub cpu_usec: Option<u64>,
+    /// 累计读写字节数(io.stat中所有设备的rbytes和wbytes之和)
+    pub io_bytes: Option<(u64, u64)>,
+    /// 进程数
+    pub pids: u64,
+}
+
+/// 进程独占的cgroup v2目录
+///
+/// 进程和它的所有子孙进程都在这个cgroup中, 可以准确统计整个进程树的资源占用、
+/// 限制资源上限, 并在停止时一次终止所有进程
+#[derive(Debug, Clone)]
+pub struct Cgroup {
+    path: PathBuf,
+    /// 打开时已发生的OOM次数, 之后增加说明进程因内存不足被终止
+    oom_baseline: u64,
+}
+
+impl Cgroup {
+    /// cgroup根目录, 系统不支持cgroup v2时返回None
+    pub fn root() -> Option<PathBuf> {
+        if !cfg!(target_os = "linux") {
+            return None;
+        }
+        let root = std::env::var_os(CGROUP_ROOT_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_ROOT));
+        is_v2_root(&root).then_some(root)
+    }
+
+    /// 进程的cgroup目录, 不同工作区的同名进程互不影响
+    pub fn path_for(root: &Path, workspace: &Workspace, process_name: &str) -> PathBuf {
+        let workspace_root = workspace.get_root_dir().to_string_lossy();
+        // 使用稳定的哈希, 升级FuckRun后仍能找到原来的cgroup
+        root.join(format!("{}-{:08x}", process_name, hash::fnv1a(workspace_root.as_bytes())))
+    }
+
+    /// 创建进程的cgroup并设置资源上限
+    ///
+    /// cgroup不可用或没有写权限时返回None, 进程照常启动, 只是没有整树统计和资源限制
+    pub fn prepare(workspace: &Workspace, process_name: &str, limits: &LimitsConfig) -> Option<Self> {
+        let Some(root) = Self::root() else {
+            if !limits.is_empty() {
+                warn!("系统不支持cgroup v2, 进程 {} 的资源限制不会生效", process_name);
+            }
+            return None;
+        };
+        let cgroup = Self::create(&root, &Self::path_for(&root, workspace, process_name), limits)
+            .and_then(|cgroup| cgroup.check_joinable().map(|()| cgroup));
+        match cgroup {
+            Ok(cgroup) => Some(cgroup),
+            Err(e) => {
+                if limits.is_empty() {
+                    info!("无法创建cgroup, 不统计进程树资源: {:#}", e);
+                } else {
+                    warn!("无法创建cgroup, 进程 {} 的资源限制不会生效: {:#}", process_name, e);
+                }
+                None
+            }
+        }
+    }
+
+    /// 在 `root` 下创建cgroup目录, 启用控制器后写入资源上限
+    ///
+    /// 目录中还有上次运行留下的进程时先终止它们
+    fn create(root: &Path, path: &Path, limits: &LimitsConfig) -> Result<Self> {
+        if !root.exists() {
+            fs::create_dir(root).with_context(|| format!("创建cgroup目录失败: {:?}", root))?;
+        }
+        // 控制器需要从上到下逐级启用, 部分控制器不可用时不影响其他控制器
+        if let Some(parent) = root.parent() {
+            enable_controllers(parent);
+        }
+        enable_controllers(root);
+        if !path.exists() {
+            fs::create_dir(path).with_context(|| format!("创建cgroup目录失败: {:?}", path))?;
+        }
+
+        let cgroup = Self::at(path);
+        let leftover = cgroup.procs();
+        if !leftover.is_empty() {
+            warn!("cgroup {:?} 中还有上次运行留下的进程 {:?}, 将被终止", path, leftover);
+            cgroup.kill()?;
+        }
+        // 限制没有生效时仍然可以统计资源和终止整个进程树
+        if let Err(e) = cgroup.apply_limits(limits) {
+            warn!("{:#}", e);
+        }
+        info!("进程cgroup: {:?}", path);
+        Ok(cgroup)
+    }
+
+    /// 打开进程已有的cgroup, 不存在时返回None
+    pub fn open(workspace: &Workspace, process_name: &str) -> Option<Self> {
+        let root = Self::root()?;
+        let path = Self::path_for(&root, workspace, process_name);
+        path.is_dir().then(|| Self::at(&path))
+    }
+
+    /// 使用指定目录
+    pub fn at(path: &Path) -> Self {
+        let mut cgroup = Self { path: path.to_path_buf(), oom_baseline: 0 };
+        cgroup.oom_baseline = cgroup.oom_kills();
+        cgroup
+    }
+
+    /// 写入资源上限, 没有配置的项恢复为不限制
+    ///
+    /// 某项写入失败时继续写入其他项, 最后返回失败的项
+    pub fn apply_limits(&self, limits: &LimitsConfig) -> Result<()> {
+        let files = limits.files();
+        let mut failed = Vec::new();
+        for file in ["memory.max", "cpu.max", "pids.max"] {
+            let path = self.path.join(file);
+            match files.iter().find(|(name, _)| *name == file) {
+                Some((_, value)) => match fs::write(&path, value) {
+                    Ok(()) => info!("已设置 {} = {}", file, value),
+                    Err(e) => failed.push(format!("{}({})", file, e)),
+                },
+                // 控制器未启用时文件不存在, 本来就不限制
+                None if path.exists() => {
+                    let _ = fs::write(&path, "max");
+                }
+                None => {}
+            }
+        }
+        if !failed.is_empty() {
+            anyhow::bail!("设置资源上限失败, 对应的cgroup控制器可能未启用: {}", failed.join(", "));
+        }
+        Ok(())
+    }
+
+    /// 让子进程在exec前加入当前cgroup, 子进程之后fork的进程都会留在cgroup中
+    #[cfg(unix)]
+    pub fn attach(&self, cmd: &mut tokio::process::Command) {
+        let Some(procs) = self.procs_path() else {
+            return;
+        };
+        // SAFETY: 闭包在fork后的子进程中执行, 只调用open/write/close, 不分配内存。
+        // 传递套接字时3号之后的描述符会被覆盖, 所以在这里打开文件而不是预先打开
+        unsafe {
+            cmd.pre_exec(move || join(&procs));
+        }
+    }
+
+    #[cfg(unix)]
+    fn procs_path(&self) -> Option<std::ffi::CString> {
+        use std::os::unix::ffi::OsStrExt;
+        std::ffi::CString::new(self.path.join("cgroup.procs").as_os_str().as_bytes()).ok()
+    }
+
+    /// 用一个临时子进程确认可以把进程移入cgroup
+    ///
+    /// 移入进程还要求对当前cgroup和目标cgroup的共同祖先有写权限, 目录可写时也可能失败;
+    /// 等到启动时才失败的话进程就无法启动
+    #[cfg(target_os = "linux")]
+    fn check_joinable(&self) -> Result<()> {
+        use nix::sys::wait::{waitpid, WaitStatus};
+        use nix::unistd::{fork, ForkResult};
+
+        let procs = self.procs_path().context("无效的cgroup路径")?;
+        // SAFETY: 子进程只调用open/write/close后立即_exit
+        let child = match unsafe { fork() }.context("创建检查cgroup的子进程失败")? {
+            ForkResult::Child => {
+                let code = match join(&procs) {
+                    Ok(()) => 0,
+                    Err(e) => e.raw_os_error().unwrap_or(nix::libc::EIO),
+                };
+                unsafe { nix::libc::_exit(code) }
+            }
+            ForkResult::Parent { child } => child,
+        };
+        match waitpid(child, None).context("等待检查cgroup的子进程失败")? {
+            WaitStatus::Exited(_, 0) => Ok(()),
+            WaitStatus::Exited(_, code) => anyhow::bail!(
+                "无法把进程移入cgroup {:?}: {}",
+                self.path,
+                std::io::Error::from_raw_os_error(code)
+            ),
+            status => anyhow::bail!("检查cgroup的子进程异常退出: {:?}", status),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn check_joinable(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// cgroup中的所有进程
+    pub fn procs(&self) -> Vec<i32> {
+        fs::read_to_string(self.path.join("cgroup.procs"))
+            .map(|content| content.lines().filter_map(|line| line.trim().parse().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    /// 进程是否在cgroup中
+    pub fn contains(&self, pid: i32) -> bool {
+        self.procs().contains(&pid)
+    }
+
+    /// 整个进程树的资源占用
+    pub fn stats(&self) -> CgroupStats {
+        let memory_bytes = read_u64(&self.path.join("memory.current"));
+        let cpu_usec = read_keyed(&self.path.join("cpu.stat"), "usage_usec");
+        let io_bytes = fs::read_to_string(self.path.join("io.stat"))
+            .ok()
+            .map(|content| parse_io_stat(&content));
+        let pids = read_u64(&self.path.join("pids.current"))
+            .unwrap_or_else(|| self.procs().len() as u64);
+        CgroupStats { memory_bytes, cpu_usec, io_bytes, pids }
+    }
+
+    /// memory.events中记录的OOM终止次数
+    pub fn oom_kills(&self) -> u64 {
+        read_keyed(&self.path.join("memory.events"), "oom_kill").unwrap_or(0)
+    }
+
+    /// 打开cgroup后是否有进程因超出内存上限被终止
+    pub fn oom_killed(&self) -> bool {
+        self.oom_kills() > self.oom_baseline
+    }
+
+    /// 终止cgroup中的所有进程
+    ///
+    /// 优先使用cgroup.kill(Linux 5.14+), 一次终止整个进程树, 期间fork的进程也不会遗漏
+    pub fn kill(&self) -> Result<()> {
+        let kill_file = self.path.join("cgroup.kill");
+        if kill_file.exists() && fs::write(&kill_file, "1").is_ok() {
+            return Ok(());
+        }
+        let backend = backend::native();
+        for pid in self.procs() {
+            if let Err(e) = backend.signal(pid, Signal::Kill) {
+                warn!("终止进程 {} 失败: {}", pid, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// 终止cgroup中剩余的进程并删除cgroup
+    pub async fn kill_and_remove(&self, timeout: Duration) -> Result<()> {
+        let procs = self.procs();
+        if !procs.is_empty() {
+            info!("终止cgroup中剩余的进程: {:?}", procs);
+            self.kill()?;
+            let deadline = tokio::time::Instant::now() + timeout;
+            while !self.procs().is_empty() && tokio::time::Instant::now() < deadline {
+                sleep(Duration::from_millis(50)).await;
+            }
+        }
+        fs::remove_dir(&self.path).with_context(|| format!("删除cgroup失败: {:?}", self.path))?;
+        info!("已删除cgroup: {:?}", self.path);
+        Ok(())
+    }
+}
+
+/// 根目录的上级是否为cgroup v2(混合模式下 /sys/fs/cgroup 是tmpfs, 没有cgroup.controllers)
+fn is_v2_root(root: &Path) -> bool {
+    root.parent().is_some_and(|parent| parent.join("cgroup.controllers").exists())
+}
+
+/// 在cgroup的子目录中启用控制器, 不可用的控制器跳过
+fn enable_controllers(path: &Path) {
+    let subtree_control = path.join("cgroup.subtree_control");
+    let available = fs::read_to_string(path.join("cgroup.controllers")).unwrap_or_default();
+    let enabled = fs::read_to_string(&subtree_control).unwrap_or_default();
+    for controller in CONTROLLERS {
+        let listed = |content: &str| content.split_whitespace().any(|c| c == controller);
+        if listed(&available) && !listed(&enabled) {
+            if let Err(e) = fs::write(&subtree_control, format!("+{}", controller)) {
+                warn!("无法在 {:?} 启用cgroup控制器 {}: {}", path, controller, e);
+            }
+        }
+    }
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// 读取 `key value` 每行一项的文件(cpu.stat、memory.events)中的值
+fn read_keyed(path: &Path, key: &str) -> Option<u64> {
+    fs::read_to_string(path).ok()?
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(' ')?;
+            (name == key).then(|| value.trim().parse().ok()).flatten()
+        })
+}
+
+/// 汇总io.stat中所有设备的读写字节数
+fn parse_io_stat(content: &str) -> (u64, u64) {
+    let mut total = (0, 0);
+    for field in content.split_whitespace() {
+        if let Some((key, value)) = field.split_once('=') {
+            let value: u64 = value.parse().unwrap_or(0);
+            match key {
+                "rbytes" => total.0 += value,
+                "wbytes" => total.1 += value,
+                _ => {}
+            }
+        }
+    }
+    total
+}
+
+/// 把调用者加入 `procs` 指向的cgroup.procs所在的cgroup, 可以在fork后的子进程中调用
+#[cfg(unix)]
+fn join(procs: &std::ffi::CStr) -> std::io::Result<()> {
+    // SAFETY: 只调用open/write/close, 路径是有效的C字符串
+    unsafe {
+        let fd = nix::libc::open(procs.as_ptr(), nix::libc::O_WRONLY | nix::libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let written = nix::libc::write(fd, b"0".as_ptr().cast(), 1);
+        let error = std::io::Error::last_os_error();
+        nix::libc::close(fd);
+        if written < 0 {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_parse_and_validate() {
+        assert_eq!(parse_size("512M"), Some(512 << 20));
+        assert_eq!(parse_size("1.5g"), Some(3 << 29));
+        assert_eq!(parse_size("64KiB"), Some(64 << 10));
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("lots"), None);
+        assert_eq!(parse_size("-1M"), None);
+
+        let limits = LimitsConfig {
+            memory_max: Some("256M".into()),
+            cpu_max: Some(0.5),
+            pids_max: Some(64),
+        };
+        assert!(limits.validate().is_ok());
+        assert_eq!(limits.files(), vec![
+            ("memory.max", (256u64 << 20).to_string()),
+            ("cpu.max", "50000 100000".to_string()),
+            ("pids.max", "64".to_string()),
+        ]);
+        assert!(LimitsConfig { memory_max: Some("1X".into()), ..Default::default() }.validate().is_err());
+        assert!(LimitsConfig { cpu_max: Some(0.0), ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_cgroup_writes_limits_and_reads_stats() -> Result<()> {
+        // 用普通目录模拟cgroup文件系统
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("cgroup.controllers"), "cpu io memory pids")?;
+        let root = dir.path().join("fuckrun.slice");
+        let path = root.join("web-1234");
+        let limits = LimitsConfig { memory_max: Some("1G".into()), pids_max: Some(10), ..Default::default() };
+        fs::create_dir_all(&path)?;
+        fs::write(path.join("memory.events"), "low 0\nhigh 0\nmax 3\noom 1\noom_kill 1\n")?;
+
+        let cgroup = Cgroup::create(&root, &path, &limits)?;
+        assert_eq!(fs::read_to_string(path.join("memory.max"))?, (1u64 << 30).to_string());
+        assert_eq!(fs::read_to_string(path.join("pids.max"))?, "10");
+        assert!(!path.join("cpu.max").exists());
+        assert!(dir.path().join("cgroup.subtree_control").exists());
+
+        fs::write(path.join("cgroup.procs"), "100\n101\n")?;
+        fs::write(path.join("memory.current"), "4096\n")?;
+        fs::write(path.join("cpu.stat"), "usage_usec 2500000\nuser_usec 2000000\nsystem_usec 500000\n")?;
+        fs::write(path.join("io.stat"), "8:0 rbytes=100 wbytes=200 rios=1 wios=2\n8:16 rbytes=1 wbytes=2 rios=1 wios=1\n")?;
+        assert_eq!(cgroup.stats(), CgroupStats {
+            memory_bytes: Some(4096),
+            cpu_usec: Some(2_500_000),
+            io_bytes: Some((101, 202)),
+            pids: 2,
+        });
+        assert!(cgroup.contains(101));
+
+        // 只有打开cgroup之后新增的OOM才算
+        assert!(!cgroup.oom_killed());
+        fs::write(path.join("memory.events"), "oom 2\noom_kill 2\n")?;
+        assert!(cgroup.oom_killed());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_joinable() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cgroup = Cgroup::at(dir.path());
+        // 没有cgroup.procs(无法加入)时不使用这个cgroup
+        let err = cgroup.check_joinable().unwrap_err();
+        assert!(format!("{:#}", err).contains("无法把进程移入cgroup"));
+        fs::write(dir.path().join("cgroup.procs"), "")?;
+        cgroup.check_joinable()?;
+        assert_eq!(fs::read_to_string(dir.path().join("cgroup.procs"))?, "0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_root_requires_cgroup_v2() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("fuckrun.slice");
+        assert!(!is_v2_root(&root));
+        fs::write(dir.path().join("cgroup.controllers"), "memory pids")?;
+        assert!(is_v2_root(&root));
+        Ok(())
+    }
+}
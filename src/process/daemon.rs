@@ -2,13 +2,20 @@
 use anyhow::{Result, Context};
 use log::{info, warn, error};
 use tokio::time::sleep;
+#[cfg(windows)]
 use tokio::process::Command;
+use tokio::process::{ChildStdout, ChildStderr};
 use std::process::Stdio;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::signal::ctrl_c;
 use crate::state::ProcessState;
-use crate::types::ProcessConfig;
+use crate::crash::CrashRecord;
+use crate::events::{self, EventKind};
+use crate::hooks::{self, HookContext, HookEvent};
+use crate::notify::{Notification, NotifyEvent};
+use crate::handoff::{self, Handoff, UpgradeSignal};
+use crate::sockets::ListenSockets;
 
 #[cfg(unix)]
 use nix::{
@@ -16,7 +23,9 @@ use nix::{
     sys::stat::Mode,
 };
 
-use super::manager::ProcessManager;
+use super::cgroup::Cgroup;
+use super::sandbox::{self, SandboxConfig};
+use super::manager::{ProcessManager, OUTPUT_DRAIN_TIMEOUT};
 
 pub trait ProcessManagerDaemonExt {
     /// 守护进程启动
@@ -32,6 +41,14 @@ pub trait ProcessManagerDaemonExt {
     /// 监控进程并自动重启
     fn monitor_and_restart(&self, child: tokio::process::Child)
         -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + '_>>;
+
+    /// 接管升级前的监控进程交接的进程, 继续监控并自动重启
+    fn resume_monitoring(&self, handoff: Handoff)
+        -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + '_>>;
+
+    /// 原监控进程退出后, 启动新的守护进程接管仍在运行的进程
+    fn adopt_daemon(&self, pid: i32)
+        -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + '_>>;
 }
 
 impl ProcessManagerDaemonExt for ProcessManager<'_> {
@@ -45,11 +62,10 @@ impl ProcessManagerDaemonExt for ProcessManager<'_> {
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + '_>> {
         let process_name = self.process_name.clone();
         let auto_restart = self.auto_restart;
-        let program = if cfg!(windows) && program.contains("python.exe") {
-            String::from("py")
-        } else {
-            program.to_string()
-        };
+        let max_restarts = self.max_restarts;
+        let hooks = self.hooks.clone();
+        let notifier = self.notifier.clone();
+        let program = program.to_string();
         let args = args.to_vec();
         let working_dir = working_dir.clone();
         let health_check_url = health_check_url.map(String::from);
@@ -57,6 +73,17 @@ impl ProcessManagerDaemonExt for ProcessManager<'_> {
         let workspace = self.workspace.clone();
         let state = self.state.clone();
         let config = self.config.clone();
+        let log_sinks = self.log_sinks.clone();
+        #[cfg(windows)]
+        let backend = self.backend.clone();
+        #[cfg(unix)]
+        let socket_configs = self.sockets.clone();
+        #[cfg(unix)]
+        let pid_file = self.pid_file.clone();
+        #[cfg(unix)]
+        let limits = self.limits.clone();
+        #[cfg(unix)]
+        let sandbox_config = self.sandbox.clone();
 
         Box::pin(async move {
             info!("以守护进程方式启动...");
@@ -67,11 +94,10 @@ impl ProcessManagerDaemonExt for ProcessManager<'_> {
             tokio::spawn(async move {
                 if let Ok(()) = ctrl_c().await {
                     info!("收到Ctrl+C信号,准备停止进程");
-                    if let Ok(mut state) = ProcessState::load(&workspace_clone, &process_name_clone) {
+                    if let Err(e) = ProcessState::update(&workspace_clone, &process_name_clone, |state| {
                         state.update_stopped_state();
-                        if let Err(e) = state.save(&workspace_clone, &process_name_clone) {
-                            error!("保存进程状态失败: {}", e);
-                        }
+                    }) {
+                        error!("保存进程状态失败: {}", e);
                     }
                     std::process::exit(0);
                 }
@@ -101,7 +127,10 @@ impl ProcessManagerDaemonExt for ProcessManager<'_> {
                     return Err(anyhow::anyhow!("配置文件不存在: {:?}", config_path));
                 }
                 
+                // 显式传入工作区, 监控进程不依赖自身的当前目录查找工作区
                 let mut monitor_args = vec![
+                    "--workspace".to_string(),
+                    workspace.get_root_dir().to_string_lossy().to_string(),
                     "monitor".to_string(),
                     "--process-name".to_string(),
                     process_name.clone(),
@@ -111,23 +140,9 @@ impl ProcessManagerDaemonExt for ProcessManager<'_> {
                     config_path.to_string_lossy().to_string(),
                 ];
                 
-                // 修正Python脚本路径,使用绝对路径
-                let mut args_iter = args.iter().peekable();
-                while let Some(arg) = args_iter.next() {
-                    if arg == "--host" || arg == "--port" {
-                        // 处理参数对
-                        monitor_args.push(arg.clone());
-                        if let Some(value) = args_iter.next() {
-                            monitor_args.push(value.clone());
-                        }
-                    } else if arg.ends_with(".py") {
-                        let script_path = process_dir.join("app.py");
-                        monitor_args.push("--args".to_string());
-                        monitor_args.push(script_path.to_string_lossy().to_string());
-                    } else {
-                        monitor_args.push("--args".to_string());
-                        monitor_args.push(arg.clone());
-                    }
+                // 程序参数已按运行时解析, 原样传给监控进程; 用 --args=值 的写法, 以-开头的参数也不会被当成选项
+                for arg in &args {
+                    monitor_args.push(format!("--args={}", arg));
                 }
 
                 // 使用应用程序目录作为工作目录
@@ -167,24 +182,21 @@ impl ProcessManagerDaemonExt for ProcessManager<'_> {
                 info!("Windows监控进程已启动, PID: {}", pid);
 
                 // 保存monitor进程ID到状态文件
-                let mut current_state = ProcessState::load(&workspace, &process_name)
-                    .unwrap_or_else(|_| state.clone());
-                current_state.monitor_pid = Some(pid as i32);
-                current_state.save(&workspace, &process_name)?;
+                ProcessState::update(&workspace, &process_name, |current_state| {
+                    if current_state.program.is_empty() {
+                        *current_state = state.clone();
+                    }
+                    current_state.set_monitor_pid(pid as i32);
+                    current_state.auto_restart = Some(auto_restart);
+                    current_state.daemon = Some(true);
+                })?;
 
                 // 等待进程初始化
                 info!("等待进程初始化({:?}秒)...", config.init_wait_secs);
                 sleep(std::time::Duration::from_secs(config.init_wait_secs)).await;
 
                 // 检查监控进程是否存活
-                let output = Command::new("tasklist")
-                    .args(["/FI", &format!("PID eq {}", pid), "/NH"])
-                    .output()
-                    .await
-                    .context("检查监控进程状态失败")?;
-
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                if !output_str.is_empty() && !output_str.contains("No tasks") {
+                if backend.exists(pid as i32) {
                     info!("监控进程正在运行");
                     
                     // 进行健康检查
@@ -215,7 +227,12 @@ impl ProcessManagerDaemonExt for ProcessManager<'_> {
                                 sleep(config.retry_interval()).await;
                             }
                         }
-                        return Err(anyhow::anyhow!("健康检查失败"));
+                        notifier.notify(&Notification::new(
+                            NotifyEvent::Unhealthy,
+                            &process_name,
+                            format!("健康检查{}次均失败: {}", config.health_check_retries, url),
+                        )).await;
+                        return Err(crate::error::Failure::HealthCheckFailed("健康检查失败".into()).into());
                     }
                     return Ok(());
                 } else {
@@ -225,72 +242,114 @@ impl ProcessManagerDaemonExt for ProcessManager<'_> {
 
             #[cfg(unix)]
             {
-                use nix::unistd::{fork, ForkResult, setsid};
-                use nix::sys::stat::Mode;
-                // ☆ 去掉对 /dev/null 的多余 File::create 调用，改用 stdin 重定向：
-                // 第一次fork
-                match unsafe { fork() } {
-                    Ok(ForkResult::Parent { child }) => {
-                        info!("守护进程第一次fork成功, 子进程PID: {}", child);
-                        // 父进程直接返回
-                        return Ok(());
+                // 在fork前绑定套接字, 绑定失败时直接返回给调用方; 守护进程一直持有, 自动重启时继续使用
+                let sockets = std::sync::Arc::new(crate::sockets::ListenSockets::bind(&socket_configs)?);
+                let daemon_dir = working_dir.clone();
+                daemonize(&daemon_dir, move || async move {
+                    // 启动实际进程; 自动重启时守护进程一直读取输出, 与重启后的进程一样写入日志和崩溃记录,
+                    // 否则守护进程随即退出, 管道会在进程写输出时断开
+                    let output = || if auto_restart { Stdio::piped() } else { Stdio::null() };
+                    let mut cmd = sockets.command(&program, &args);
+                    cmd.current_dir(&working_dir)
+                       .stdout(output())
+                       .stderr(output())
+                       .stdin(Stdio::null()); // 确保无阻塞地脱离终端
+
+                    if let Some(vars) = &env_vars {
+                        cmd.envs(vars);
                     }
-                    Ok(ForkResult::Child) => {
-                        setsid()?;
-                        std::env::set_current_dir(&working_dir)?;
-                        // 第二次fork
-                        match unsafe { fork() } {
-                            Ok(ForkResult::Parent { child }) => {
-                                info!("守护进程第二次fork成功, 最终进程PID: {}", child);
-                                std::process::exit(0);
-                            }
-                            Ok(ForkResult::Child) => {
-                                // 设置umask
-                                nix::sys::stat::umask(Mode::empty());
-
-                                // 启动实际进程
-                                let mut cmd = Command::new(&program);
-                                cmd.args(&args)
-                                   .current_dir(&working_dir)
-                                   .stdout(Stdio::null())
-                                   .stderr(Stdio::null())
-                                   .stdin(Stdio::null()); // 确保无阻塞地脱离终端
-
-                                if let Some(vars) = env_vars {
-                                    cmd.envs(vars);
-                                }
 
-                                let mut child = cmd.spawn()?;
-                                let pid = child.id().unwrap() as i32;
-
-                                // 保存进程状态
-                                let mut state = state.clone();
-                                state.pid = Some(pid);
-                                state.program = program.clone();
-                                state.args = args.clone();
-                                state.working_dir = working_dir.clone();
-                                state.health_check_url = health_check_url.clone();
-                                state.save(&workspace, &process_name)?;
-
-                                if auto_restart {
-                                    let monitor = ProcessManager {
-                                        state,
-                                        config: ProcessConfig::default(),
-                                        workspace: &workspace,
-                                        process_name: process_name.clone(),
-                                        daemon_mode: false,
-                                        auto_restart,
-                                    };
-                                    monitor.monitor_and_restart(child).await?;
-                                }
+                    let pid_file_path = pid_file.as_ref().map(|pid_file| working_dir.join(pid_file));
+                    if let Some(path) = &pid_file_path {
+                        super::manager::remove_stale_pid_file(path)?;
+                    }
 
-                                Ok(())
+                    // 进程和它fork的所有进程都放在独立的cgroup中
+                    if let Some(cgroup) = Cgroup::prepare(&workspace, &process_name, &limits) {
+                        cgroup.attach(&mut cmd);
+                    }
+                    sandbox::apply(&sandbox_config, &mut cmd, &working_dir)?;
+
+                    #[cfg(target_os = "linux")]
+                    let existing_children = super::subreaper::children();
+                    let mut child = cmd.spawn()?;
+                    let pid = child.id().unwrap() as i32;
+
+                    // 保存进程状态
+                    let state = ProcessState::update(&workspace, &process_name, |current_state| {
+                        if current_state.program.is_empty() {
+                            *current_state = state.clone();
+                        }
+                        current_state.set_pid(pid);
+                        current_state.program = program.clone();
+                        current_state.args = args.clone();
+                        current_state.working_dir = working_dir.clone();
+                        current_state.health_check_url = health_check_url.clone();
+                        current_state.auto_restart = Some(auto_restart);
+                        current_state.daemon = Some(true);
+                        // 当前进程负责监控和重启
+                        if auto_restart {
+                            current_state.set_monitor_pid(std::process::id() as i32);
+                        }
+                    })?;
+                    events::record(&workspace, &process_name, EventKind::Started { pid });
+
+                    // 程序自己fork到后台时, 等待PID文件并改为监控其中的进程
+                    let mut daemonized = None;
+                    if let Some(path) = &pid_file_path {
+                        let daemon_pid = match super::manager::wait_for_pid_file(path, &mut child, config.pid_file_timeout()).await {
+                            Ok(daemon_pid) => daemon_pid,
+                            Err(e) => {
+                                ProcessState::update(&workspace, &process_name, |current_state| {
+                                    current_state.update_stopped_state();
+                                })?;
+                                return Err(e);
                             }
-                            Err(e) => Err(anyhow::anyhow!("Second fork failed: {}", e)),
+                        };
+                        info!("进程已转入后台运行, PID: {}", daemon_pid);
+                        ProcessState::update(&workspace, &process_name, |current_state| {
+                            current_state.set_pid(daemon_pid);
+                        })?;
+                        events::record(&workspace, &process_name, EventKind::Daemonized { launcher: pid, pid: daemon_pid });
+                        daemonized = Some(daemon_pid);
+                    }
+
+                    if auto_restart {
+                        let mut monitor = ProcessManager::with_config(
+                            &workspace,
+                            process_name.clone(),
+                            config.clone(),
+                        );
+                        monitor.state = state;
+                        monitor.set_auto_restart(auto_restart);
+                        monitor.max_restarts = max_restarts;
+                        monitor.set_hooks(hooks);
+                        monitor.set_notifier(notifier);
+                        monitor.set_log_sinks(log_sinks);
+                        monitor.set_env_vars(env_vars.unwrap_or_default());
+                        monitor.listen_sockets = sockets.clone();
+                        monitor.set_pid_file(pid_file);
+                        monitor.set_limits(limits);
+                        monitor.set_sandbox(sandbox_config);
+                        #[cfg(target_os = "linux")]
+                        {
+                            monitor.existing_children = Some(existing_children);
+                        }
+                        match daemonized {
+                            Some(daemon_pid) => {
+                                // 启动程序已退出, 交给tokio在后台回收; 后台进程继承了输出管道
+                                let stdout = child.stdout.take();
+                                let stderr = child.stderr.take();
+                                drop(child);
+                                let child = Supervised::daemonized(daemon_pid).await;
+                                monitor.supervise(child, stdout, stderr, std::time::Instant::now(), false).await?;
+                            }
+                            None => monitor.monitor_and_restart(child).await?,
                         }
                     }
-                    Err(e) => Err(anyhow::anyhow!("First fork failed: {}", e)),
-                }
+
+                    Ok(())
+                })
             }
         })
     }
@@ -298,103 +357,648 @@ impl ProcessManagerDaemonExt for ProcessManager<'_> {
     fn monitor_and_restart(
         &self,
         mut child: tokio::process::Child
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + '_>> {
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        self.supervise(Supervised::Spawned(child), stdout, stderr, std::time::Instant::now(), false)
+    }
+
+    fn resume_monitoring(
+        &self,
+        handoff: Handoff,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + '_>> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::{FromRawFd, OwnedFd};
+
+            info!("接管进程 {}, PID: {}", handoff.process_name, handoff.pid);
+            // SAFETY: 描述符由旧程序在exec前保留并记录在交接文件中, 只在这里接管一次
+            let stdout = handoff.stdout_fd
+                .map(|fd| tokio::process::ChildStdout::from_std(unsafe { OwnedFd::from_raw_fd(fd) }.into()))
+                .transpose();
+            let stderr = handoff.stderr_fd
+                .map(|fd| tokio::process::ChildStderr::from_std(unsafe { OwnedFd::from_raw_fd(fd) }.into()))
+                .transpose();
+            let (stdout, stderr) = match (stdout, stderr) {
+                (Ok(stdout), Ok(stderr)) => (stdout, stderr),
+                (Err(e), _) | (_, Err(e)) => {
+                    return Box::pin(async move { Err(anyhow::Error::new(e).context("接管进程输出失败")) });
+                }
+            };
+
+            for line in &handoff.output_tail {
+                self.output_tail.push(&line.stream, &line.line);
+            }
+            let elapsed = (chrono::Local::now() - handoff.started_at).to_std().unwrap_or_default();
+            let started_at = std::time::Instant::now()
+                .checked_sub(elapsed)
+                .unwrap_or_else(std::time::Instant::now);
+            let child = if handoff.orphan {
+                Supervised::orphan(handoff.pid)
+            } else {
+                Supervised::Adopted(handoff.pid)
+            };
+            self.supervise(child, stdout, stderr, started_at, handoff.awaiting_recovery)
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = handoff;
+            Box::pin(async { Err(anyhow::anyhow!("监控进程在线升级只支持Unix系统")) })
+        }
+    }
+
+    fn adopt_daemon(
+        &self,
+        pid: i32,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + '_>> {
         let process_name = self.process_name.clone();
         let auto_restart = self.auto_restart;
+        let max_restarts = self.max_restarts;
+        let hooks = self.hooks.clone();
+        let notifier = self.notifier.clone();
         let workspace = self.workspace.clone();
         let state = self.state.clone();
+        let config = self.config.clone();
+        let log_sinks = self.log_sinks.clone();
+        let env_vars = self.env_vars.clone();
+        let uptime = self.backend.metrics(pid).map(|stats| stats.uptime_secs);
+        let pid_file = self.pid_file.clone();
+        let limits = self.limits.clone();
+        let sandbox_config = self.sandbox.clone();
+        #[cfg(unix)]
+        let socket_configs = self.sockets.clone();
 
         Box::pin(async move {
-            if let Some(pid) = child.id() {
+            info!("接管进程 {}, PID: {}", process_name, pid);
+
+            #[cfg(unix)]
+            {
+                // 进程仍持有原来的监听套接字, 端口被占用时重启后不再传递套接字
+                let sockets = crate::sockets::ListenSockets::bind(&socket_configs)
+                    .unwrap_or_else(|e| {
+                        warn!("无法重新绑定监听套接字, 重启时不再传递: {:#}", e);
+                        crate::sockets::ListenSockets::default()
+                    });
+                let working_dir = state.working_dir.clone();
+                daemonize(&working_dir, move || async move {
+                    let monitor_pid = std::process::id() as i32;
+                    let state = ProcessState::update(&workspace, &process_name, |current_state| {
+                        current_state.set_monitor_pid(monitor_pid);
+                    })?;
+                    events::record(&workspace, &process_name, EventKind::Adopted { pid, monitor_pid });
+
+                    let mut monitor = ProcessManager::with_config(&workspace, process_name.clone(), config);
+                    monitor.state = state;
+                    monitor.set_auto_restart(auto_restart);
+                    monitor.max_restarts = max_restarts;
+                    monitor.set_hooks(hooks);
+                    monitor.set_notifier(notifier);
+                    monitor.set_log_sinks(log_sinks);
+                    monitor.set_env_vars(env_vars);
+                    monitor.set_pid_file(pid_file);
+                    monitor.set_limits(limits);
+                    monitor.set_sandbox(sandbox_config);
+                    monitor.listen_sockets = std::sync::Arc::new(sockets);
+
+                    let stdout = reopen_output_pipe(pid, 1)
+                        .map(|fd| tokio::process::ChildStdout::from_std(fd.into()))
+                        .transpose()?;
+                    let stderr = reopen_output_pipe(pid, 2)
+                        .map(|fd| tokio::process::ChildStderr::from_std(fd.into()))
+                        .transpose()?;
+                    let started_at = uptime
+                        .and_then(|secs| std::time::Instant::now().checked_sub(std::time::Duration::from_secs(secs)))
+                        .unwrap_or_else(std::time::Instant::now);
+                    monitor.supervise(Supervised::orphan(pid), stdout, stderr, started_at, false).await
+                })
+            }
+            #[cfg(not(unix))]
+            {
+                let _ = (auto_restart, max_restarts, hooks, notifier, workspace, state, config, log_sinks, env_vars, uptime, pid_file, limits, sandbox_config);
+                Err(anyhow::anyhow!("接管进程只支持Unix系统"))
+            }
+        })
+    }
+}
+
+/// 以双重fork脱离终端, 在守护进程中执行 `body`
+///
+/// 调用方进程直接返回Ok, 守护进程执行完 `body` 后退出, 不会返回到调用方的命令输出逻辑
+#[cfg(unix)]
+fn daemonize<F, Fut>(working_dir: &Path, body: F) -> Result<()>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<()>>,
+{
+    // 第一次fork
+    match unsafe { fork() } {
+        Ok(ForkResult::Parent { child }) => {
+            info!("守护进程第一次fork成功, 子进程PID: {}", child);
+            // 父进程直接返回
+            Ok(())
+        }
+        Ok(ForkResult::Child) => {
+            setsid()?;
+            std::env::set_current_dir(working_dir)?;
+            // 第二次fork
+            match unsafe { fork() } {
+                Ok(ForkResult::Parent { child }) => {
+                    info!("守护进程第二次fork成功, 最终进程PID: {}", child);
+                    std::process::exit(0);
+                }
+                Ok(ForkResult::Child) => {
+                    // fork后只剩当前线程, 原运行时的工作线程和事件驱动已不存在,
+                    // 在新线程上创建单线程运行时执行守护逻辑, 否则等待子进程退出会永远阻塞
+                    let daemon = std::thread::spawn(move || -> Result<()> {
+                        let runtime = tokio::runtime::Builder::new_current_thread()
+                            .enable_all()
+                            .build()
+                            .context("创建守护进程运行时失败")?;
+                        runtime.block_on(async move {
+                            // 设置umask
+                            nix::sys::stat::umask(Mode::empty());
+                            #[cfg(target_os = "linux")]
+                            super::subreaper::start();
+                            body().await
+                        })
+                    });
+                    let result = daemon.join()
+                        .unwrap_or_else(|_| Err(anyhow::anyhow!("守护进程线程异常退出")));
+                    if let Err(e) = &result {
+                        error!("守护进程异常退出: {:?}", e);
+                    }
+                    std::process::exit(if result.is_ok() { 0 } else { 1 });
+                }
+                Err(e) => Err(anyhow::anyhow!("Second fork failed: {}", e)),
+            }
+        }
+        Err(e) => Err(anyhow::anyhow!("First fork failed: {}", e)),
+    }
+}
+
+/// 重新打开孤儿进程的输出管道
+///
+/// 原监控进程退出后管道没有了读端, 进程再写输出会收到SIGPIPE; 只要进程还持有管道,
+/// 通过 `/proc/<pid>/fd/<n>` 就能打开一个新的读端继续读取
+#[cfg(target_os = "linux")]
+fn reopen_output_pipe(pid: i32, fd: i32) -> Option<std::os::unix::io::OwnedFd> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let path = format!("/proc/{}/fd/{}", pid, fd);
+    let target = std::fs::read_link(&path).ok()?;
+    if !target.to_string_lossy().starts_with("pipe:") {
+        return None;
+    }
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(nix::libc::O_NONBLOCK)
+        .open(&path)
+        .map_err(|e| warn!("重新打开进程 {} 的输出管道失败: {}", pid, e))
+        .ok()
+        .map(Into::into)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn reopen_output_pipe(_pid: i32, _fd: i32) -> Option<std::os::unix::io::OwnedFd> {
+    None
+}
+
+/// 被监控的进程
+enum Supervised {
+    /// 当前程序启动的子进程
+    Spawned(tokio::process::Child),
+    /// 升级前由旧程序启动的子进程, exec后仍是当前进程的子进程, 但不归tokio管理
+    #[cfg(unix)]
+    Adopted(i32),
+    /// 原监控进程退出后接管的孤儿进程, 不是当前进程的子进程, 无法获得退出状态
+    #[cfg(unix)]
+    Orphan {
+        pid: i32,
+        #[cfg(target_os = "linux")]
+        pidfd: Option<super::pidfd::PidFd>,
+    },
+}
+
+impl Supervised {
+    /// 接管不是子进程的进程, Linux下优先用pidfd等待退出
+    #[cfg(unix)]
+    fn orphan(pid: i32) -> Self {
+        Supervised::Orphan {
+            pid,
+            #[cfg(target_os = "linux")]
+            pidfd: super::pidfd::PidFd::open(pid)
+                .map_err(|e| warn!("打开进程 {} 的pidfd失败, 改为轮询: {}", pid, e))
+                .ok(),
+        }
+    }
+
+    /// 接管程序fork到后台后留下的进程
+    ///
+    /// 成为子进程收割者时, 启动程序退出后留下的进程会挂到当前进程下, 可以获得退出状态;
+    /// 否则只能按孤儿进程等待
+    #[cfg(unix)]
+    async fn daemonized(pid: i32) -> Self {
+        #[cfg(target_os = "linux")]
+        if super::subreaper::is_enabled() {
+            let backend = super::backend::native();
+            let me = std::process::id() as i32;
+            for _ in 0..20 {
+                if backend.children(me).contains(&pid) {
+                    return Supervised::Adopted(pid);
+                }
+                sleep(std::time::Duration::from_millis(50)).await;
+            }
+        }
+        Supervised::orphan(pid)
+    }
+
+    fn id(&self) -> Option<i32> {
+        match self {
+            Supervised::Spawned(child) => child.id().map(|pid| pid as i32),
+            #[cfg(unix)]
+            Supervised::Adopted(pid) => Some(*pid),
+            #[cfg(unix)]
+            Supervised::Orphan { pid, .. } => Some(*pid),
+        }
+    }
+
+    async fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
+        match self {
+            Supervised::Spawned(child) => child.wait().await,
+            #[cfg(unix)]
+            Supervised::Adopted(pid) => wait_adopted(*pid).await,
+            #[cfg(unix)]
+            #[cfg(target_os = "linux")]
+            Supervised::Orphan { pidfd: Some(pidfd), .. } => {
+                pidfd.exited().await?;
+                Ok(unknown_exit_status())
+            }
+            #[cfg(unix)]
+            Supervised::Orphan { pid, .. } => {
+                while super::backend::native().exists(*pid) {
+                    sleep(std::time::Duration::from_millis(500)).await;
+                }
+                Ok(unknown_exit_status())
+            }
+        }
+    }
+}
+
+/// 无法获得退出状态时使用的状态
+///
+/// 只有父进程能收集退出状态, 接管的孤儿进程退出后按异常退出处理, 让重启策略生效。
+/// 0x7f既不是正常退出也不是被信号终止, `code()` 和 `signal()` 都返回None
+#[cfg(unix)]
+pub(crate) fn unknown_exit_status() -> std::process::ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    std::process::ExitStatus::from_raw(0x7f)
+}
+
+/// 等待接管的子进程退出
+///
+/// 用WNOHANG轮询waitpid, 在select中被取消也不会丢失退出状态
+#[cfg(unix)]
+async fn wait_adopted(pid: i32) -> std::io::Result<std::process::ExitStatus> {
+    use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+    use nix::unistd::Pid;
+    use std::os::unix::process::ExitStatusExt;
+
+    loop {
+        match waitpid(Pid::from_raw(pid), Some(WaitPidFlag::WNOHANG))? {
+            WaitStatus::Exited(_, code) => {
+                return Ok(std::process::ExitStatus::from_raw((code & 0xff) << 8));
+            }
+            WaitStatus::Signaled(_, signal, core_dumped) => {
+                let core = if core_dumped { 0x80 } else { 0 };
+                return Ok(std::process::ExitStatus::from_raw(signal as i32 | core));
+            }
+            _ => sleep(std::time::Duration::from_millis(200)).await,
+        }
+    }
+}
+
+/// 把监控交给新程序: 写入交接文件并exec, 成功时不会返回
+///
+/// exec失败时删除交接文件并恢复描述符的 `FD_CLOEXEC`, 由当前程序继续监控
+#[cfg(unix)]
+fn reexec(workspace: &crate::workspace::Workspace, mut handoff: Handoff) -> Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let exe = Handoff::take_request(workspace, &handoff.process_name)?;
+    info!("准备exec新程序: {:?}", exe);
+
+    // 输出管道可能已随进程退出关闭, 丢弃即可; 套接字必须全部保留
+    handoff.stdout_fd = handoff.stdout_fd.filter(|fd| handoff::inherit_fd(*fd, false));
+    handoff.stderr_fd = handoff.stderr_fd.filter(|fd| handoff::inherit_fd(*fd, false));
+    let inherited: Vec<i32> = handoff.stdout_fd.into_iter()
+        .chain(handoff.stderr_fd)
+        .chain(handoff.socket_fds.iter().copied().filter(|fd| handoff::inherit_fd(*fd, true)))
+        .collect();
+    let restore = |fds: &[i32]| fds.iter().for_each(|fd| handoff::close_on_exec(*fd));
+    if inherited.len() < handoff.socket_fds.len()
+        + handoff.stdout_fd.iter().count()
+        + handoff.stderr_fd.iter().count()
+    {
+        restore(&inherited);
+        anyhow::bail!("监听套接字已失效, 无法交接");
+    }
+
+    if let Err(e) = handoff.save(workspace) {
+        restore(&inherited);
+        return Err(e);
+    }
+
+    let args = [
+        exe.as_os_str().as_bytes(),
+        b"--workspace",
+        workspace.get_root_dir().as_os_str().as_bytes(),
+        b"resume",
+        handoff.process_name.as_bytes(),
+    ];
+    let args = args.iter()
+        .map(|arg| CString::new(*arg))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .context("程序参数包含空字符")?;
+    let err = nix::unistd::execv(&args[0], &args).unwrap_err();
+
+    let _ = std::fs::remove_file(Handoff::path(workspace, &handoff.process_name));
+    restore(&inherited);
+    Err(anyhow::anyhow!("exec新程序失败: {}", err))
+}
+
+impl ProcessManager<'_> {
+    /// 监控进程退出并按重启策略重启, 收到升级信号时把监控交给新程序
+    fn supervise(
+        &self,
+        mut child: Supervised,
+        stdout: Option<tokio::process::ChildStdout>,
+        stderr: Option<tokio::process::ChildStderr>,
+        mut started_at: std::time::Instant,
+        mut awaiting_recovery: bool,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + '_>> {
+        let process_name = self.process_name.clone();
+        let auto_restart = self.auto_restart;
+        let max_restarts = self.max_restarts;
+        let workspace = self.workspace.clone();
+        let state = self.state.clone();
+        let config = self.config.clone();
+        let log_sinks = self.log_sinks.clone();
+        let env_vars = self.env_vars.clone();
+        let tail = self.output_tail.clone();
+        let max_crash_records = self.config.max_crash_records;
+        let hooks = self.hooks.clone();
+        let notifier = self.notifier.clone();
+        let sockets = self.listen_sockets.clone();
+        let pid_file = self.pid_file.clone();
+        let limits = self.limits.clone();
+        let sandbox_config = self.sandbox.clone();
+
+        Box::pin(async move {
+            let recovered_after = std::time::Duration::from_secs(notifier.config().recovered_after_secs);
+            let mut upgrade = UpgradeSignal::new();
+            let mut child_pid = child.id();
+            // 进程启动前已有的子进程, 不会是它fork后留下的进程
+            #[cfg(target_os = "linux")]
+            let mut existing_children = self.existing_children.clone().unwrap_or_else(super::subreaper::children);
+            // 进程启动时创建的cgroup, 用于检测OOM, 重启时重新创建
+            let mut cgroup = Cgroup::open(&workspace, &process_name);
+            if let Some(pid) = child_pid {
                 info!("保存初始进程状态, PID: {}", pid);
-                // 先加载现有状态
-                let mut current_state = ProcessState::load(&workspace, &process_name)
-                    .unwrap_or_else(|_| state.clone());
-                info!("当前状态: {:?}", current_state);
                 // 只更新pid,保持monitor_pid不变
-                current_state.pid = Some(pid as i32);
+                let current_state = ProcessState::update(&workspace, &process_name, |current_state| {
+                    if current_state.program.is_empty() {
+                        *current_state = state.clone();
+                    }
+                    current_state.set_pid(pid);
+                })?;
                 info!("更新后状态: {:?}", current_state);
-                current_state.save(&workspace, &process_name)?;
             }
 
+            // 输出管道的描述符, 升级时交给新程序
+            let mut log_fds = (raw_fd(stdout.as_ref()), raw_fd(stderr.as_ref()));
             let logger = crate::logger::Logger::new(workspace.clone());
-            ProcessManager::handle_process_output(
+            logger.attach_process_sinks(&process_name, &log_sinks);
+            let mut readers = ProcessManager::handle_process_output(
                 process_name.clone(),
                 logger.clone(),
-                child.stdout.take(),
-                child.stderr.take()
+                tail.clone(),
+                stdout,
+                stderr,
             ).await;
 
             loop {
-                match child.wait().await {
-                    Ok(status) => {
-                        if !status.success() && auto_restart {
-                            warn!("进程异常退出({}), 准备重启...", status);
-                            sleep(std::time::Duration::from_secs(3)).await;
-
-                            // 加载完整的状态
-                            let mut current_state = ProcessState::load(&workspace, &process_name)?;
-                            
-                            // 增加重启计数
-                            current_state.restart_count += 1;
-                            info!("进程重启次数: {}", current_state.restart_count);
-
-                            // 获取python.exe的完整路径
-                            let program = if cfg!(windows) {
-                                which::which("python")
-                                    .unwrap_or_else(|_| std::path::PathBuf::from("python"))
-                                    .to_string_lossy()
-                                    .into_owned()
-                            } else {
-                                current_state.program.clone()
+                let result = tokio::select! {
+                    result = child.wait() => result,
+                    // 故障重启后等待进程稳定运行, 以便发送恢复通知
+                    _ = sleep(recovered_after), if awaiting_recovery => {
+                        awaiting_recovery = false;
+                        info!("进程重启后已稳定运行{:?}", recovered_after);
+                        let restart_count = ProcessState::load(&workspace, &process_name)
+                            .map(|s| s.restart_count)
+                            .unwrap_or(0);
+                        notifier.notify_in_background(
+                            Notification::new(NotifyEvent::Recovered, &process_name, "进程已恢复正常运行")
+                                .with_restart_count(restart_count),
+                        );
+                        continue;
+                    }
+                    _ = upgrade.recv() => {
+                        info!("收到升级信号, 准备把进程交给新程序");
+                        #[cfg(unix)]
+                        if let Some(pid) = child_pid {
+                            let elapsed = chrono::Duration::from_std(started_at.elapsed()).unwrap_or_default();
+                            let handoff = Handoff {
+                                version: handoff::HANDOFF_VERSION,
+                                process_name: process_name.clone(),
+                                pid,
+                                orphan: matches!(child, Supervised::Orphan { .. }),
+                                started_at: chrono::Local::now() - elapsed,
+                                awaiting_recovery,
+                                auto_restart,
+                                max_restarts,
+                                config: config.clone(),
+                                env_vars: env_vars.clone(),
+                                hooks: hooks.clone(),
+                                notifications: notifier.config().clone(),
+                                log_sinks: log_sinks.clone(),
+                                stdout_fd: log_fds.0,
+                                stderr_fd: log_fds.1,
+                                socket_fds: sockets.raw_fds(),
+                                output_tail: tail.snapshot(),
+                                pid_file: pid_file.clone(),
+                                limits: limits.clone(),
+                                sandbox: sandbox_config.clone(),
                             };
+                            if let Err(e) = reexec(&workspace, handoff) {
+                                error!("升级监控进程失败, 继续由当前程序监控: {:?}", e);
+                            }
+                        }
+                        continue;
+                    }
+                };
 
-                            info!("重启程序路径: {}", program);
-                            info!("重启参数: {:?}", current_state.args);
-                            info!("工作目录: {:?}", current_state.working_dir);
-
-                            let mut cmd = Command::new(program);
-                            cmd.args(&current_state.args)
-                               .current_dir(&current_state.working_dir)
-                               .stdout(Stdio::piped())
-                               .stderr(Stdio::piped())
-                               .stdin(Stdio::null());
-
-                            #[cfg(windows)]
-                            {
-                                use winapi::um::winbase::CREATE_NO_WINDOW;
-                                cmd.creation_flags(CREATE_NO_WINDOW);
+                match result {
+                    Ok(status) => {
+                        // 以守护进程方式运行的程序(如nginx)fork后正常退出, 留下的进程挂到了当前进程下
+                        #[cfg(target_os = "linux")]
+                        if status.success() && config.follow_forks {
+                            if let Some(pid) = super::subreaper::find_daemonized(&existing_children, std::time::Duration::from_secs(1)).await {
+                                let launcher = child_pid.unwrap_or_default();
+                                info!("进程 {} 已转入后台运行, 继续监控后台进程 {}", launcher, pid);
+                                ProcessState::update(&workspace, &process_name, |current_state| {
+                                    current_state.set_pid(pid);
+                                })?;
+                                events::record(&workspace, &process_name, EventKind::Daemonized { launcher, pid });
+                                child = Supervised::Adopted(pid);
+                                child_pid = Some(pid);
+                                continue;
+                            }
+                        }
+                        events::record(&workspace, &process_name, EventKind::exited(&status));
+                        if !status.success() {
+                            let oom_killed = super::manager::check_oom_kill(&workspace, &process_name, cgroup.as_ref(), &limits);
+                            readers.wait(OUTPUT_DRAIN_TIMEOUT).await;
+                            let current_state = ProcessState::load(&workspace, &process_name)
+                                .unwrap_or_else(|_| state.clone());
+                            let mut record = CrashRecord::capture(
+                                &process_name,
+                                child_pid,
+                                &status,
+                                started_at.elapsed(),
+                                current_state.restart_count,
+                                &env_vars,
+                                &tail,
+                            );
+                            record.oom_killed = oom_killed;
+                            if let Err(e) = record.save(&workspace, max_crash_records) {
+                                warn!("保存崩溃记录失败: {}", e);
                             }
+                            notifier.notify_in_background(
+                                Notification::new(NotifyEvent::Crash, &process_name, format!("进程异常退出: {}", record.reason()))
+                                    .with_exit_status(&status)
+                                    .with_restart_count(current_state.restart_count),
+                            );
 
-                            match cmd.spawn() {
-                                Ok(new_child) => {
-                                    child = new_child;
-                                    if let Some(pid) = child.id() {
-                                        info!("进程已重启, 新PID: {}", pid);
-                                        // 更新pid,保持重启计数
-                                        current_state.pid = Some(pid as i32);
-                                        // 不更新monitor_pid,保持原值
-                                        info!("重启后更新状态: {:?}", current_state);
-                                        current_state.save(&workspace, &process_name)?;
-
-                                        ProcessManager::handle_process_output(
-                                            process_name.clone(),
-                                            logger.clone(),
-                                            child.stdout.take(),
-                                            child.stderr.take()
-                                        ).await;
-                                    }
+                            let ctx = HookContext {
+                                workspace: &workspace,
+                                process_name: &process_name,
+                                working_dir: &current_state.working_dir,
+                                env_vars: &env_vars,
+                                exit_status: Some(&status),
+                            };
+                            if let Err(e) = hooks::run_hook(&hooks, HookEvent::OnCrash, &ctx).await {
+                                warn!("on_crash钩子失败: {}", e);
+                            }
+                        }
+
+                        if !status.success() && auto_restart {
+                            // 重启失败时同样计入重启次数, 直到重启成功或达到上限
+                            let mut restarted = false;
+                            loop {
+                                let restart_count = ProcessState::load(&workspace, &process_name)
+                                    .map(|s| s.restart_count)
+                                    .unwrap_or(0);
+                                if let Some(max_restarts) = max_restarts.filter(|max| restart_count >= *max) {
+                                    error!("进程重启次数已达上限({}), 不再重启", max_restarts);
+                                    events::record(&workspace, &process_name, EventKind::LimitExceeded {
+                                        restart_count,
+                                        max_restarts,
+                                    });
+                                    ProcessState::update(&workspace, &process_name, |current_state| {
+                                        current_state.update_stopped_state();
+                                    })?;
+                                    notifier.notify(
+                                        &Notification::new(
+                                            NotifyEvent::RestartLimit,
+                                            &process_name,
+                                            format!("重启次数已达上限({}), 不再重启", max_restarts),
+                                        )
+                                        .with_exit_status(&status)
+                                        .with_restart_count(restart_count),
+                                    ).await;
+                                    break;
                                 }
-                                Err(e) => {
-                                    error!("重启进程失败: {}", e);
-                                    return Err(e.into());
+
+                                warn!("进程异常退出({}), 准备重启...", status);
+                                sleep(std::time::Duration::from_secs(3)).await;
+
+                                // 增加重启计数
+                                let current_state = ProcessState::update(&workspace, &process_name, |current_state| {
+                                    current_state.restart_count += 1;
+                                })?;
+                                info!("进程重启次数: {}", current_state.restart_count);
+
+                                // 新的cgroup会先终止上次运行留下的进程
+                                cgroup = Cgroup::prepare(&workspace, &process_name, &limits);
+                                #[cfg(target_os = "linux")]
+                                {
+                                    existing_children = super::subreaper::children();
                                 }
+                                let spawned = respawn(
+                                    &current_state,
+                                    &env_vars,
+                                    &sockets,
+                                    cgroup.as_ref(),
+                                    &sandbox_config,
+                                    pid_file.as_deref(),
+                                    config.pid_file_timeout(),
+                                ).await;
+                                let (new_child, launcher, daemon_pid, stdout, stderr) = match spawned {
+                                    Ok(spawned) => spawned,
+                                    Err(e) => {
+                                        error!("重启进程失败: {:#}", e);
+                                        events::record(&workspace, &process_name, EventKind::RestartFailed {
+                                            error: format!("{:#}", e),
+                                        });
+                                        continue;
+                                    }
+                                };
+
+                                child = new_child;
+                                started_at = std::time::Instant::now();
+                                child_pid = child.id();
+                                tail.clear();
+                                if let Some(pid) = child_pid {
+                                    info!("进程已重启, 新PID: {}", pid);
+                                    // 更新pid,保持重启计数和monitor_pid
+                                    let current_state = ProcessState::update(&workspace, &process_name, |current_state| {
+                                        current_state.set_pid(pid);
+                                    })?;
+                                    info!("重启后更新状态: {:?}", current_state);
+                                    events::record(&workspace, &process_name, EventKind::Restarted {
+                                        pid: launcher.unwrap_or(pid),
+                                        restart_count: current_state.restart_count,
+                                    });
+                                    if let (Some(launcher), Some(_)) = (launcher, daemon_pid) {
+                                        events::record(&workspace, &process_name, EventKind::Daemonized { launcher, pid });
+                                    }
+                                    awaiting_recovery = true;
+
+                                    log_fds = (raw_fd(stdout.as_ref()), raw_fd(stderr.as_ref()));
+                                    readers = ProcessManager::handle_process_output(
+                                        process_name.clone(),
+                                        logger.clone(),
+                                        tail.clone(),
+                                        stdout,
+                                        stderr,
+                                    ).await;
+                                }
+                                restarted = true;
+                                break;
+                            }
+                            if !restarted {
+                                break;
                             }
                         } else {
-                            info!("进程正常退出: {}", status);
-                            let mut current_state = ProcessState::load(&workspace, &process_name)?;
-                            current_state.update_stopped_state();
-                            current_state.save(&workspace, &process_name)?;
+                            if status.success() {
+                                info!("进程正常退出: {}", status);
+                            } else {
+                                error!("进程异常退出({}), 没有开启自动重启, 不再重启", status);
+                            }
+                            ProcessState::update(&workspace, &process_name, |current_state| {
+                                current_state.update_stopped_state();
+                            })?;
                             break;
                         }
                     }
@@ -408,3 +1012,407 @@ impl ProcessManagerDaemonExt for ProcessManager<'_> {
         })
     }
 }
+
+/// 重启时启动的进程: 监控对象、启动程序PID、PID文件中的PID和输出管道
+type Respawned = (Supervised, Option<i32>, Option<i32>, Option<ChildStdout>, Option<ChildStderr>);
+
+/// 按状态中保存的命令重新启动进程
+async fn respawn(
+    current_state: &ProcessState,
+    env_vars: &HashMap<String, String>,
+    sockets: &ListenSockets,
+    cgroup: Option<&Cgroup>,
+    sandbox_config: &SandboxConfig,
+    pid_file: Option<&Path>,
+    pid_file_timeout: std::time::Duration,
+) -> Result<Respawned> {
+    // 状态中保存的是按运行时解析后的命令
+    let program = current_state.program.clone();
+
+    info!("重启程序路径: {}", program);
+    info!("重启参数: {:?}", current_state.args);
+    info!("工作目录: {:?}", current_state.working_dir);
+
+    let mut cmd = sockets.command(&program, &current_state.args);
+    cmd.current_dir(&current_state.working_dir)
+       .envs(env_vars)
+       .stdout(Stdio::piped())
+       .stderr(Stdio::piped())
+       .stdin(Stdio::null());
+
+    #[cfg(windows)]
+    {
+        use winapi::um::winbase::CREATE_NO_WINDOW;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let pid_file_path = pid_file.map(|pid_file| current_state.working_dir.join(pid_file));
+    if let Some(path) = &pid_file_path {
+        super::manager::remove_stale_pid_file(path)?;
+    }
+
+    #[cfg(unix)]
+    if let Some(cgroup) = cgroup {
+        cgroup.attach(&mut cmd);
+    }
+    #[cfg(not(unix))]
+    let _ = cgroup;
+    sandbox::apply(sandbox_config, &mut cmd, &current_state.working_dir)?;
+
+    let mut new_child = cmd.spawn().context("启动进程失败")?;
+    let stdout = new_child.stdout.take();
+    let stderr = new_child.stderr.take();
+    let launcher = new_child.id().map(|pid| pid as i32);
+    // 程序自己fork到后台时, 等待PID文件并改为监控其中的进程
+    let daemon_pid = match &pid_file_path {
+        Some(path) => Some(super::manager::wait_for_pid_file(path, &mut new_child, pid_file_timeout).await?),
+        None => None,
+    };
+    let child = match daemon_pid {
+        #[cfg(unix)]
+        Some(daemon_pid) => Supervised::daemonized(daemon_pid).await,
+        _ => Supervised::Spawned(new_child),
+    };
+    Ok((child, launcher, daemon_pid, stdout, stderr))
+}
+
+/// 输出管道的文件描述符
+#[cfg(unix)]
+fn raw_fd<T: std::os::unix::io::AsRawFd>(pipe: Option<&T>) -> Option<i32> {
+    pipe.map(|pipe| pipe.as_raw_fd())
+}
+
+#[cfg(not(unix))]
+fn raw_fd<T>(_pipe: Option<&T>) -> Option<i32> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use crate::test_support::{wait_until, Fixture, TestWorkspace};
+
+    #[tokio::test]
+    async fn test_monitor_restarts_until_limit() -> Result<()> {
+        let env = TestWorkspace::new();
+        let mut manager = env.manager("crasher");
+        manager.set_auto_restart(true);
+        manager.set_max_restarts(1);
+
+        let fixture = Fixture::CrashAfter(Duration::from_millis(100));
+        let working_dir = env.working_dir("crasher");
+        std::fs::create_dir_all(&working_dir)?;
+        manager.state = ProcessState {
+            program: fixture.program().to_string(),
+            args: fixture.args(),
+            working_dir: working_dir.clone(),
+            ..Default::default()
+        };
+
+        let started = Instant::now();
+        manager.monitor_and_restart(fixture.spawn(&working_dir)?).await?;
+        // 每次重启前固定等待3秒
+        assert!(started.elapsed() >= Duration::from_secs(3));
+
+        let events = env.events("crasher");
+        let exits = events.iter().filter(|event| matches!(event, EventKind::Exited { code: Some(1), .. })).count();
+        assert_eq!(exits, 2);
+        assert!(events.iter().any(|event| matches!(event, EventKind::Restarted { restart_count: 1, .. })));
+        assert_eq!(
+            events.last(),
+            Some(&EventKind::LimitExceeded { restart_count: 1, max_restarts: 1 })
+        );
+
+        let state = env.state("crasher");
+        assert_eq!(state.pid, None);
+        assert_eq!(state.restart_count, 1);
+        assert_eq!(CrashRecord::list(&env.workspace, "crasher")?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_monitor_stops_after_clean_exit() -> Result<()> {
+        let env = TestWorkspace::new();
+        let mut manager = env.manager("oneshot");
+        manager.set_auto_restart(true);
+
+        let fixture = Fixture::ExitAfter(Duration::from_millis(100));
+        let working_dir = env.working_dir("oneshot");
+        std::fs::create_dir_all(&working_dir)?;
+        manager.monitor_and_restart(fixture.spawn(&working_dir)?).await?;
+
+        // 正常退出不触发重启, 也不产生崩溃记录
+        assert_eq!(env.state("oneshot").pid, None);
+        assert!(!env.events("oneshot").iter().any(|event| matches!(event, EventKind::Restarted { .. })));
+        assert!(CrashRecord::list(&env.workspace, "oneshot")?.is_empty());
+
+        // 输出写入按日期划分的日志目录
+        let logged = wait_until(Duration::from_secs(2), || {
+            let log = env.workspace.get_process_log_dir("oneshot")
+                .join(chrono::Local::now().format("%Y-%m-%d").to_string())
+                .join("stdout.log");
+            std::fs::read_to_string(log).is_ok_and(|content| content.contains("starting"))
+        }).await;
+        assert!(logged);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_monitor_records_crash_without_auto_restart() -> Result<()> {
+        let env = TestWorkspace::new();
+        let mut manager = env.manager("crasher");
+        manager.set_auto_restart(false);
+
+        let fixture = Fixture::CrashAfter(Duration::from_millis(100));
+        let working_dir = env.working_dir("crasher");
+        std::fs::create_dir_all(&working_dir)?;
+        manager.monitor_and_restart(fixture.spawn(&working_dir)?).await?;
+
+        // 没有开启自动重启时异常退出同样留下崩溃记录, 但不会重启
+        let events = env.events("crasher");
+        assert!(events.contains(&EventKind::Exited { code: Some(1), signal: None }));
+        assert!(!events.iter().any(|event| matches!(event, EventKind::Restarted { .. })));
+        assert_eq!(env.state("crasher").pid, None);
+        let crashes = CrashRecord::list(&env.workspace, "crasher")?;
+        assert_eq!(crashes.len(), 1);
+        let lines: Vec<&str> = crashes[0].output.iter().map(|line| line.line.as_str()).collect();
+        assert_eq!(lines, ["starting", "crashing"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resume_monitoring_adopted_child() -> Result<()> {
+        use std::os::unix::io::IntoRawFd;
+
+        let env = TestWorkspace::new();
+        let mut manager = env.manager("adopted");
+        manager.set_auto_restart(true);
+        manager.set_max_restarts(0);
+
+        // 模拟exec前由旧程序启动的子进程: 是当前进程的子进程, 但不归tokio管理
+        let fixture = Fixture::CrashAfter(Duration::from_millis(300));
+        let working_dir = env.working_dir("adopted");
+        std::fs::create_dir_all(&working_dir)?;
+        let mut child = std::process::Command::new(fixture.program())
+            .args(fixture.args())
+            .current_dir(&working_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let pid = child.id() as i32;
+        let handoff = Handoff {
+            version: handoff::HANDOFF_VERSION,
+            process_name: "adopted".into(),
+            pid,
+            orphan: false,
+            started_at: chrono::Local::now() - chrono::Duration::seconds(60),
+            awaiting_recovery: false,
+            auto_restart: true,
+            max_restarts: Some(0),
+            config: manager.config.clone(),
+            env_vars: HashMap::new(),
+            hooks: Default::default(),
+            notifications: Default::default(),
+            log_sinks: Vec::new(),
+            stdout_fd: child.stdout.take().map(|pipe| pipe.into_raw_fd()),
+            stderr_fd: child.stderr.take().map(|pipe| pipe.into_raw_fd()),
+            socket_fds: Vec::new(),
+            output_tail: vec![crate::crash::TailLine { stream: "stdout".into(), line: "before upgrade".into() }],
+            pid_file: None,
+            limits: Default::default(),
+            sandbox: Default::default(),
+        };
+
+        manager.resume_monitoring(handoff).await?;
+
+        let events = env.events("adopted");
+        assert!(events.contains(&EventKind::Exited { code: Some(1), signal: None }));
+        assert_eq!(events.last(), Some(&EventKind::LimitExceeded { restart_count: 0, max_restarts: 0 }));
+
+        // 崩溃记录包含交接前的输出和接管后从管道读到的输出, 运行时长从交接前开始计算
+        let crashes = CrashRecord::list(&env.workspace, "adopted")?;
+        assert_eq!(crashes.len(), 1);
+        assert_eq!(crashes[0].pid, Some(pid));
+        assert!(crashes[0].uptime_secs >= 60);
+        let lines: Vec<&str> = crashes[0].output.iter().map(|line| line.line.as_str()).collect();
+        assert!(lines.contains(&"before upgrade"));
+        assert!(lines.contains(&"crashing"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_daemon_follows_forked_process() -> Result<()> {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        let env = TestWorkspace::new();
+        // 启动后台进程后立即正常退出, 与nginx等程序的守护进程模式相同
+        let mut process = Fixture::Sleep.process_config("forking");
+        process.args = vec!["-c".into(), "sleep 60 & exit 0".into()];
+        process.auto_restart = true;
+        process.process.follow_forks = true;
+        let config = env.write_config(vec![process])?;
+        std::fs::create_dir_all(env.workspace.get_process_working_dir("forking"))?;
+        crate::commands::start::start_process(
+            &env.workspace, &config, "forking", &config.processes["forking"], true, true,
+        ).await?;
+
+        let daemonized = || env.events("forking").into_iter().find_map(|event| match event {
+            EventKind::Daemonized { pid, .. } => Some(pid),
+            _ => None,
+        });
+        assert!(wait_until(Duration::from_secs(10), || daemonized().is_some()).await);
+        let pid = daemonized().unwrap();
+        assert_eq!(env.state("forking").pid, Some(pid));
+        assert!(!env.events("forking").iter().any(|event| matches!(event, EventKind::Exited { .. })));
+
+        // 后台进程是监控进程的子进程, 退出状态可以获得, 崩溃后重新启动
+        kill(Pid::from_raw(pid), Signal::SIGKILL)?;
+        assert!(wait_until(Duration::from_secs(10), || {
+            env.events("forking").contains(&EventKind::Exited { code: None, signal: Some(9) })
+                && env.events("forking").iter().filter(|event| matches!(event, EventKind::Daemonized { .. })).count() == 2
+        }).await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_daemon_first_crash_keeps_output() -> Result<()> {
+        let env = TestWorkspace::new();
+        let mut process = Fixture::CrashAfter(Duration::from_millis(300)).process_config("first");
+        process.auto_restart = true;
+        process.max_restarts = 0;
+        let config = env.write_config(vec![process])?;
+        std::fs::create_dir_all(env.workspace.get_process_working_dir("first"))?;
+        crate::commands::start::start_process(
+            &env.workspace, &config, "first", &config.processes["first"], true, true,
+        ).await?;
+
+        // 首次启动的进程同样通过管道读取输出
+        assert!(wait_until(Duration::from_secs(10), || {
+            CrashRecord::list(&env.workspace, "first").is_ok_and(|records| !records.is_empty())
+        }).await);
+        let crashes = CrashRecord::list(&env.workspace, "first")?;
+        let lines: Vec<&str> = crashes[0].output.iter().map(|line| line.line.as_str()).collect();
+        assert_eq!(lines, vec!["starting", "crashing"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_daemon_ignores_forked_process_by_default() -> Result<()> {
+        let env = TestWorkspace::new();
+        // 没有设置follow_forks时, 正常退出后留下的进程(如钩子或通知的子进程)不会被当作后台进程
+        let mut process = Fixture::Sleep.process_config("leftover");
+        process.args = vec!["-c".into(), "sleep 5 & exit 0".into()];
+        process.auto_restart = true;
+        let config = env.write_config(vec![process])?;
+        std::fs::create_dir_all(env.workspace.get_process_working_dir("leftover"))?;
+        crate::commands::start::start_process(
+            &env.workspace, &config, "leftover", &config.processes["leftover"], true, true,
+        ).await?;
+
+        assert!(wait_until(Duration::from_secs(10), || {
+            env.events("leftover").contains(&EventKind::Exited { code: Some(0), signal: None })
+        }).await);
+        assert!(!env.events("leftover").iter().any(|event| matches!(event, EventKind::Daemonized { .. })));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_daemon_restarts_pid_file_process() -> Result<()> {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        let env = TestWorkspace::new();
+        let mut process = Fixture::Sleep.process_config("pidfile");
+        process.args = vec!["-c".into(), "sleep 60 >/dev/null 2>&1 & echo $! > app.pid".into()];
+        process.auto_restart = true;
+        process.pid_file = Some("app.pid".into());
+        let config = env.write_config(vec![process])?;
+        std::fs::create_dir_all(env.workspace.get_process_working_dir("pidfile"))?;
+        crate::commands::start::start_process(
+            &env.workspace, &config, "pidfile", &config.processes["pidfile"], true, true,
+        ).await?;
+
+        let daemonized = || env.events("pidfile").into_iter().filter_map(|event| match event {
+            EventKind::Daemonized { pid, .. } => Some(pid),
+            _ => None,
+        }).collect::<Vec<_>>();
+        assert!(wait_until(Duration::from_secs(10), || daemonized().len() == 1).await);
+        let pid = daemonized()[0];
+        assert!(wait_until(Duration::from_secs(5), || {
+            ProcessState::load(&env.workspace, "pidfile").is_ok_and(|state| state.pid == Some(pid) && state.monitor_pid.is_some())
+        }).await);
+
+        // PID文件中的进程崩溃后重新运行启动程序, 改为监控新写入的PID
+        kill(Pid::from_raw(pid), Signal::SIGKILL)?;
+        assert!(wait_until(Duration::from_secs(10), || daemonized().len() == 2).await);
+        let new_pid = daemonized()[1];
+        assert_ne!(new_pid, pid);
+        assert!(env.events("pidfile").contains(&EventKind::Exited { code: None, signal: Some(9) }));
+        assert!(wait_until(Duration::from_secs(5), || env.state("pidfile").pid == Some(new_pid)).await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_restart_counts_toward_limit() -> Result<()> {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        let env = TestWorkspace::new();
+        // 只有第一次运行写入PID文件, 之后的重启都失败
+        let mut process = Fixture::Sleep.process_config("flaky");
+        process.args = vec![
+            "-c".into(),
+            "[ -e started ] && exit 1; touch started; sleep 60 >/dev/null 2>&1 & echo $! > app.pid".into(),
+        ];
+        process.auto_restart = true;
+        process.max_restarts = 2;
+        process.pid_file = Some("app.pid".into());
+        let config = env.write_config(vec![process])?;
+        std::fs::create_dir_all(env.workspace.get_process_working_dir("flaky"))?;
+        crate::commands::start::start_process(
+            &env.workspace, &config, "flaky", &config.processes["flaky"], true, true,
+        ).await?;
+
+        assert!(wait_until(Duration::from_secs(5), || {
+            ProcessState::load(&env.workspace, "flaky").is_ok_and(|state| state.pid.is_some() && state.monitor_pid.is_some())
+        }).await);
+        kill(Pid::from_raw(env.state("flaky").pid.unwrap()), Signal::SIGKILL)?;
+
+        assert!(wait_until(Duration::from_secs(15), || {
+            env.events("flaky").contains(&EventKind::LimitExceeded { restart_count: 2, max_restarts: 2 })
+        }).await);
+        let failures = env.events("flaky").into_iter()
+            .filter(|event| matches!(event, EventKind::RestartFailed { .. }))
+            .count();
+        assert_eq!(failures, 2);
+        let state = env.state("flaky");
+        assert_eq!(state.pid, None);
+        assert_eq!(state.monitor_pid, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_daemon_runs_process_in_sandbox() -> Result<()> {
+        let env = TestWorkspace::new();
+        let mut process = Fixture::Sleep.process_config("sandboxed");
+        process.sandbox.private_network = true;
+        let config = env.write_config(vec![process])?;
+        std::fs::create_dir_all(env.workspace.get_process_working_dir("sandboxed"))?;
+        if let Err(e) = crate::commands::start::start_process(
+            &env.workspace, &config, "sandboxed", &config.processes["sandboxed"], true, true,
+        ).await {
+            eprintln!("跳过: 无法创建沙箱: {:#}", e);
+            return Ok(());
+        }
+
+        assert!(wait_until(Duration::from_secs(10), || {
+            ProcessState::load(&env.workspace, "sandboxed").is_ok_and(|state| state.pid.is_some())
+        }).await);
+        let pid = env.state("sandboxed").pid.unwrap();
+        let own_net = std::fs::read_link("/proc/self/ns/net")?;
+        assert_ne!(std::fs::read_link(format!("/proc/{}/ns/net", pid))?, own_net);
+        Ok(())
+    }
+}
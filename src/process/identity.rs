@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+
+/// 进程身份信息, 用于在PID被复用后识别出"已不是原来那个进程"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessIdentity {
+    /// 进程启动时间(自系统启动以来的时钟滴答数, 来自/proc/<pid>/stat)
+    pub start_time: Option<u64>,
+    /// 系统启动ID, 用于识别重启后的PID复用
+    pub boot_id: Option<String>,
+    /// 可执行文件路径
+    pub exe: Option<PathBuf>,
+    /// 命令行参数
+    pub cmdline: Vec<String>,
+}
+
+impl ProcessIdentity {
+    /// 读取指定PID当前的身份信息, 进程不存在或平台不支持时返回None
+    #[cfg(target_os = "linux")]
+    pub fn capture(pid: i32) -> Option<Self> {
+        let proc_dir = PathBuf::from(format!("/proc/{}", pid));
+        let stat = std::fs::read_to_string(proc_dir.join("stat")).ok()?;
+        let start_time = parse_start_time(&stat);
+        let cmdline = std::fs::read(proc_dir.join("cmdline"))
+            .map(|raw| {
+                raw.split(|b| *b == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Self {
+            start_time,
+            boot_id: std::fs::read_to_string("/proc/sys/kernel/random/boot_id")
+                .ok()
+                .map(|id| id.trim().to_string()),
+            exe: std::fs::read_link(proc_dir.join("exe")).ok(),
+            cmdline,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn capture(_pid: i32) -> Option<Self> {
+        None
+    }
+
+    /// 判断另一份身份信息是否属于同一个进程
+    pub fn matches(&self, other: &Self) -> bool {
+        if let (Some(a), Some(b)) = (&self.boot_id, &other.boot_id) {
+            if a != b {
+                return false;
+            }
+        }
+        match (self.start_time, other.start_time) {
+            (Some(a), Some(b)) => a == b,
+            // 没有启动时间时退化为比较命令行
+            _ => self.cmdline == other.cmdline,
+        }
+    }
+
+    /// 检查PID当前是否仍是记录的进程
+    ///
+    /// 只有能确定PID已属于另一个进程时才返回false, 无法读取身份信息
+    /// (进程已退出或平台不支持)时返回true, 由调用方再做存活检查
+    pub fn verify(&self, pid: i32) -> bool {
+        match Self::capture(pid) {
+            Some(current) => self.matches(&current),
+            None => true,
+        }
+    }
+}
+
+/// 从/proc/<pid>/stat中解析第22个字段(starttime)
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_start_time(stat: &str) -> Option<u64> {
+    stat_field(stat, 22)?.parse().ok()
+}
+
+/// 获取/proc/<pid>/stat中的第n个字段(从1开始计数)
+///
+/// 第2个字段是括号包围的进程名, 其中可能含有空格和括号, 所以从最后一个')'之后开始切分
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn stat_field(stat: &str, n: usize) -> Option<&str> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    // rest从第3个字段(state)开始
+    rest.split_whitespace().nth(n.checked_sub(3)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_start_time() {
+        let stat = "1234 (my (weird) proc) S 1 1234 1234 0 -1 4194560 100 0 0 0 \
+                    5 3 0 0 20 0 1 0 987654 12345678 100 18446744073709551615";
+        assert_eq!(parse_start_time(stat), Some(987654));
+        assert_eq!(parse_start_time("garbage"), None);
+    }
+
+    #[test]
+    fn test_identity_mismatch_on_start_time() {
+        let recorded = ProcessIdentity {
+            start_time: Some(100),
+            boot_id: Some("boot".into()),
+            exe: Some(PathBuf::from("/usr/bin/python3")),
+            cmdline: vec!["python3".into(), "app.py".into()],
+        };
+        let reused = ProcessIdentity { start_time: Some(200), ..recorded.clone() };
+        let rebooted = ProcessIdentity { boot_id: Some("other".into()), ..recorded.clone() };
+        assert!(recorded.matches(&recorded.clone()));
+        assert!(!recorded.matches(&reused));
+        assert!(!recorded.matches(&rebooted));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_capture_current_process() {
+        let pid = std::process::id() as i32;
+        let identity = ProcessIdentity::capture(pid).expect("应能读取当前进程信息");
+        assert!(identity.start_time.is_some());
+        assert!(identity.verify(pid));
+    }
+}
@@ -1,18 +1,30 @@
 /// process/manager.rs
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use anyhow::{Result, Context};
 use log::{info, error, warn};
-use tokio::process::{Child, Command};
 use tokio::io::{BufReader, AsyncBufReadExt};
 use tokio::time::sleep;
 use tokio::sync::mpsc;
-use std::process::Stdio;
+use std::process::{ExitStatus, Stdio};
 
 use crate::state::ProcessState;
+use crate::process::identity::ProcessIdentity;
 use crate::workspace::Workspace;
 use crate::types::ProcessConfig;
+use crate::sinks::{LogRecord, SinkConfig, SinkSet};
+use crate::logger::OUTPUT_TARGET;
+use crate::crash::{CrashRecord, OutputTail};
+use crate::events::{self, EventKind};
+use crate::hooks::{self, HookContext, HookEvent, HooksConfig};
+use crate::notify::{Notification, NotifyEvent, Notifier};
+use crate::error::Failure;
+use crate::process::backend::{self, ProcessBackend, Signal};
+use crate::runtime::RuntimeConfig;
+use crate::sockets::{ListenSockets, SocketConfig};
+use crate::process::cgroup::{Cgroup, LimitsConfig};
+use crate::process::sandbox::{self, SandboxConfig};
 
 pub struct ProcessManager<'a> {
     pub state: ProcessState,
@@ -21,21 +33,38 @@ pub struct ProcessManager<'a> {
     pub process_name: String,
     pub daemon_mode: bool,
     pub auto_restart: bool,
+    pub log_sinks: Vec<SinkConfig>,
+    /// 传给子进程的环境变量(重启时同样使用)
+    pub env_vars: HashMap<String, String>,
+    /// 最近的进程输出, 用于崩溃记录
+    pub output_tail: OutputTail,
+    /// 自动重启次数上限, None表示不限制
+    pub max_restarts: Option<u32>,
+    /// 生命周期钩子
+    pub hooks: HooksConfig,
+    /// 状态变化通知
+    pub notifier: Notifier,
+    /// 平台相关的进程操作
+    pub backend: std::sync::Arc<dyn ProcessBackend>,
+    /// 运行时, 启动时用来解析解释器和环境变量
+    pub runtime: RuntimeConfig,
+    /// 预先绑定并传给子进程的监听套接字
+    pub sockets: Vec<SocketConfig>,
+    /// 已绑定的监听套接字, 守护进程重启子进程时继续传给新进程
+    pub listen_sockets: std::sync::Arc<ListenSockets>,
+    /// 程序写入真实PID的文件(相对于工作目录), 设置后监控该PID而不是启动的程序
+    pub pid_file: Option<PathBuf>,
+    /// 进程树的资源上限, cgroup可用时生效
+    pub limits: LimitsConfig,
+    /// 进程的沙箱设置, 启动和重启时生效
+    pub sandbox: SandboxConfig,
+    /// 启动被监控的进程前已有的子进程, 查找它fork后留下的进程时排除; None表示开始监控时的子进程
+    pub existing_children: Option<HashSet<i32>>,
 }
 
 impl<'a> ProcessManager<'a> {
-    pub fn new(workspace: &'a Workspace, process_name: String) -> Self {
-        Self {
-            state: ProcessState::default(),
-            config: ProcessConfig::default(),
-            workspace,
-            process_name,
-            daemon_mode: false,
-            auto_restart: false,
-        }
-    }
-
     pub fn with_config(workspace: &'a Workspace, process_name: String, config: ProcessConfig) -> Self {
+        let output_tail = OutputTail::new(config.crash_tail_lines);
         Self {
             state: ProcessState::default(),
             config,
@@ -43,6 +72,20 @@ impl<'a> ProcessManager<'a> {
             process_name,
             daemon_mode: false,
             auto_restart: false,
+            log_sinks: Vec::new(),
+            env_vars: HashMap::new(),
+            output_tail,
+            max_restarts: None,
+            hooks: HooksConfig::default(),
+            notifier: Notifier::default(),
+            backend: backend::native(),
+            runtime: RuntimeConfig::default(),
+            sockets: Vec::new(),
+            listen_sockets: std::sync::Arc::new(ListenSockets::default()),
+            pid_file: None,
+            limits: LimitsConfig::default(),
+            sandbox: SandboxConfig::default(),
+            existing_children: None,
         }
     }
 
@@ -54,29 +97,44 @@ impl<'a> ProcessManager<'a> {
         self.auto_restart = enabled;
     }
 
-    pub fn get_config(&self) -> &ProcessConfig {
-        &self.config
+    pub fn set_max_restarts(&mut self, max_restarts: u32) {
+        self.max_restarts = Some(max_restarts);
     }
 
-    /// 保存进程状态(给守护进程、重启等场景用)
-    pub async fn save_process_state(
-        &self,
-        pid: i32,
-        program: &str,
-        args: &[String],
-        working_dir: &PathBuf,
-        health_check_url: Option<&str>,
-    ) -> Result<()> {
-        let mut state = ProcessState::default();
-        state.pid = Some(pid);
-        state.program = program.to_string();
-        state.args = args.to_vec();
-        state.working_dir = working_dir.clone();
-        state.port = self.config.default_port;
-        state.health_check_url = health_check_url.map(String::from);
-        state.save(self.workspace, &self.process_name)?;
-        info!("进程状态已保存");
-        Ok(())
+    pub fn set_hooks(&mut self, hooks: HooksConfig) {
+        self.hooks = hooks;
+    }
+
+    pub fn set_notifier(&mut self, notifier: Notifier) {
+        self.notifier = notifier;
+    }
+
+    pub fn set_log_sinks(&mut self, sinks: Vec<SinkConfig>) {
+        self.log_sinks = sinks;
+    }
+
+    pub fn set_runtime(&mut self, runtime: RuntimeConfig) {
+        self.runtime = runtime;
+    }
+
+    pub fn set_sockets(&mut self, sockets: Vec<SocketConfig>) {
+        self.sockets = sockets;
+    }
+
+    pub fn set_pid_file(&mut self, pid_file: Option<PathBuf>) {
+        self.pid_file = pid_file;
+    }
+
+    pub fn set_limits(&mut self, limits: LimitsConfig) {
+        self.limits = limits;
+    }
+
+    pub fn set_sandbox(&mut self, sandbox: SandboxConfig) {
+        self.sandbox = sandbox;
+    }
+
+    pub fn set_env_vars(&mut self, env_vars: HashMap<String, String>) {
+        self.env_vars = env_vars;
     }
 
     pub async fn start(
@@ -91,16 +149,35 @@ impl<'a> ProcessManager<'a> {
 
         if self.status(health_check_url).await? {
             error!("进程已在运行");
-            return Err(anyhow::anyhow!("进程已在运行,如需重启请先停止进程"));
+            return Err(Failure::AlreadyRunning("进程已在运行,如需重启请先停止进程".into()).into());
         }
 
+        self.limits.validate()?;
+
+        // 解析运行时, 状态中保存解析后的命令, 重启时直接使用
+        let resolved = self.runtime.resolve(program, args, working_dir, env_vars.cloned().unwrap_or_default())?;
+        let (program, args, env_vars) = (resolved.program.as_str(), resolved.args.as_slice(), Some(&resolved.env));
+
         info!("程序: {}", program);
         info!("参数: {:?}", args);
         info!("工作目录: {:?}", working_dir);
 
+        let hook_env = resolved.env.clone();
+        if let Err(e) = self.run_hook(HookEvent::PreStart, working_dir, &hook_env, None).await {
+            if self.hooks.abort_on_pre_start_failure {
+                error!("pre_start钩子失败, 中止启动: {}", e);
+                return Err(e.context("pre_start钩子失败, 已中止启动"));
+            }
+            warn!("pre_start钩子失败, 继续启动: {}", e);
+        }
+
         if self.daemon_mode {
             use super::daemon::ProcessManagerDaemonExt;
-            return self.start_daemon(program, args, working_dir, health_check_url, env_vars).await;
+            self.start_daemon(program, args, working_dir, health_check_url, env_vars).await?;
+            if let Err(e) = self.run_hook(HookEvent::PostStart, working_dir, &hook_env, None).await {
+                warn!("post_start钩子失败: {}", e);
+            }
+            return Ok(());
         }
 
         fs::create_dir_all(working_dir).context("创建工作目录失败")?;
@@ -108,9 +185,10 @@ impl<'a> ProcessManager<'a> {
 
         self.workspace.ensure_process_dirs(&self.process_name)?;
 
-        let mut cmd = Command::new(program);
-        cmd.args(args)
-           .current_dir(working_dir)
+        // 套接字在启动子进程前绑定, 不会和其他进程抢端口
+        let sockets = ListenSockets::bind(&self.sockets)?;
+        let mut cmd = sockets.command(program, args);
+        cmd.current_dir(working_dir)
            .stdout(Stdio::piped())
            .stderr(Stdio::piped())
            .stdin(Stdio::null()) // ☆ 保证非Daemon时也不阻塞主进程
@@ -126,30 +204,62 @@ impl<'a> ProcessManager<'a> {
             info!("已设置环境变量: {:?}", vars);
         }
 
+        let pid_file = self.pid_file.as_ref().map(|pid_file| working_dir.join(pid_file));
+        if let Some(pid_file) = &pid_file {
+            remove_stale_pid_file(pid_file)?;
+        }
+
+        // 进程和它fork的所有进程都放在独立的cgroup中
+        let cgroup = Cgroup::prepare(self.workspace, &self.process_name, &self.limits);
+        #[cfg(unix)]
+        if let Some(cgroup) = &cgroup {
+            cgroup.attach(&mut cmd);
+        }
+        sandbox::apply(&self.sandbox, &mut cmd, working_dir)?;
+
         info!("正在启动进程...");
         let mut child = cmd.spawn().context("启动进程失败")?;
         let pid = child.id().unwrap();
         info!("进程已启动, PID: {}", pid);
 
         let mut state = ProcessState::default();
-        state.pid = Some(pid as i32);
+        state.set_pid(pid as i32);
         state.program = program.to_string();
         state.args = args.to_vec();
         state.working_dir = working_dir.clone();
         state.port = self.config.default_port;
         state.health_check_url = health_check_url.map(String::from);
+        state.auto_restart = Some(self.auto_restart);
+        state.daemon = Some(false);
+        // 保留run命令写入的临时进程定义
+        state.transient = ProcessState::load(self.workspace, &self.process_name)
+            .ok()
+            .and_then(|previous| previous.transient);
         state.save(self.workspace, &self.process_name)?;
         info!("进程状态已保存");
+        events::record(self.workspace, &self.process_name, EventKind::Started { pid: pid as i32 });
 
         let (tx, mut rx) = mpsc::channel(1);
+        let sinks = std::sync::Arc::new(SinkSet::from_configs(&self.log_sinks));
+
+        let started_at = std::time::Instant::now();
+
+        // 与logs命令读取的目录一致, 按日期划分
+        let log_dir = self.workspace.get_process_log_dir(&self.process_name)
+            .join(chrono::Local::now().format("%Y-%m-%d").to_string());
+        fs::create_dir_all(&log_dir).context("创建日志目录失败")?;
 
+        let mut readers = OutputReaders::default();
         if let Some(stdout) = child.stdout.take() {
-            let tx = tx.clone();
+            // 使用PID文件时程序会关闭输出, 以监控的进程退出为准
+            let tx = pid_file.is_none().then(|| tx.clone());
+            let sinks = sinks.clone();
+            let tail = self.output_tail.clone();
+            let process_name = self.process_name.clone();
             let mut reader = BufReader::new(stdout).lines();
-            let log_dir = self.workspace.get_process_log_dir(&self.process_name);
             let stdout_log = log_dir.join("stdout.log");
 
-            tokio::spawn(async move {
+            readers.push(tokio::spawn(async move {
                 use std::fs::OpenOptions;
                 use std::io::Write;
 
@@ -160,20 +270,27 @@ impl<'a> ProcessManager<'a> {
                     .unwrap_or_else(|_| panic!("无法打开标准输出日志文件"));
 
                 while let Ok(Some(line)) = reader.next_line().await {
-                    info!("[stdout] {}", line);
+                    info!(target: OUTPUT_TARGET, "[stdout] {}", line);
                     writeln!(file, "{}", line).unwrap_or_else(|_| error!("写入标准输出日志失败"));
+                    sinks.emit(&LogRecord::new(log::Level::Info, &process_name, Some("stdout"), &line));
+                    tail.push("stdout", &line);
                 }
-                let _ = tx.send(false).await;
-            });
+                if let Some(tx) = tx {
+                    let _ = tx.send(false).await;
+                }
+            }));
         }
 
         if let Some(stderr) = child.stderr.take() {
-            let tx = tx.clone();
+            // 使用PID文件时程序会关闭输出, 以监控的进程退出为准
+            let tx = pid_file.is_none().then(|| tx.clone());
+            let sinks = sinks.clone();
+            let tail = self.output_tail.clone();
+            let process_name = self.process_name.clone();
             let mut reader = BufReader::new(stderr).lines();
-            let log_dir = self.workspace.get_process_log_dir(&self.process_name);
             let stderr_log = log_dir.join("stderr.log");
 
-            tokio::spawn(async move {
+            readers.push(tokio::spawn(async move {
                 use std::fs::OpenOptions;
                 use std::io::Write;
 
@@ -184,29 +301,106 @@ impl<'a> ProcessManager<'a> {
                     .unwrap_or_else(|_| panic!("无法打开标准错误日志文件"));
 
                 while let Ok(Some(line)) = reader.next_line().await {
-                    info!("[stderr] {}", line);
+                    info!(target: OUTPUT_TARGET, "[stderr] {}", line);
                     writeln!(file, "{}", line).unwrap_or_else(|_| error!("写入标准错误日志失败"));
+                    sinks.emit(&LogRecord::new(log::Level::Info, &process_name, Some("stderr"), &line));
+                    tail.push("stderr", &line);
                 }
-                let _ = tx.send(false).await;
-            });
+                if let Some(tx) = tx {
+                    let _ = tx.send(false).await;
+                }
+            }));
         }
 
-        let tx = tx.clone();
-        tokio::spawn(async move {
-            match child.wait().await {
-                Ok(status) => {
-                    if status.success() {
-                        info!("进程正常退出: {:?}", status);
-                    } else {
-                        error!("进程异常退出: {:?}", status);
-                    }
-                }
+        if let Some(pid_file) = &pid_file {
+            let daemon_pid = match wait_for_pid_file(pid_file, &mut child, self.config.pid_file_timeout()).await {
+                Ok(daemon_pid) => daemon_pid,
                 Err(e) => {
-                    error!("监控进程失败: {}", e);
+                    error!("进程初始化失败: {:#}", e);
+                    let _ = self.update_stopped_state().await;
+                    return Err(e);
                 }
-            }
-            let _ = tx.send(false).await;
-        });
+            };
+            info!("进程已转入后台运行, PID: {}", daemon_pid);
+            ProcessState::update(self.workspace, &self.process_name, |state| {
+                state.set_pid(daemon_pid);
+            })?;
+            events::record(self.workspace, &self.process_name, EventKind::Daemonized {
+                launcher: pid as i32,
+                pid: daemon_pid,
+            });
+
+            // 回收启动程序, 后台进程退出时结束等待
+            let tx = tx.clone();
+            let backend = self.backend.clone();
+            tokio::spawn(async move {
+                let _ = child.wait().await;
+                while backend.exists(daemon_pid) {
+                    sleep(std::time::Duration::from_millis(200)).await;
+                }
+                let _ = tx.send(false).await;
+            });
+        } else {
+            let tx = tx.clone();
+            let workspace = self.workspace.clone();
+            let process_name = self.process_name.clone();
+            let env_vars = env_vars.cloned().unwrap_or_default();
+            let tail = self.output_tail.clone();
+            let max_crash_records = self.config.max_crash_records;
+            let hooks = self.hooks.clone();
+            let notifier = self.notifier.clone();
+            let hook_working_dir = working_dir.clone();
+            let limits = self.limits.clone();
+            tokio::spawn(async move {
+                match child.wait().await {
+                    Ok(status) => {
+                        events::record(&workspace, &process_name, EventKind::exited(&status));
+                        if status.success() {
+                            info!("进程正常退出: {:?}", status);
+                        } else {
+                            error!("进程异常退出: {:?}", status);
+                            let oom_killed = check_oom_kill(&workspace, &process_name, cgroup.as_ref(), &limits);
+                            readers.wait(OUTPUT_DRAIN_TIMEOUT).await;
+                            let restart_count = ProcessState::load(&workspace, &process_name)
+                                .map(|s| s.restart_count)
+                                .unwrap_or(0);
+                            let mut record = CrashRecord::capture(
+                                &process_name,
+                                Some(pid as i32),
+                                &status,
+                                started_at.elapsed(),
+                                restart_count,
+                                &env_vars,
+                                &tail,
+                            );
+                            record.oom_killed = oom_killed;
+                            if let Err(e) = record.save(&workspace, max_crash_records) {
+                                warn!("保存崩溃记录失败: {}", e);
+                            }
+                            notifier.notify(
+                                &Notification::new(NotifyEvent::Crash, &process_name, format!("进程异常退出: {}", record.reason()))
+                                    .with_exit_status(&status)
+                                    .with_restart_count(restart_count),
+                            ).await;
+                            let ctx = HookContext {
+                                workspace: &workspace,
+                                process_name: &process_name,
+                                working_dir: &hook_working_dir,
+                                env_vars: &env_vars,
+                                exit_status: Some(&status),
+                            };
+                            if let Err(e) = hooks::run_hook(&hooks, HookEvent::OnCrash, &ctx).await {
+                                warn!("on_crash钩子失败: {}", e);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!("监控进程失败: {}", e);
+                    }
+                }
+                let _ = tx.send(false).await;
+            });
+        }
 
         info!("等待进程初始化({:?})...", self.config.init_wait());
         tokio::select! {
@@ -236,6 +430,7 @@ impl<'a> ProcessManager<'a> {
                                 info!("收到响应: {}", response.status());
                                 if response.status().is_success() {
                                     info!("健康检查通过，进程已在后台运行");
+                                    self.mark_ready(working_dir, &hook_env).await;
                                     return Ok(());
                                 }
                             }
@@ -246,7 +441,7 @@ impl<'a> ProcessManager<'a> {
                     }
                     Some(false) = rx.recv() => {
                         error!("进程在健康检查过程中退出");
-                        return Err(anyhow::anyhow!("进程在健康检查过程中退出"));
+                        return Err(Failure::HealthCheckFailed("进程在健康检查过程中退出".into()).into());
                     }
                 }
 
@@ -261,14 +456,46 @@ impl<'a> ProcessManager<'a> {
             }
 
             error!("健康检查最终失败，终止进程");
-            self.force_shutdown(pid as i32).await?;
-            return Err(anyhow::anyhow!("进程健康检查失败"));
+            let reason = format!("健康检查{}次均失败: {}", self.config.health_check_retries, url);
+            events::record(self.workspace, &self.process_name, EventKind::Unhealthy {
+                reason: reason.clone(),
+            });
+            self.notifier.notify(&Notification::new(NotifyEvent::Unhealthy, &self.process_name, reason)).await;
+            self.force_shutdown(pid as i32, None).await?;
+            self.cleanup_cgroup().await;
+            return Err(Failure::HealthCheckFailed("进程健康检查失败".into()).into());
         }
 
         info!("进程启动成功，已在后台运行");
+        self.mark_ready(working_dir, &hook_env).await;
         Ok(())
     }
 
+    /// 记录就绪事件并执行post_start钩子
+    async fn mark_ready(&self, working_dir: &Path, env_vars: &HashMap<String, String>) {
+        events::record(self.workspace, &self.process_name, EventKind::Ready);
+        if let Err(e) = self.run_hook(HookEvent::PostStart, working_dir, env_vars, None).await {
+            warn!("post_start钩子失败: {}", e);
+        }
+    }
+
+    /// 执行生命周期钩子
+    pub async fn run_hook(
+        &self,
+        event: HookEvent,
+        working_dir: &Path,
+        env_vars: &HashMap<String, String>,
+        exit_status: Option<&ExitStatus>,
+    ) -> Result<()> {
+        hooks::run_hook(&self.hooks, event, &HookContext {
+            workspace: self.workspace,
+            process_name: &self.process_name,
+            working_dir,
+            env_vars,
+            exit_status,
+        }).await
+    }
+
     pub async fn stop(&self) -> Result<()> {
         info!("开始停止进程");
 
@@ -278,38 +505,25 @@ impl<'a> ProcessManager<'a> {
         let port = state.port;
         info!("当前进程使用端口: {}", port);
 
+        if state.pid.is_some() {
+            if let Err(e) = self.run_hook(HookEvent::PreStop, &state.working_dir, &self.env_vars, None).await {
+                warn!("pre_stop钩子失败: {}", e);
+            }
+        }
+
         // 先停止monitor进程
         if let Some(monitor_pid) = state.monitor_pid {
             info!("检查监控进程 {} 是否存在", monitor_pid);
-            let exists = self.check_process_exists(monitor_pid).await?;
+            let exists = self.backend.exists(monitor_pid)
+                && self.verify_identity(monitor_pid, state.monitor_identity.as_ref())?;
             if exists {
                 info!("正在停止 PID 为 {} 的监控进程", monitor_pid);
-                if !self.try_graceful_shutdown(monitor_pid).await {
+                if !self.try_graceful_shutdown(monitor_pid, state.monitor_identity.as_ref()).await {
                     info!("优雅终止失败，将强制终止监控进程");
-                    self.force_shutdown(monitor_pid).await?;
+                    self.force_shutdown(monitor_pid, state.monitor_identity.as_ref()).await?;
                 }
             } else {
-                info!("监控进程 {} 已不存在,尝试查找实际运行的fuckrun进程", monitor_pid);
-                // 查找实际运行的fuckrun进程
-                let output = Command::new("tasklist")
-                    .args(["/FI", "IMAGENAME eq fuckrun.exe", "/NH", "/FO", "CSV"])
-                    .output()
-                    .await
-                    .context("查找fuckrun进程失败")?;
-
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                for line in output_str.lines() {
-                    if let Some(pid_str) = line.split(',').nth(1) {
-                        if let Ok(pid) = pid_str.trim_matches('"').parse::<i32>() {
-                            info!("找到运行中的fuckrun进程: {}", pid);
-                            if !self.try_graceful_shutdown(pid).await {
-                                info!("优雅终止失败，将强制终止监控进程");
-                                self.force_shutdown(pid).await?;
-                            }
-                            break;
-                        }
-                    }
-                }
+                info!("监控进程 {} 已不存在", monitor_pid);
             }
         } else {
             info!("没有找到运行中的监控进程");
@@ -318,12 +532,13 @@ impl<'a> ProcessManager<'a> {
         // 再停止用户进程
         if let Some(pid) = state.pid {
             info!("检查用户进程 {} 是否存在", pid);
-            let exists = self.check_process_exists(pid).await?;
+            let exists = self.backend.exists(pid)
+                && self.verify_identity(pid, state.identity.as_ref())?;
             if exists {
                 info!("正在停止 PID 为 {} 的用户进程", pid);
-                if !self.try_graceful_shutdown(pid).await {
+                if !self.try_graceful_shutdown(pid, state.identity.as_ref()).await {
                     info!("优雅终止失败，将强制终止进程");
-                    self.force_shutdown(pid).await?;
+                    self.force_shutdown(pid, state.identity.as_ref()).await?;
                 }
             } else {
                 info!("用户进程 {} 已不存在", pid);
@@ -332,40 +547,43 @@ impl<'a> ProcessManager<'a> {
             info!("没有找到运行中的用户进程");
         }
 
+        // 子孙进程可能已脱离父进程, 通过cgroup终止剩余的所有进程
+        self.cleanup_cgroup().await;
         self.cleanup_port(port).await?;
 
-        let mut state = state;
-        // 重置重启计数
-        state.restart_count = 0;
-        state.update_stopped_state();
-        state.save(self.workspace, &self.process_name)
-            .context("更新进程状态失败")?;
+        ProcessState::update(self.workspace, &self.process_name, |state| {
+            // 重置重启计数
+            state.restart_count = 0;
+            state.update_stopped_state();
+        })
+        .context("更新进程状态失败")?;
 
         info!("进程状态已更新为停止");
+        events::record(self.workspace, &self.process_name, EventKind::Stopped);
+
+        if let Err(e) = self.run_hook(HookEvent::PostStop, &state.working_dir, &self.env_vars, None).await {
+            warn!("post_stop钩子失败: {}", e);
+        }
         info!("进程停止操作完成");
         Ok(())
     }
 
-    /// 检查进程是否存在
-    async fn check_process_exists(&self, pid: i32) -> Result<bool> {
-        #[cfg(windows)]
-        {
-            let output = Command::new("tasklist")
-                .args(["/FI", &format!("PID eq {}", pid), "/NH"])
-                .output()
-                .await
-                .context("检查进程状态失败")?;
-
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            Ok(!output_str.is_empty() && !output_str.contains("No tasks"))
+    /// 校验PID是否仍是状态中记录的那个进程
+    ///
+    /// PID已被其他进程复用时把状态标记为失效并返回false, 避免误杀无关进程
+    fn verify_identity(&self, pid: i32, identity: Option<&ProcessIdentity>) -> Result<bool> {
+        let Some(identity) = identity else {
+            return Ok(true);
+        };
+        if identity.verify(pid) {
+            return Ok(true);
         }
 
-        #[cfg(unix)]
-        {
-            use nix::unistd::Pid;
-            use nix::sys::signal::{kill, Signal};
-            Ok(kill(Pid::from_raw(pid), None).is_ok())
-        }
+        warn!("PID {} 已被其他进程复用, 状态已失效", pid);
+        ProcessState::update(self.workspace, &self.process_name, |state| {
+            state.mark_stale(pid);
+        })?;
+        Ok(false)
     }
 
     pub async fn status(&self, health_check_url: Option<&str>) -> Result<bool> {
@@ -375,69 +593,32 @@ impl<'a> ProcessManager<'a> {
             Ok(state) => {
                 if let Some(pid) = state.pid {
                     info!("检查PID为{}的进程", pid);
-                    #[cfg(windows)]
-                    {
-                        let output = Command::new("tasklist")
-                            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
-                            .output()
-                            .await
-                            .context("检查进程状态失败")?;
-
-                        let output_str = String::from_utf8_lossy(&output.stdout);
-                        if !output_str.is_empty() && !output_str.contains("No tasks") {
-                            info!("进程正在运行");
-                            if let Some(url) = health_check_url {
-                                info!("检查健康状态: {}", url);
-                                match reqwest::get(url).await {
-                                    Ok(response) => {
-                                        let is_healthy = response.status().is_success();
-                                        if is_healthy {
-                                            info!("进程健康检查通过");
-                                        } else {
-                                            error!("进程健康检查失败: {}", response.status());
-                                        }
-                                        return Ok(is_healthy);
-                                    }
-                                    Err(e) => {
-                                        error!("健康检查失败: {}", e);
-                                        return Ok(false);
-                                    }
-                                }
-                            }
-                            return Ok(true);
-                        } else {
-                            info!("进程未运行");
-                        }
+                    if !self.verify_identity(pid, state.identity.as_ref())? {
+                        return Ok(false);
                     }
-
-                    #[cfg(unix)]
-                    {
-                        use nix::unistd::Pid;
-                        use nix::sys::signal::{kill, Signal};
-                        if kill(Pid::from_raw(pid), None).is_ok() {
-                            info!("进程正在运行");
-                            if let Some(url) = health_check_url {
-                                info!("检查健康状态: {}", url);
-                                match reqwest::get(url).await {
-                                    Ok(response) => {
-                                        let is_healthy = response.status().is_success();
-                                        if is_healthy {
-                                            info!("进程健康检查通过");
-                                        } else {
-                                            error!("进程健康检查失败: {}", response.status());
-                                        }
-                                        return Ok(is_healthy);
-                                    }
-                                    Err(e) => {
-                                        error!("健康检查失败: {}", e);
-                                        return Ok(false);
+                    if self.backend.exists(pid) {
+                        info!("进程正在运行");
+                        if let Some(url) = health_check_url {
+                            info!("检查健康状态: {}", url);
+                            match reqwest::get(url).await {
+                                Ok(response) => {
+                                    let is_healthy = response.status().is_success();
+                                    if is_healthy {
+                                        info!("进程健康检查通过");
+                                    } else {
+                                        error!("进程健康检查失败: {}", response.status());
                                     }
+                                    return Ok(is_healthy);
+                                }
+                                Err(e) => {
+                                    error!("健康检查失败: {}", e);
+                                    return Ok(false);
                                 }
                             }
-                            return Ok(true);
-                        } else {
-                            info!("进程未运行");
                         }
+                        return Ok(true);
+                    } else {
+                        info!("进程未运行");
                     }
                 } else {
                     info!("没有找到进程PID");
@@ -451,147 +632,96 @@ impl<'a> ProcessManager<'a> {
         Ok(false)
     }
 
+    /// 按健康检查配置重试, 直到进程运行且健康检查通过
+    ///
+    /// 守护进程模式下启动时不等待健康检查, 需要确认时调用
+    pub async fn wait_healthy(&self, health_check_url: &str) -> Result<()> {
+        for i in 0..self.config.health_check_retries {
+            if self.status(Some(health_check_url)).await? {
+                return Ok(());
+            }
+            if i + 1 < self.config.health_check_retries {
+                sleep(self.config.retry_interval()).await;
+            }
+        }
+        Err(Failure::HealthCheckFailed(format!(
+            "健康检查{}次均失败: {}", self.config.health_check_retries, health_check_url
+        )).into())
+    }
+
     pub async fn update_stopped_state(&self) -> Result<()> {
-        let mut state = ProcessState::load(self.workspace, &self.process_name)?;
-        state.pid = None;
-        state.save(self.workspace, &self.process_name)?;
+        ProcessState::update(self.workspace, &self.process_name, |state| {
+            state.pid = None;
+        })?;
         Ok(())
     }
 
-    async fn try_graceful_shutdown(&self, pid: i32) -> bool {
+    /// 请求进程退出并等待, 在超时前退出时返回true
+    ///
+    /// 信号只发给 `identity` 记录的进程, 进程已退出且PID被复用时视为已退出
+    async fn try_graceful_shutdown(&self, pid: i32, identity: Option<&ProcessIdentity>) -> bool {
         info!("尝试优雅终止进程 {}", pid);
-
-        #[cfg(windows)]
-        {
-            info!("Windows 平台：使用 taskkill /PID 发送终止信号");
-            match Command::new("taskkill")
-                .args(["/PID", &pid.to_string()])
-                .output()
-                .await
-            {
-                Ok(output) => {
-                    if output.status.success() {
-                        info!("已发送终止信号到进程 {}", pid);
-                        tokio::time::sleep(self.config.graceful_shutdown_timeout()).await;
-                        info!("优雅终止等待完成");
-                        true
-                    } else {
-                        let error = String::from_utf8_lossy(&output.stderr);
-                        warn!("发送终止信号失败: {}", error);
-                        false
-                    }
-                }
-                Err(e) => {
-                    warn!("执行 taskkill 命令失败: {}", e);
-                    false
-                }
+        match self.backend.signal_verified(pid, identity, Signal::Term) {
+            Ok(true) => {}
+            Ok(false) => {
+                info!("进程 {} 已退出", pid);
+                return true;
             }
-        }
-
-        #[cfg(unix)]
-        {
-            info!("Unix 平台：发送 SIGTERM 信号");
-            use nix::unistd::Pid;
-            use nix::sys::signal::{kill, Signal};
-            match kill(
-                Pid::from_raw(pid),
-                Signal::SIGTERM,
-            ) {
-                Ok(()) => {
-                    info!("已发送 SIGTERM 信号到进程 {}", pid);
-                    tokio::time::sleep(self.config.graceful_shutdown_timeout()).await;
-                    info!("优雅终止等待完成");
-                    true
-                }
-                Err(e) => {
-                    warn!("发送 SIGTERM 信号失败: {}", e);
-                    false
-                }
+            Err(e) => {
+                warn!("发送终止信号失败: {}", e);
+                return false;
             }
         }
+        info!("已发送终止信号到进程 {}", pid);
+        let exited = self.wait_for_exit(pid, self.config.graceful_shutdown_timeout()).await;
+        if exited {
+            info!("进程 {} 已退出", pid);
+        } else {
+            warn!("进程 {} 在{:?}内未退出", pid, self.config.graceful_shutdown_timeout());
+        }
+        exited
     }
 
-    async fn force_shutdown(&self, pid: i32) -> Result<()> {
+    /// 强制终止进程及其子进程
+    ///
+    /// 等待优雅退出期间进程可能已退出且PID被复用, 由后端按 `identity` 确认后再终止
+    async fn force_shutdown(&self, pid: i32, identity: Option<&ProcessIdentity>) -> Result<()> {
         info!("开始强制终止进程 {}", pid);
+        self.backend.kill_tree(pid, identity).context("强制终止进程失败")?;
+        self.wait_for_exit(pid, self.config.exit_wait()).await;
+        info!("进程 {} 已强制终止", pid);
+        Ok(())
+    }
 
-        #[cfg(windows)]
-        {
-            info!("Windows 平台：使用 taskkill /F 强制终止");
-            let output = Command::new("taskkill")
-                .args(["/F", "/PID", &pid.to_string()])
-                .output()
-                .await
-                .context("执行 taskkill 命令失败")?;
-
-            if !output.status.success() {
-                let error = String::from_utf8_lossy(&output.stderr);
-                warn!("强制终止进程失败: {}", error);
-                return Err(anyhow::anyhow!("强制终止进程失败: {}", error));
+    /// 等待进程退出, 超时返回false
+    async fn wait_for_exit(&self, pid: i32, timeout: std::time::Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.backend.exists(pid) {
+            if tokio::time::Instant::now() >= deadline {
+                return false;
             }
+            sleep(std::time::Duration::from_millis(100)).await;
         }
+        true
+    }
 
-        #[cfg(unix)]
-        {
-            info!("Unix 平台：发送 SIGKILL 信号");
-            use nix::unistd::Pid;
-            use nix::sys::signal::{kill, Signal};
-            kill(
-                Pid::from_raw(pid),
-                Signal::SIGKILL,
-            ).context("发送 SIGKILL 信号失败")?;
+    /// 终止进程cgroup中剩余的进程并删除cgroup, 没有cgroup时什么都不做
+    async fn cleanup_cgroup(&self) {
+        if let Some(cgroup) = Cgroup::open(self.workspace, &self.process_name) {
+            if let Err(e) = cgroup.kill_and_remove(self.config.exit_wait()).await {
+                warn!("清理进程cgroup失败: {:#}", e);
+            }
         }
-
-        tokio::time::sleep(self.config.exit_wait()).await;
-        info!("进程 {} 已强制终止", pid);
-
-        Ok(())
     }
 
     async fn cleanup_port(&self, port: u16) -> Result<()> {
         info!("开始清理端口 {} 的所有进程", port);
-
-        #[cfg(windows)]
-        {
-            let output = Command::new("netstat")
-                .args(["-ano", "-p", "TCP"])
-                .output()
-                .await
-                .context("执行 netstat 命令失败")?;
-
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            for line in output_str.lines() {
-                if line.contains(&format!(":{}", port)) {
-                    if let Some(pid_str) = line.split_whitespace().last() {
-                        if let Ok(pid) = pid_str.parse::<i32>() {
-                            info!("发现占用端口 {} 的进程: {}", port, pid);
-                            if let Err(e) = self.force_shutdown(pid).await {
-                                warn!("终止进程 {} 失败: {}", pid, e);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        #[cfg(unix)]
-        {
-            let output = Command::new("lsof")
-                .args(["-i", &format!(":{}", port), "-t"])
-                .output()
-                .await
-                .context("执行 lsof 命令失败")?;
-
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            for pid_str in output_str.lines() {
-                if let Ok(pid) = pid_str.parse::<i32>() {
-                    info!("发现占用端口 {} 的进程: {}", port, pid);
-                    if let Err(e) = self.force_shutdown(pid).await {
-                        warn!("终止进程 {} 失败: {}", pid, e);
-                    }
-                }
+        for pid in self.backend.port_owners(port) {
+            info!("发现占用端口 {} 的进程: {}", port, pid);
+            if let Err(e) = self.force_shutdown(pid, None).await {
+                warn!("终止进程 {} 失败: {}", pid, e);
             }
         }
-
         info!("端口 {} 清理完成", port);
         Ok(())
     }
@@ -599,35 +729,410 @@ impl<'a> ProcessManager<'a> {
     pub async fn handle_process_output(
         process_name: String,
         logger: crate::logger::Logger,
+        tail: OutputTail,
         mut stdout: Option<tokio::process::ChildStdout>,
         mut stderr: Option<tokio::process::ChildStderr>,
-    ) {
+    ) -> OutputReaders {
+        let mut readers = OutputReaders::default();
         if let Some(stdout) = stdout.take() {
             let process_name = process_name.clone();
             let logger = logger.clone();
-            tokio::spawn(async move {
+            let tail = tail.clone();
+            readers.push(tokio::spawn(async move {
                 use tokio::io::{BufReader, AsyncBufReadExt};
                 let mut reader = BufReader::new(stdout).lines();
                 while let Ok(Some(line)) = reader.next_line().await {
+                    tail.push("stdout", &line);
                     if let Err(e) = logger.write_stdout(&process_name, &line) {
                         error!("写入标准输出日志失败: {}", e);
                     }
                 }
-            });
+            }));
         }
 
         if let Some(stderr) = stderr.take() {
             let process_name = process_name.clone();
             let logger = logger.clone();
-            tokio::spawn(async move {
+            readers.push(tokio::spawn(async move {
                 use tokio::io::{BufReader, AsyncBufReadExt};
                 let mut reader = BufReader::new(stderr).lines();
                 while let Ok(Some(line)) = reader.next_line().await {
+                    tail.push("stderr", &line);
                     if let Err(e) = logger.write_stderr(&process_name, &line) {
                         error!("写入标准错误日志失败: {}", e);
                     }
                 }
-            });
+            }));
         }
+        readers
+    }
+}
+
+/// 进程退出后等待输出读取完的最长时间
+pub(crate) const OUTPUT_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// 读取进程stdout/stderr的任务
+#[derive(Default)]
+pub struct OutputReaders {
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl OutputReaders {
+    fn push(&mut self, task: tokio::task::JoinHandle<()>) {
+        self.tasks.push(task);
+    }
+
+    /// 等待读取任务处理完剩余输出(管道关闭), 最多等待 `timeout`
+    ///
+    /// 进程fork出的子进程可能仍持有管道, 超时后读取任务继续在后台运行
+    pub async fn wait(&mut self, timeout: std::time::Duration) {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut pending = Vec::new();
+        for mut task in self.tasks.drain(..) {
+            if tokio::time::timeout_at(deadline, &mut task).await.is_err() {
+                pending.push(task);
+            }
+        }
+        self.tasks = pending;
+    }
+}
+
+/// 进程异常退出后检查是否因超出内存上限被终止, 是时记录OOM事件
+pub(crate) fn check_oom_kill(
+    workspace: &Workspace,
+    process_name: &str,
+    cgroup: Option<&Cgroup>,
+    limits: &LimitsConfig,
+) -> bool {
+    if !cgroup.is_some_and(Cgroup::oom_killed) {
+        return false;
+    }
+    error!("进程 {} 因内存不足被终止", process_name);
+    events::record(workspace, process_name, EventKind::OomKilled {
+        memory_max: limits.memory_max_bytes(),
+    });
+    true
+}
+
+/// 读取PID文件, 文件不存在或内容不是PID时返回None
+pub(crate) fn read_pid_file(path: &Path) -> Option<i32> {
+    fs::read_to_string(path).ok()?
+        .trim()
+        .parse()
+        .ok()
+        .filter(|pid| *pid > 0)
+}
+
+/// 启动前删除上次运行留下的PID文件, 避免把已退出进程的PID当成新进程
+pub(crate) fn remove_stale_pid_file(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => {
+            info!("已删除旧的PID文件: {:?}", path);
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("删除旧的PID文件失败: {:?}", path)),
+    }
+}
+
+/// 等待启动的程序写入PID文件, 返回文件中仍在运行的进程的PID
+///
+/// 启动程序正常退出后继续等待; 启动程序异常退出或超时仍没有有效的PID时返回错误,
+/// 超时时终止仍在运行的启动程序
+pub(crate) async fn wait_for_pid_file(
+    path: &Path,
+    launcher: &mut tokio::process::Child,
+    timeout: std::time::Duration,
+) -> Result<i32> {
+    info!("等待PID文件: {:?}", path);
+    let backend = backend::native();
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut launcher_exited = false;
+    loop {
+        if let Some(pid) = read_pid_file(path).filter(|pid| backend.exists(*pid)) {
+            return Ok(pid);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            if !launcher_exited {
+                let _ = launcher.start_kill();
+            }
+            anyhow::bail!("等待PID文件超时({:?}): {:?}", timeout, path);
+        }
+        tokio::select! {
+            _ = sleep(std::time::Duration::from_millis(100)) => {}
+            status = launcher.wait(), if !launcher_exited => {
+                let status = status.context("等待启动程序退出失败")?;
+                if !status.success() {
+                    anyhow::bail!("启动程序异常退出({}), 没有写入PID文件: {:?}", status, path);
+                }
+                info!("启动程序已退出, 继续等待PID文件");
+                launcher_exited = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::events::ProcessEvent;
+    use crate::process::backend::MockBackend;
+
+    fn manager<'a>(workspace: &'a Workspace, backend: Arc<MockBackend>) -> ProcessManager<'a> {
+        let config = ProcessConfig {
+            graceful_shutdown_timeout_secs: 0,
+            exit_wait_ms: 0,
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::with_config(workspace, "web".into(), config);
+        manager.backend = backend;
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_stop_escalates_and_cleans_port() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        let state = ProcessState {
+            pid: Some(200),
+            monitor_pid: Some(100),
+            port: 8080,
+            restart_count: 2,
+            ..Default::default()
+        };
+        state.save(&workspace, "web")?;
+
+        let backend = Arc::new(MockBackend::default());
+        backend.spawn(100, 1);
+        backend.spawn(200, 100);
+        backend.spawn(201, 200);
+        backend.ignore_term(200);
+        backend.spawn(300, 1);
+        backend.listen(8080, 300);
+
+        manager(&workspace, backend.clone()).stop().await?;
+
+        // 监控进程响应Term; 用户进程忽略Term, 被连同子进程一起强制终止
+        let signals = backend.signals();
+        assert!(signals.contains(&(100, Signal::Term)));
+        assert!(signals.contains(&(200, Signal::Term)));
+        assert!(signals.contains(&(200, Signal::Kill)));
+        assert!(signals.contains(&(201, Signal::Kill)));
+        assert!(signals.contains(&(300, Signal::Kill)));
+        for pid in [100, 200, 201, 300] {
+            assert!(!backend.exists(pid));
+        }
+
+        let state = ProcessState::load(&workspace, "web")?;
+        assert_eq!(state.pid, None);
+        assert_eq!(state.restart_count, 0);
+        let events = ProcessEvent::list(&workspace, "web", None)?;
+        assert!(matches!(events.last().map(|e| &e.kind), Some(EventKind::Stopped)));
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_start_and_stop_real_process() -> Result<()> {
+        use crate::test_support::{Fixture, TestWorkspace};
+
+        let env = TestWorkspace::new();
+        let manager = env.manager("web");
+        let fixture = Fixture::Sleep;
+        manager.start(fixture.program(), &fixture.args(), &env.working_dir("web"), None, None).await?;
+
+        let pid = env.state("web").pid.expect("应记录PID");
+        assert!(manager.status(None).await?);
+        let err = manager.start(fixture.program(), &fixture.args(), &env.working_dir("web"), None, None)
+            .await.unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 4);
+
+        manager.stop().await?;
+        assert!(!manager.backend.exists(pid));
+        assert!(!manager.status(None).await?);
+        assert_eq!(env.state("web").pid, None);
+
+        let events = env.events("web");
+        assert!(matches!(events[0], EventKind::Started { pid: p } if p == pid));
+        assert_eq!(events[1], EventKind::Ready);
+        assert_eq!(events.last(), Some(&EventKind::Stopped));
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_start_follows_pid_file() -> Result<()> {
+        use crate::test_support::TestWorkspace;
+
+        let env = TestWorkspace::new();
+        let mut manager = env.manager("forking");
+        manager.config.pid_file_timeout_secs = 1;
+        manager.set_pid_file(Some(PathBuf::from("run/app.pid")));
+        let working_dir = env.working_dir("forking");
+        fs::create_dir_all(working_dir.join("run"))?;
+        // 旧的PID文件在启动前删除, 不会被当成新进程
+        fs::write(working_dir.join("run/app.pid"), std::process::id().to_string())?;
+
+        // 启动程序在后台运行真正的进程, 写入PID文件后立即退出, 并关闭输出
+        let args = vec!["-c".to_string(), "sleep 60 >/dev/null 2>&1 & echo $! > run/app.pid".to_string()];
+        manager.start("sh", &args, &working_dir, None, None).await?;
+
+        let pid = env.state("forking").pid.expect("应记录PID文件中的PID");
+        assert_eq!(read_pid_file(&working_dir.join("run/app.pid")), Some(pid));
+        assert_ne!(pid, std::process::id() as i32);
+        assert!(manager.backend.exists(pid));
+        let events = env.events("forking");
+        assert!(matches!(events[1], EventKind::Daemonized { pid: p, .. } if p == pid));
+        assert!(!events.iter().any(|event| matches!(event, EventKind::Exited { .. })));
+
+        manager.stop().await?;
+        assert!(!manager.backend.exists(pid));
+
+        // 一直没有写入PID文件时启动失败
+        let args = vec!["-c".to_string(), "exit 0".to_string()];
+        let err = manager.start("sh", &args, &working_dir, None, None).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("等待PID文件超时"));
+        assert_eq!(env.state("forking").pid, None);
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_start_validates_limits_and_runs_without_cgroup() -> Result<()> {
+        use crate::test_support::{Fixture, TestWorkspace};
+
+        let env = TestWorkspace::new();
+        let mut manager = env.manager("limited");
+        let working_dir = env.working_dir("limited");
+        manager.set_limits(LimitsConfig { memory_max: Some("lots".into()), ..Default::default() });
+        let err = manager.start(Fixture::Sleep.program(), &Fixture::Sleep.args(), &working_dir, None, None)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("limits.memory_max"));
+
+        // cgroup不可用时进程照常启动, 只是资源限制不生效
+        manager.set_limits(LimitsConfig { memory_max: Some("64M".into()), pids_max: Some(16), ..Default::default() });
+        manager.start(Fixture::Sleep.program(), &Fixture::Sleep.args(), &working_dir, None, None).await?;
+        let pid = env.state("limited").pid.expect("进程应已启动");
+        assert!(manager.backend.exists(pid));
+        manager.stop().await?;
+        assert!(!manager.backend.exists(pid));
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_stop_escalates_to_kill() -> Result<()> {
+        use crate::test_support::{Fixture, TestWorkspace};
+
+        let env = TestWorkspace::new();
+        let manager = env.manager("stubborn");
+        let fixture = Fixture::IgnoreTerm;
+        manager.start(fixture.program(), &fixture.args(), &env.working_dir("stubborn"), None, None).await?;
+        let pid = env.state("stubborn").pid.expect("应记录PID");
+
+        let started = std::time::Instant::now();
+        manager.stop().await?;
+        // 等待优雅终止超时后才强制终止
+        assert!(started.elapsed() >= manager.config.graceful_shutdown_timeout());
+        assert!(!manager.backend.exists(pid));
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_stop_kills_process_tree_ignoring_term() -> Result<()> {
+        use crate::test_support::{wait_until, Fixture, TestWorkspace};
+
+        let env = TestWorkspace::new();
+        let manager = env.manager("tree");
+        let fixture = Fixture::SpawnChildren(2);
+        manager.start(fixture.program(), &fixture.args(), &env.working_dir("tree"), None, None).await?;
+
+        let pid = env.state("tree").pid.expect("应记录PID");
+        assert!(wait_until(Duration::from_secs(2), || manager.backend.children(pid).len() == 2).await);
+        let children = manager.backend.children(pid);
+
+        manager.stop().await?;
+        assert!(!manager.backend.exists(pid));
+        for child in children {
+            assert!(!manager.backend.exists(child), "子进程 {} 未被终止", child);
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_start_waits_for_health_check() -> Result<()> {
+        use crate::test_support::{Fixture, HealthServer, TestWorkspace};
+
+        let env = TestWorkspace::new();
+        let server = HealthServer::start(true).await;
+        let manager = env.manager("web");
+        let fixture = Fixture::Sleep;
+        manager.start(fixture.program(), &fixture.args(), &env.working_dir("web"), Some(&server.url()), None)
+            .await?;
+        assert!(server.requests() >= 1);
+        assert!(manager.status(Some(&server.url())).await?);
+
+        // 健康检查失败后status报告不健康
+        server.set_healthy(false);
+        assert!(!manager.status(Some(&server.url())).await?);
+        manager.stop().await?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_start_fails_when_unhealthy() -> Result<()> {
+        use crate::test_support::{Fixture, HealthServer, TestWorkspace};
+
+        let env = TestWorkspace::new();
+        let server = HealthServer::start(false).await;
+        let manager = env.manager("web");
+        let fixture = Fixture::Sleep;
+        let err = manager.start(fixture.program(), &fixture.args(), &env.working_dir("web"), Some(&server.url()), None)
+            .await.unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 5);
+        assert_eq!(server.requests(), manager.config.health_check_retries as usize);
+
+        // 健康检查失败后进程被终止
+        let pid = env.state("web").pid.expect("应记录PID");
+        assert!(!manager.backend.exists(pid));
+        assert!(env.events("web").iter().any(|event| matches!(event, EventKind::Unhealthy { .. })));
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_start_fails_when_process_exits_during_init() -> Result<()> {
+        use crate::test_support::{wait_until, Fixture, TestWorkspace};
+
+        let env = TestWorkspace::new();
+        let manager = env.manager("crash");
+        let fixture = Fixture::CrashAfter(Duration::from_millis(100));
+        let result = manager.start(fixture.program(), &fixture.args(), &env.working_dir("crash"), None, None).await;
+        assert!(result.is_err());
+        let exited = wait_until(Duration::from_secs(2), || {
+            env.events("crash").contains(&EventKind::Exited { code: Some(1), signal: None })
+        }).await;
+        assert!(exited);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status_uses_backend() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        ProcessState { pid: Some(42), ..Default::default() }.save(&workspace, "web")?;
+
+        let backend = Arc::new(MockBackend::default());
+        let manager = manager(&workspace, backend.clone());
+        assert!(!manager.status(None).await?);
+        backend.spawn(42, 1);
+        assert!(manager.status(None).await?);
+        Ok(())
     }
 }
@@ -1,4 +1,12 @@
 pub mod manager;
 pub mod daemon;
+pub mod identity;
+pub mod backend;
+pub mod cgroup;
+pub mod sandbox;
+#[cfg(target_os = "linux")]
+pub mod pidfd;
+#[cfg(target_os = "linux")]
+pub mod subreaper;
 
 pub use manager::ProcessManager;
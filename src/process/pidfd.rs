@@ -0,0 +1,80 @@
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use nix::sys::signal::Signal;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+/// 指向一个进程的文件描述符(Linux 5.3+)
+///
+/// 与PID不同, pidfd始终指向打开时的那个进程, 不受PID复用影响。
+/// 进程退出后描述符变为可读, 不是自己的子进程也能等待其退出
+pub struct PidFd {
+    fd: OwnedFd,
+}
+
+impl PidFd {
+    /// 打开指定PID的pidfd, 进程不存在或内核不支持时返回错误
+    pub fn open(pid: i32) -> std::io::Result<Self> {
+        // SAFETY: pidfd_open只读取参数, 成功时返回新的描述符
+        let fd = unsafe { nix::libc::syscall(nix::libc::SYS_pidfd_open, pid, 0) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: 描述符刚由内核返回, 只归这里所有
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+        Ok(Self { fd })
+    }
+
+    /// 向进程发送信号, 进程已退出时返回ESRCH, 不会发给复用同一PID的新进程
+    pub fn send_signal(&self, signal: Signal) -> std::io::Result<()> {
+        // SAFETY: info为空指针时由内核填充信号信息, flags必须为0
+        let ret = unsafe {
+            nix::libc::syscall(
+                nix::libc::SYS_pidfd_send_signal,
+                self.fd.as_raw_fd(),
+                signal as i32,
+                std::ptr::null::<nix::libc::siginfo_t>(),
+                0,
+            )
+        };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// 等待进程退出, 可以安全地取消后再次等待
+    pub async fn exited(&self) -> std::io::Result<()> {
+        let fd = AsyncFd::with_interest(self.fd.as_raw_fd(), Interest::READABLE)?;
+        let _guard = fd.readable().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pidfd_waits_for_exit() -> std::io::Result<()> {
+        let mut child = std::process::Command::new("sleep").arg("0.3").spawn()?;
+        let pidfd = PidFd::open(child.id() as i32)?;
+        assert!(tokio::time::timeout(Duration::from_millis(50), pidfd.exited()).await.is_err());
+        tokio::time::timeout(Duration::from_secs(5), pidfd.exited()).await??;
+        child.wait()?;
+        assert!(PidFd::open(i32::MAX).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pidfd_signal_does_not_reach_reaped_pid() -> std::io::Result<()> {
+        let mut child = std::process::Command::new("sleep").arg("30").spawn()?;
+        let pidfd = PidFd::open(child.id() as i32)?;
+        pidfd.send_signal(Signal::SIGKILL)?;
+        child.wait()?;
+        // 进程已被回收, PID即使被复用也不会收到信号
+        let err = pidfd.send_signal(Signal::SIGKILL).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(nix::libc::ESRCH));
+        Ok(())
+    }
+}
@@ -0,0 +1,393 @@
+use std::path::Path;
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use tokio::process::Command;
+
+/// 进程沙箱, 用Linux命名空间隔离不受信任的脚本, 所有选项默认关闭
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    /// 使用独立的空 /tmp(tmpfs), 看不到其他进程的临时文件
+    #[serde(default)]
+    pub private_tmp: bool,
+    /// 以只读方式重新挂载应用目录(进程的工作目录)
+    #[serde(default)]
+    pub read_only_app_dir: bool,
+    /// 设置no_new_privs, 不能通过setuid程序或文件capabilities提权
+    #[serde(default)]
+    pub no_new_privileges: bool,
+    /// 清空所有capabilities(包括bounding set), root运行的进程也没有特权
+    #[serde(default)]
+    pub drop_capabilities: bool,
+    /// 使用独立的网络命名空间, 只有回环网卡
+    #[serde(default)]
+    pub private_network: bool,
+}
+
+impl SandboxConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 让子进程在exec前进入沙箱, 没有启用任何选项时什么都不做
+///
+/// 系统不允许创建需要的命名空间时返回错误, 不会在没有隔离的情况下启动进程
+#[cfg(target_os = "linux")]
+pub fn apply(config: &SandboxConfig, cmd: &mut Command, app_dir: &Path) -> Result<()> {
+    if config.is_empty() {
+        return Ok(());
+    }
+    let plan = linux::Plan::new(config, app_dir)?;
+    plan.probe()?;
+    log::info!("进程将在沙箱中运行: {:?}", config);
+    // SAFETY: 闭包在fork后的子进程中执行, 需要的路径和内容都已在fork前准备好,
+    // 只调用unshare/mount/prctl等系统调用
+    unsafe {
+        cmd.pre_exec(move || plan.enter());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(config: &SandboxConfig, _cmd: &mut Command, _app_dir: &Path) -> Result<()> {
+    if config.is_empty() {
+        return Ok(());
+    }
+    Err(crate::error::Failure::ConfigInvalid("sandbox 只支持Linux".into()).into())
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use anyhow::{Result, Context};
+    use nix::errno::Errno;
+    use nix::libc;
+    use nix::mount::{mount, MsFlags};
+    use nix::sched::{unshare, CloneFlags};
+    use nix::sys::statvfs::{statvfs, FsFlags};
+    use nix::sys::wait::{waitpid, WaitStatus};
+    use nix::unistd::{fork, ForkResult, Gid, Uid};
+    use super::SandboxConfig;
+
+    /// `capset` 使用的版本号(_LINUX_CAPABILITY_VERSION_3)
+    const CAPABILITY_VERSION: u32 = 0x2008_0522;
+
+    #[repr(C)]
+    struct CapHeader {
+        version: u32,
+        pid: i32,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    struct CapData {
+        effective: u32,
+        permitted: u32,
+        inheritable: u32,
+    }
+
+    /// 非root用户创建用户命名空间时写入的ID映射, 进程内外的UID/GID保持不变
+    struct IdMaps {
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+    }
+
+    /// fork前准备好的沙箱设置, 子进程中只需要执行系统调用
+    pub(super) struct Plan {
+        namespaces: CloneFlags,
+        id_maps: Option<IdMaps>,
+        /// 只读挂载的应用目录和它原有的挂载选项
+        read_only: Option<(CString, MsFlags)>,
+        private_tmp: bool,
+        private_network: bool,
+        no_new_privileges: bool,
+        /// 需要清空capabilities时为最大的capability编号
+        last_capability: Option<u64>,
+    }
+
+    impl Plan {
+        pub(super) fn new(config: &SandboxConfig, app_dir: &Path) -> Result<Self> {
+            let mut namespaces = CloneFlags::empty();
+            if config.private_tmp || config.read_only_app_dir {
+                namespaces |= CloneFlags::CLONE_NEWNS;
+            }
+            if config.private_network {
+                namespaces |= CloneFlags::CLONE_NEWNET;
+            }
+
+            // 非root用户在新的用户命名空间中才有挂载和清空bounding set的权限
+            let id_maps = if !Uid::effective().is_root() && (!namespaces.is_empty() || config.drop_capabilities) {
+                namespaces |= CloneFlags::CLONE_NEWUSER;
+                let (uid, gid) = (Uid::effective(), Gid::effective());
+                Some(IdMaps {
+                    uid_map: format!("{} {} 1", uid, uid).into_bytes(),
+                    gid_map: format!("{} {} 1", gid, gid).into_bytes(),
+                })
+            } else {
+                None
+            };
+
+            let read_only = if config.read_only_app_dir {
+                let path = CString::new(app_dir.as_os_str().as_bytes())
+                    .with_context(|| format!("无效的应用目录: {:?}", app_dir))?;
+                // 在用户命名空间中重新挂载时必须保留原有的nosuid/nodev等选项
+                let stat = statvfs(path.as_c_str())
+                    .with_context(|| format!("读取应用目录的挂载选项失败: {:?}", app_dir))?;
+                Some((path, locked_flags(stat.flags())))
+            } else {
+                None
+            };
+
+            let last_capability = if config.drop_capabilities {
+                let last = std::fs::read_to_string("/proc/sys/kernel/cap_last_cap")
+                    .ok()
+                    .and_then(|content| content.trim().parse().ok())
+                    .unwrap_or(63);
+                Some(last)
+            } else {
+                None
+            };
+
+            Ok(Self {
+                namespaces,
+                id_maps,
+                read_only,
+                private_tmp: config.private_tmp,
+                private_network: config.private_network,
+                no_new_privileges: config.no_new_privileges,
+                last_capability,
+            })
+        }
+
+        /// 在临时子进程中尝试创建命名空间, 系统不支持时返回说明原因的错误
+        ///
+        /// 在启动进程时失败只能得到一个错误码, 分不清是哪一步出错
+        pub(super) fn probe(&self) -> Result<()> {
+            if self.namespaces.is_empty() {
+                return Ok(());
+            }
+            let namespaces = self.namespaces;
+            // SAFETY: 子进程只调用unshare后立即_exit
+            let child = match unsafe { fork() }.context("创建检查沙箱的子进程失败")? {
+                ForkResult::Child => {
+                    let code = match unshare(namespaces) {
+                        Ok(()) => 0,
+                        Err(errno) => errno as i32,
+                    };
+                    unsafe { libc::_exit(code) }
+                }
+                ForkResult::Parent { child } => child,
+            };
+            match waitpid(child, None).context("等待检查沙箱的子进程失败")? {
+                WaitStatus::Exited(_, 0) => Ok(()),
+                WaitStatus::Exited(_, code) => {
+                    let errno = Errno::from_i32(code);
+                    if namespaces.contains(CloneFlags::CLONE_NEWUSER) {
+                        anyhow::bail!(
+                            "当前系统不允许非特权用户创建命名空间, 无法启用沙箱({}): \
+                             需要以root运行, 或开启非特权用户命名空间(sysctl kernel.unprivileged_userns_clone=1, user.max_user_namespaces > 0)",
+                            errno.desc()
+                        );
+                    }
+                    anyhow::bail!("创建沙箱需要的命名空间失败, 内核可能不支持或被容器限制({})", errno.desc());
+                }
+                status => anyhow::bail!("检查沙箱的子进程异常退出: {:?}", status),
+            }
+        }
+
+        /// 在fork后的子进程中进入沙箱
+        pub(super) fn enter(&self) -> std::io::Result<()> {
+            if !self.namespaces.is_empty() {
+                unshare(self.namespaces)?;
+            }
+            if let Some(id_maps) = &self.id_maps {
+                write_file(c"/proc/self/setgroups", b"deny")?;
+                write_file(c"/proc/self/uid_map", &id_maps.uid_map)?;
+                write_file(c"/proc/self/gid_map", &id_maps.gid_map)?;
+            }
+
+            if self.namespaces.contains(CloneFlags::CLONE_NEWNS) {
+                // 挂载不传播回原来的命名空间
+                mount(None::<&CStr>, c"/", None::<&CStr>, MsFlags::MS_REC | MsFlags::MS_PRIVATE, None::<&CStr>)?;
+                if let Some((app_dir, flags)) = &self.read_only {
+                    mount(Some(app_dir.as_c_str()), app_dir.as_c_str(), None::<&CStr>, MsFlags::MS_BIND | MsFlags::MS_REC, None::<&CStr>)?;
+                    mount(
+                        None::<&CStr>,
+                        app_dir.as_c_str(),
+                        None::<&CStr>,
+                        MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY | *flags,
+                        None::<&CStr>,
+                    )?;
+                    // 工作目录在挂载前已经切换过, 需要重新进入才能看到只读的挂载
+                    nix::unistd::chdir(app_dir.as_c_str())?;
+                }
+                // 应用目录在 /tmp 下时, 挂载tmpfs后仍可以通过工作目录访问
+                if self.private_tmp {
+                    mount(
+                        Some(c"tmpfs"),
+                        c"/tmp",
+                        Some(c"tmpfs"),
+                        MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
+                        Some(c"mode=1777"),
+                    )?;
+                }
+            }
+
+            if self.private_network {
+                loopback_up()?;
+            }
+
+            if self.no_new_privileges {
+                // SAFETY: prctl只修改当前进程的属性
+                if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+
+            if let Some(last_capability) = self.last_capability {
+                drop_capabilities(last_capability)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// 重新挂载时需要保留的挂载选项
+    fn locked_flags(flags: FsFlags) -> MsFlags {
+        let mut locked = MsFlags::empty();
+        for (fs_flag, ms_flag) in [
+            (FsFlags::ST_NOSUID, MsFlags::MS_NOSUID),
+            (FsFlags::ST_NODEV, MsFlags::MS_NODEV),
+            (FsFlags::ST_NOEXEC, MsFlags::MS_NOEXEC),
+            (FsFlags::ST_NOATIME, MsFlags::MS_NOATIME),
+            (FsFlags::ST_NODIRATIME, MsFlags::MS_NODIRATIME),
+            (FsFlags::ST_RELATIME, MsFlags::MS_RELATIME),
+        ] {
+            if flags.contains(fs_flag) {
+                locked |= ms_flag;
+            }
+        }
+        locked
+    }
+
+    /// 写入 /proc 下的文件, 不分配内存
+    fn write_file(path: &CStr, content: &[u8]) -> std::io::Result<()> {
+        // SAFETY: 路径和内容由调用方保证有效
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let written = libc::write(fd, content.as_ptr().cast(), content.len());
+            let error = std::io::Error::last_os_error();
+            libc::close(fd);
+            if written < 0 {
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// 启用新网络命名空间中的回环网卡, 新命名空间中的网卡默认是关闭的
+    fn loopback_up() -> std::io::Result<()> {
+        // SAFETY: ifreq是普通的C结构体, 全零是有效值
+        unsafe {
+            let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let mut request: libc::ifreq = std::mem::zeroed();
+            for (dst, src) in request.ifr_name.iter_mut().zip(b"lo\0") {
+                *dst = *src as libc::c_char;
+            }
+            let mut result = libc::ioctl(fd, libc::SIOCGIFFLAGS as _, &mut request);
+            if result == 0 {
+                request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+                result = libc::ioctl(fd, libc::SIOCSIFFLAGS as _, &request);
+            }
+            let error = std::io::Error::last_os_error();
+            libc::close(fd);
+            if result < 0 {
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// 清空bounding set、ambient和当前的capabilities, exec后以root运行也不会重新获得
+    fn drop_capabilities(last_capability: u64) -> std::io::Result<()> {
+        // SAFETY: prctl和capset只修改当前进程的属性, 参数指向有效的结构体
+        unsafe {
+            for capability in 0..=last_capability {
+                if libc::prctl(libc::PR_CAPBSET_DROP, capability, 0, 0, 0) != 0 && Errno::last() != Errno::EINVAL {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            // 旧内核不支持ambient capabilities
+            if libc::prctl(libc::PR_CAP_AMBIENT, libc::PR_CAP_AMBIENT_CLEAR_ALL, 0, 0, 0) != 0 && Errno::last() != Errno::EINVAL {
+                return Err(std::io::Error::last_os_error());
+            }
+            let header = CapHeader { version: CAPABILITY_VERSION, pid: 0 };
+            let data = [CapData::default(); 2];
+            if libc::syscall(libc::SYS_capset, &header, data.as_ptr()) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    /// 在沙箱中执行shell检查, 返回是否全部通过; 系统不支持命名空间时返回None
+    async fn run_checks(config: &SandboxConfig, app_dir: &Path, script: &str) -> Option<bool> {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script).current_dir(app_dir);
+        if let Err(e) = apply(config, &mut cmd, app_dir) {
+            eprintln!("跳过沙箱测试: {:#}", e);
+            return None;
+        }
+        Some(cmd.status().await.unwrap().success())
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_isolates_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = tempfile::NamedTempFile::new().unwrap();
+        let config = SandboxConfig {
+            private_tmp: true,
+            read_only_app_dir: true,
+            no_new_privileges: true,
+            drop_capabilities: true,
+            private_network: true,
+        };
+        let script = format!(
+            "test ! -e {marker} && \
+             test -z \"$(ls -A /tmp)\" && \
+             ! touch app.txt 2>/dev/null && \
+             test \"$(grep -c : /proc/net/dev)\" = 1 && grep -q 'lo:' /proc/net/dev && \
+             {{ ! command -v ip >/dev/null || ip -o link show lo | grep -q ',UP'; }} && \
+             grep -q '^NoNewPrivs:[[:space:]]*1$' /proc/self/status && \
+             grep -q '^CapEff:[[:space:]]*0*$' /proc/self/status && \
+             grep -q '^CapBnd:[[:space:]]*0*$' /proc/self/status",
+            marker = marker.path().display(),
+        );
+        if let Some(passed) = run_checks(&config, dir.path(), &script).await {
+            assert!(passed, "沙箱中的检查未通过");
+            // 沙箱外的目录和 /tmp 不受影响
+            assert!(marker.path().exists());
+            std::fs::write(dir.path().join("app.txt"), "ok").unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_sandbox_is_noop() {
+        let dir = tempfile::tempdir().unwrap();
+        let passed = run_checks(&SandboxConfig::default(), dir.path(), "touch app.txt").await;
+        assert_eq!(passed, Some(true));
+        assert!(dir.path().join("app.txt").exists());
+    }
+}
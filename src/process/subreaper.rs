@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use log::{info, warn};
+use tokio::time::sleep;
+use super::backend::{LinuxBackend, ProcessBackend};
+
+/// 两次检查僵尸子进程之间的间隔
+const REAP_INTERVAL: Duration = Duration::from_secs(5);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 让当前进程成为子进程收割者(Linux 3.4+)
+///
+/// 被监控的进程自己fork后退出(nginx等以守护进程方式运行的程序)时, 留下的进程会挂到
+/// 监控进程下而不是init下, 监控进程可以继续跟踪并获得它的退出状态
+pub fn enable() -> bool {
+    // SAFETY: prctl只修改当前进程的属性
+    let ret = unsafe { nix::libc::prctl(nix::libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) };
+    if ret != 0 {
+        warn!("设置子进程收割者失败: {}", std::io::Error::last_os_error());
+        return false;
+    }
+    ENABLED.store(true, Ordering::Relaxed);
+    true
+}
+
+/// 当前进程是否已成为子进程收割者
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 成为子进程收割者并在后台回收无人等待的僵尸进程, 需要在tokio运行时中调用
+pub fn start() {
+    if enable() {
+        tokio::spawn(reap_orphans());
+    }
+}
+
+/// 当前进程的子进程, 启动被监控的进程前记录下来, 查找它留下的进程时排除
+pub fn children() -> HashSet<i32> {
+    LinuxBackend.children(std::process::id() as i32).into_iter().collect()
+}
+
+/// 等待被监控的进程fork后留下的进程挂到当前进程下
+///
+/// 它退出后新挂到当前进程下且仍在运行的子进程是它留下的, `existing` 是启动它之前已有的子进程。
+/// 双重fork时中间进程可能稍晚退出, 最多等待 `timeout`; 没有成为收割者时返回None
+pub async fn find_daemonized(existing: &HashSet<i32>, timeout: Duration) -> Option<i32> {
+    if !is_enabled() {
+        return None;
+    }
+    let backend = LinuxBackend;
+    let me = std::process::id() as i32;
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let mut children: Vec<i32> = backend.children(me)
+            .into_iter()
+            .filter(|pid| !existing.contains(pid) && backend.exists(*pid))
+            .collect();
+        children.sort();
+        if let Some(pid) = children.first() {
+            return Some(*pid);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return None;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// 定期回收挂到当前进程下的僵尸进程
+///
+/// 自己启动的子进程由tokio或监控循环及时回收, 连续两次检查都还是僵尸的才是无人等待的孤儿进程,
+/// 避免抢走其他地方等待的退出状态
+async fn reap_orphans() {
+    use nix::sys::wait::{waitpid, WaitPidFlag};
+    use nix::unistd::Pid;
+
+    let backend = LinuxBackend;
+    let me = std::process::id() as i32;
+    let mut seen = HashSet::new();
+    loop {
+        sleep(REAP_INTERVAL).await;
+        let zombies: HashSet<i32> = backend.children(me)
+            .into_iter()
+            .filter(|pid| !backend.exists(*pid))
+            .collect();
+        for pid in zombies.intersection(&seen) {
+            if waitpid(Pid::from_raw(*pid), Some(WaitPidFlag::WNOHANG)).is_ok() {
+                info!("回收孤儿进程 {}", pid);
+            }
+        }
+        seen = zombies;
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{Result, Context};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use crate::config::ProcessConfig;
+use crate::error::Failure;
+use crate::state::write_atomic;
+use crate::workspace::Workspace;
+
+/// 蓝绿切换代理配置
+///
+/// 设置后FuckRun在 `listen` 上运行TCP代理, 进程实例轮流使用 `ports` 中的两个内部端口,
+/// 重启和发布时先在另一个端口启动新实例, 健康检查通过后再切换代理目标
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    /// 对外监听地址
+    pub listen: String,
+    /// 实例轮流使用的两个内部端口
+    pub ports: [u16; 2],
+    /// 切换后等待旧实例上连接结束的最长时间(秒)
+    #[serde(default = "default_drain_timeout")]
+    pub drain_timeout: u64,
+}
+
+fn default_drain_timeout() -> u64 { 10 }
+
+impl ProxyConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.ports[0] == self.ports[1] {
+            return Err(Failure::ConfigInvalid(format!("proxy.ports 需要两个不同的端口: {:?}", self.ports)).into());
+        }
+        if self.listen_port().is_none() {
+            return Err(Failure::ConfigInvalid(format!("proxy.listen 不是有效的地址: {}", self.listen)).into());
+        }
+        Ok(())
+    }
+
+    /// 对外监听的端口
+    pub fn listen_port(&self) -> Option<u16> {
+        self.listen.rsplit_once(':')?.1.parse().ok()
+    }
+
+    /// 另一个内部端口
+    pub fn other_port(&self, port: u16) -> u16 {
+        if port == self.ports[0] { self.ports[1] } else { self.ports[0] }
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.drain_timeout)
+    }
+}
+
+/// 使用指定内部端口的实例名称
+pub fn instance_name(name: &str, port: u16) -> String {
+    format!("{}@{}", name, port)
+}
+
+/// 使用指定内部端口的实例配置
+///
+/// args和健康检查地址中的 `{port}` 替换为实例端口, 同时通过 `PORT` 环境变量传给进程
+pub fn instance_config(process: &ProcessConfig, port: u16) -> ProcessConfig {
+    let port_str = port.to_string();
+    let mut instance = process.clone();
+    instance.name = instance_name(&process.name, port);
+    instance.args = process.args.iter().map(|arg| arg.replace("{port}", &port_str)).collect();
+    instance.health_check_url = process.health_check_url.as_ref().map(|url| url.replace("{port}", &port_str));
+    instance.env.insert("PORT".to_string(), port_str);
+    instance.process.default_port = port;
+    instance.proxy = None;
+    instance
+}
+
+/// 记录代理当前目标端口的文件
+fn target_file(workspace: &Workspace, name: &str) -> PathBuf {
+    workspace.get_process_dir(name).join("proxy.target")
+}
+
+/// 记录各目标端口上活动连接数的文件
+fn connections_file(workspace: &Workspace, name: &str) -> PathBuf {
+    workspace.get_process_dir(name).join("proxy.connections")
+}
+
+/// 代理当前转发到的内部端口
+pub fn active_port(workspace: &Workspace, name: &str) -> Option<u16> {
+    fs::read_to_string(target_file(workspace, name)).ok()?.trim().parse().ok()
+}
+
+/// 切换代理目标, 之后的新连接转发到该端口
+///
+/// 先写临时文件再重命名, 代理不会读到写了一半的内容
+pub fn set_active_port(workspace: &Workspace, name: &str, port: u16) -> Result<()> {
+    workspace.ensure_process_dirs(name)?;
+    let file = target_file(workspace, name);
+    let staging = file.with_extension("tmp");
+    fs::write(&staging, port.to_string()).context("写入代理目标失败")?;
+    fs::rename(&staging, &file).context("切换代理目标失败")?;
+    info!("代理 {} 已切换到端口 {}", name, port);
+    Ok(())
+}
+
+/// 指定内部端口上仍在代理的连接数
+pub fn active_connections(workspace: &Workspace, name: &str, port: u16) -> usize {
+    fs::read_to_string(connections_file(workspace, name))
+        .ok()
+        .and_then(|content| serde_json::from_str::<HashMap<u16, usize>>(&content).ok())
+        .and_then(|counts| counts.get(&port).copied())
+        .unwrap_or(0)
+}
+
+/// TCP反向代理
+///
+/// 每个新连接按当时的目标端口转发, 已建立的连接不受切换影响, 直到自然结束
+#[derive(Clone)]
+pub struct TcpProxy {
+    workspace: Workspace,
+    name: String,
+    /// 各目标端口上的活动连接数, 变化后由写入任务保存到文件
+    connections: Arc<watch::Sender<HashMap<u16, usize>>>,
+}
+
+impl TcpProxy {
+    pub fn new(workspace: Workspace, name: String) -> Self {
+        Self {
+            workspace,
+            name,
+            connections: Arc::new(watch::Sender::new(HashMap::new())),
+        }
+    }
+
+    pub async fn serve(&self, listen: &str) -> Result<()> {
+        let listener = TcpListener::bind(listen).await
+            .with_context(|| format!("监听代理地址 {} 失败", listen))?;
+        info!("代理 {} 已启动: {}", self.name, listener.local_addr()?);
+        self.serve_listener(listener).await
+    }
+
+    pub async fn serve_listener(&self, listener: TcpListener) -> Result<()> {
+        self.workspace.ensure_process_dirs(&self.name)?;
+        tokio::spawn(save_connections(
+            self.connections.subscribe(),
+            connections_file(&self.workspace, &self.name),
+        ));
+        loop {
+            let (stream, peer) = listener.accept().await.context("接受代理连接失败")?;
+            let Some(port) = active_port(&self.workspace, &self.name) else {
+                warn!("代理 {} 还没有目标, 关闭来自 {} 的连接", self.name, peer);
+                continue;
+            };
+            let proxy = self.clone();
+            tokio::spawn(async move {
+                proxy.track(port, 1);
+                if let Err(e) = proxy.forward(stream, port).await {
+                    warn!("转发来自 {} 的连接到端口 {} 失败: {}", peer, port, e);
+                }
+                proxy.track(port, -1);
+            });
+        }
+    }
+
+    async fn forward(&self, mut client: TcpStream, port: u16) -> Result<()> {
+        let mut upstream = TcpStream::connect(("127.0.0.1", port)).await
+            .with_context(|| format!("连接端口 {} 失败", port))?;
+        tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+        Ok(())
+    }
+
+    /// 更新活动连接数, 供切换时判断旧实例是否排空
+    fn track(&self, port: u16, delta: isize) {
+        self.connections.send_modify(|connections| {
+            let count = connections.entry(port).or_insert(0);
+            *count = count.saturating_add_signed(delta);
+            if *count == 0 {
+                connections.remove(&port);
+            }
+        });
+    }
+}
+
+/// 连接数变化后原子写入文件, 多次变化只写入最新的值, 代理停止后结束
+async fn save_connections(mut changes: watch::Receiver<HashMap<u16, usize>>, file: PathBuf) {
+    loop {
+        let content = serde_json::to_vec(&*changes.borrow_and_update());
+        let file = file.clone();
+        let result = match content {
+            Ok(content) => tokio::task::spawn_blocking(move || write_atomic(&file, &content)).await
+                .unwrap_or_else(|e| Err(e.into())),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            warn!("保存代理连接数失败: {}", e);
+        }
+        if changes.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// 回应固定内容的后端, 连接保持到客户端关闭
+    async fn backend(reply: &'static str) -> Result<u16> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 16];
+                    while let Ok(n) = stream.read(&mut buf).await {
+                        if n == 0 || stream.write_all(reply.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        Ok(port)
+    }
+
+    async fn ask(stream: &mut TcpStream) -> Result<String> {
+        stream.write_all(b"?").await?;
+        let mut buf = [0u8; 16];
+        let n = stream.read(&mut buf).await?;
+        Ok(String::from_utf8_lossy(&buf[..n]).into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_switch_keeps_existing_connections() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        let (blue, green) = (backend("blue").await?, backend("green").await?);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let front = listener.local_addr()?;
+        let proxy = TcpProxy::new(workspace.clone(), "web".into());
+        tokio::spawn(async move { proxy.serve_listener(listener).await });
+
+        set_active_port(&workspace, "web", blue)?;
+        let mut old = TcpStream::connect(front).await?;
+        assert_eq!(ask(&mut old).await?, "blue");
+        assert!(crate::test_support::wait_until(Duration::from_secs(5), || {
+            active_connections(&workspace, "web", blue) == 1
+        }).await);
+
+        // 切换后新连接到green, 已有连接仍然连着blue
+        set_active_port(&workspace, "web", green)?;
+        let mut new = TcpStream::connect(front).await?;
+        assert_eq!(ask(&mut new).await?, "green");
+        assert_eq!(ask(&mut old).await?, "blue");
+
+        drop(old);
+        let drained = crate::test_support::wait_until(Duration::from_secs(5), || {
+            active_connections(&workspace, "web", blue) == 0
+        }).await;
+        assert!(drained);
+        assert_eq!(active_connections(&workspace, "web", green), 1);
+        assert!(!workspace.get_process_dir("web").read_dir()?.any(|entry| {
+            entry.is_ok_and(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+        }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_instance_config_substitutes_port() {
+        let process: ProcessConfig = serde_yaml::from_str(r#"
+name: web
+program: python3
+args: ["-m", "http.server", "{port}"]
+health_check_url: http://127.0.0.1:{port}/
+proxy:
+  listen: 127.0.0.1:8000
+  ports: [8001, 8002]
+"#).unwrap();
+        let proxy = process.proxy.clone().unwrap();
+        assert!(proxy.validate().is_ok());
+        assert_eq!(proxy.other_port(8001), 8002);
+
+        let instance = instance_config(&process, 8002);
+        assert_eq!(instance.name, "web@8002");
+        assert_eq!(instance.args[2], "8002");
+        assert_eq!(instance.health_check_url.as_deref(), Some("http://127.0.0.1:8002/"));
+        assert_eq!(instance.env["PORT"], "8002");
+        assert_eq!(instance.process.default_port, 8002);
+        assert!(instance.proxy.is_none());
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use log::info;
+use serde::{Serialize, Deserialize};
+use crate::error::Failure;
+
+/// 进程运行时, 负责解析解释器和运行环境
+///
+/// `program` 写解释器(如 `python`)时参数原样传入; 写脚本(如 `app.py`)时由运行时的解释器执行该脚本。
+/// 没有配置时根据 `program` 推断, 见 [`RuntimeConfig::infer`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RuntimeConfig {
+    /// 直接执行程序
+    #[default]
+    Binary,
+    /// Python, 可使用虚拟环境
+    Python {
+        /// 虚拟环境目录, 相对路径基于工作目录
+        #[serde(default)]
+        venv: Option<PathBuf>,
+    },
+    /// Node.js, 可使用nvm安装的指定版本
+    Node {
+        /// nvm中的Node版本, 如 `20.11.0`
+        #[serde(default)]
+        version: Option<String>,
+    },
+    /// Shell脚本
+    Shell {
+        /// 使用的shell, 默认Unix下为sh, Windows下为cmd
+        #[serde(default)]
+        shell: Option<String>,
+    },
+}
+
+/// 解析后实际执行的命令
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+}
+
+impl RuntimeConfig {
+    /// 根据程序名推断运行时
+    pub fn infer(program: &str) -> Self {
+        let path = Path::new(program);
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let extension = path.extension().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+        if is_python(program) || extension == "py" {
+            RuntimeConfig::Python { venv: None }
+        } else if stem == "node" || matches!(extension.as_str(), "js" | "mjs" | "cjs") {
+            RuntimeConfig::Node { version: None }
+        } else if matches!(extension.as_str(), "sh" | "bat" | "cmd") {
+            RuntimeConfig::Shell { shell: None }
+        } else {
+            RuntimeConfig::Binary
+        }
+    }
+
+    /// 解析实际执行的程序、参数和环境变量
+    pub fn resolve(
+        &self,
+        program: &str,
+        args: &[String],
+        working_dir: &Path,
+        mut env: HashMap<String, String>,
+    ) -> Result<ResolvedCommand> {
+        let (program, args) = match self {
+            RuntimeConfig::Binary => (program.to_string(), args.to_vec()),
+            RuntimeConfig::Python { venv } => {
+                let interpreter = match venv {
+                    Some(venv) => {
+                        let venv = working_dir.join(venv);
+                        if !venv.is_dir() {
+                            return Err(Failure::ConfigInvalid(format!("虚拟环境不存在: {}", venv.display())).into());
+                        }
+                        let bin_dir = venv.join(if cfg!(windows) { "Scripts" } else { "bin" });
+                        prepend_path(&mut env, &bin_dir)?;
+                        env.insert("VIRTUAL_ENV".to_string(), venv.to_string_lossy().into_owned());
+                        env.remove("PYTHONHOME");
+                        bin_dir.join(if cfg!(windows) { "python.exe" } else { "python" })
+                            .to_string_lossy()
+                            .into_owned()
+                    }
+                    // Windows上优先使用py启动器
+                    None if cfg!(windows) => "py".to_string(),
+                    None if is_python(program) => program.to_string(),
+                    None => "python".to_string(),
+                };
+                let is_script = has_extension(program, &["py"]);
+                with_interpreter(interpreter, program, args, is_python(program), is_script)
+            }
+            RuntimeConfig::Node { version } => {
+                let interpreter = match version {
+                    Some(version) => {
+                        let bin_dir = node_bin_dir(&nvm_dir()?, version);
+                        if !bin_dir.is_dir() {
+                            return Err(Failure::ConfigInvalid(format!(
+                                "未找到nvm安装的Node {}: {}", version, bin_dir.display()
+                            )).into());
+                        }
+                        prepend_path(&mut env, &bin_dir)?;
+                        bin_dir.join(if cfg!(windows) { "node.exe" } else { "node" })
+                            .to_string_lossy()
+                            .into_owned()
+                    }
+                    None => "node".to_string(),
+                };
+                let is_node = Path::new(program).file_stem().is_some_and(|stem| stem == "node");
+                let is_script = has_extension(program, &["js", "mjs", "cjs"]);
+                with_interpreter(interpreter, program, args, is_node, is_script)
+            }
+            RuntimeConfig::Shell { shell } => {
+                let shell = shell.clone().unwrap_or_else(|| {
+                    if cfg!(windows) { "cmd".to_string() } else { "sh".to_string() }
+                });
+                let mut shell_args = Vec::new();
+                if cfg!(windows) && shell.eq_ignore_ascii_case("cmd") {
+                    shell_args.push("/C".to_string());
+                }
+                shell_args.push(program.to_string());
+                shell_args.extend_from_slice(args);
+                (shell, shell_args)
+            }
+        };
+        info!("运行时解析结果: {} {:?}", program, args);
+        Ok(ResolvedCommand { program, args, env })
+    }
+}
+
+fn is_python(program: &str) -> bool {
+    let stem = Path::new(program).file_stem().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+    stem == "py" || stem.starts_with("python")
+}
+
+fn has_extension(program: &str, extensions: &[&str]) -> bool {
+    Path::new(program).extension()
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+/// program本身是解释器时替换为解析出的解释器, 是脚本时交给解释器执行,
+/// 其他程序(如虚拟环境中的gunicorn)直接执行, 通过PATH找到运行时目录中的可执行文件
+fn with_interpreter(
+    interpreter: String,
+    program: &str,
+    args: &[String],
+    is_interpreter: bool,
+    is_script: bool,
+) -> (String, Vec<String>) {
+    if is_interpreter {
+        (interpreter, args.to_vec())
+    } else if is_script {
+        let mut interpreter_args = vec![program.to_string()];
+        interpreter_args.extend_from_slice(args);
+        (interpreter, interpreter_args)
+    } else {
+        (program.to_string(), args.to_vec())
+    }
+}
+
+/// 把目录加到PATH最前面, 子进程和程序查找都会优先使用这里的可执行文件
+fn prepend_path(env: &mut HashMap<String, String>, dir: &Path) -> Result<()> {
+    let current = env.get("PATH").cloned()
+        .or_else(|| std::env::var("PATH").ok())
+        .unwrap_or_default();
+    let paths = std::iter::once(dir.to_path_buf()).chain(std::env::split_paths(&current));
+    let joined = std::env::join_paths(paths).context("设置PATH失败")?;
+    env.insert("PATH".to_string(), joined.to_string_lossy().into_owned());
+    Ok(())
+}
+
+/// nvm的安装目录: NVM_DIR(Windows下为NVM_HOME), 默认 ~/.nvm
+fn nvm_dir() -> Result<PathBuf> {
+    let var = if cfg!(windows) { "NVM_HOME" } else { "NVM_DIR" };
+    if let Some(dir) = std::env::var_os(var) {
+        return Ok(PathBuf::from(dir));
+    }
+    let home = std::env::var_os("HOME").context("无法确定nvm目录, 请设置NVM_DIR")?;
+    Ok(PathBuf::from(home).join(".nvm"))
+}
+
+/// nvm中指定版本Node的可执行文件目录
+fn node_bin_dir(nvm_dir: &Path, version: &str) -> PathBuf {
+    let version = format!("v{}", version.trim_start_matches('v'));
+    if cfg!(windows) {
+        nvm_dir.join(version)
+    } else {
+        nvm_dir.join("versions").join("node").join(version).join("bin")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_runtime() {
+        assert_eq!(RuntimeConfig::infer("python3"), RuntimeConfig::Python { venv: None });
+        assert_eq!(RuntimeConfig::infer("/usr/bin/python3.11"), RuntimeConfig::Python { venv: None });
+        assert_eq!(RuntimeConfig::infer("app.py"), RuntimeConfig::Python { venv: None });
+        assert_eq!(RuntimeConfig::infer("server.js"), RuntimeConfig::Node { version: None });
+        assert_eq!(RuntimeConfig::infer("./deploy.sh"), RuntimeConfig::Shell { shell: None });
+        assert_eq!(RuntimeConfig::infer("sleep"), RuntimeConfig::Binary);
+        assert_eq!(node_bin_dir(Path::new("/nvm"), "20.1.0"), node_bin_dir(Path::new("/nvm"), "v20.1.0"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_python_venv_and_shell() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir_all(dir.path().join(".venv").join("bin"))?;
+        let env = HashMap::from([("PATH".to_string(), "/usr/bin".to_string())]);
+        let runtime = RuntimeConfig::Python { venv: Some(".venv".into()) };
+
+        let script = runtime.resolve("app.py", &["--debug".into()], dir.path(), env.clone())?;
+        let venv = dir.path().join(".venv");
+        assert_eq!(script.program, venv.join("bin").join("python").to_string_lossy());
+        assert_eq!(script.args, vec!["app.py".to_string(), "--debug".to_string()]);
+        assert_eq!(script.env["PATH"], format!("{}:/usr/bin", venv.join("bin").display()));
+        assert_eq!(script.env["VIRTUAL_ENV"], venv.to_string_lossy());
+
+        // program本身是解释器时参数原样传入
+        let module = runtime.resolve("python3", &["-m".into(), "http.server".into()], dir.path(), env.clone())?;
+        assert_eq!(module.program, script.program);
+        assert_eq!(module.args, vec!["-m".to_string(), "http.server".to_string()]);
+
+        let missing = RuntimeConfig::Python { venv: Some("missing".into()) };
+        assert!(missing.resolve("app.py", &[], dir.path(), env.clone()).is_err());
+
+        let shell = RuntimeConfig::Shell { shell: None }.resolve("./run.sh", &["a".into()], dir.path(), env)?;
+        assert_eq!((shell.program.as_str(), shell.args), ("sh", vec!["./run.sh".to_string(), "a".to_string()]));
+        Ok(())
+    }
+}
@@ -0,0 +1,856 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context};
+use chrono::{DateTime, Local, SecondsFormat};
+use log::{Level, warn};
+use serde::{Serialize, Deserialize, Serializer};
+
+/// 日志投递目标配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    /// RFC5424 syslog
+    Syslog {
+        /// 地址: UDP/TCP为host:port, Unix为socket路径
+        address: String,
+        /// 传输协议
+        #[serde(default)]
+        transport: SyslogTransport,
+        /// syslog facility(默认16, 即local0)
+        #[serde(default = "default_facility")]
+        facility: u8,
+        /// APP-NAME字段, 默认使用进程名
+        #[serde(default)]
+        app_name: Option<String>,
+        /// 断线时的缓冲行数
+        #[serde(default = "default_buffer_size")]
+        buffer_size: usize,
+    },
+    /// journald原生协议socket
+    Journald {
+        /// socket路径
+        #[serde(default = "default_journald_socket")]
+        path: String,
+        /// 断线时的缓冲行数
+        #[serde(default = "default_buffer_size")]
+        buffer_size: usize,
+    },
+    /// 按行转发的TCP连接
+    Tcp {
+        /// 远端地址 host:port
+        address: String,
+        /// 断线时的缓冲行数
+        #[serde(default = "default_buffer_size")]
+        buffer_size: usize,
+        /// 重连间隔(毫秒)
+        #[serde(default = "default_reconnect_delay_ms")]
+        reconnect_delay_ms: u64,
+    },
+    /// 批量POST到HTTP接口
+    Http {
+        /// 接收日志的URL
+        url: String,
+        /// 每批最多的日志条数
+        #[serde(default = "default_batch_size")]
+        batch_size: usize,
+        /// 批量发送间隔(毫秒)
+        #[serde(default = "default_flush_interval_ms")]
+        flush_interval_ms: u64,
+        /// 发送失败时的缓冲行数
+        #[serde(default = "default_buffer_size")]
+        buffer_size: usize,
+    },
+}
+
+/// syslog传输协议
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogTransport {
+    #[default]
+    Udp,
+    Tcp,
+    Unix,
+}
+
+fn default_facility() -> u8 { 16 }
+fn default_buffer_size() -> usize { 1000 }
+fn default_reconnect_delay_ms() -> u64 { 1000 }
+fn default_batch_size() -> usize { 100 }
+fn default_flush_interval_ms() -> u64 { 1000 }
+fn default_journald_socket() -> String { String::from("/run/systemd/journal/socket") }
+
+/// 单条日志记录
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Local>,
+    #[serde(serialize_with = "serialize_level")]
+    pub level: Level,
+    /// 日志来源(进程名或fuckrun)
+    pub source: String,
+    /// 输出流(stdout/stderr), 主程序日志为空
+    pub stream: Option<String>,
+    pub message: String,
+}
+
+fn serialize_level<S: Serializer>(level: &Level, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(level.as_str())
+}
+
+impl LogRecord {
+    pub fn new(level: Level, source: &str, stream: Option<&str>, message: &str) -> Self {
+        Self {
+            timestamp: Local::now(),
+            level,
+            source: source.to_string(),
+            stream: stream.map(String::from),
+            message: message.to_string(),
+        }
+    }
+
+    /// syslog severity
+    fn severity(&self) -> u8 {
+        match self.level {
+            Level::Error => 3,
+            Level::Warn => 4,
+            Level::Info => 6,
+            Level::Debug | Level::Trace => 7,
+        }
+    }
+
+    /// 格式化为RFC5424消息
+    pub fn to_rfc5424(&self, facility: u8, hostname: &str, app_name: &str) -> String {
+        let pri = facility as u32 * 8 + self.severity() as u32;
+        format!(
+            "<{}>1 {} {} {} {} {} - {}",
+            pri,
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Micros, false),
+            nil_if_empty(hostname),
+            nil_if_empty(app_name),
+            std::process::id(),
+            self.stream.as_deref().unwrap_or("-"),
+            self.message,
+        )
+    }
+
+    /// 格式化为journald原生协议
+    pub fn to_journald(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if self.message.contains('\n') {
+            // 多行消息需要使用带长度前缀的二进制格式
+            out.extend_from_slice(b"MESSAGE\n");
+            out.extend_from_slice(&(self.message.len() as u64).to_le_bytes());
+            out.extend_from_slice(self.message.as_bytes());
+            out.push(b'\n');
+        } else {
+            out.extend_from_slice(format!("MESSAGE={}\n", self.message).as_bytes());
+        }
+        out.extend_from_slice(format!("PRIORITY={}\n", self.severity()).as_bytes());
+        out.extend_from_slice(format!("SYSLOG_IDENTIFIER={}\n", self.source).as_bytes());
+        if let Some(stream) = &self.stream {
+            out.extend_from_slice(format!("FUCKRUN_STREAM={}\n", stream).as_bytes());
+        }
+        out
+    }
+
+    /// 格式化为单行文本
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} [{}] {}{} {}",
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Millis, false),
+            self.level,
+            self.source,
+            self.stream.as_ref().map(|s| format!("[{}]", s)).unwrap_or_default(),
+            self.message.replace('\n', "\\n"),
+        )
+    }
+}
+
+fn nil_if_empty(value: &str) -> &str {
+    if value.is_empty() { "-" } else { value }
+}
+
+fn local_hostname() -> String {
+    #[cfg(unix)]
+    {
+        nix::unistd::gethostname()
+            .ok()
+            .and_then(|h| h.into_string().ok())
+            .unwrap_or_default()
+    }
+    #[cfg(windows)]
+    {
+        std::env::var("COMPUTERNAME").unwrap_or_default()
+    }
+}
+
+/// 实际负责发送日志的传输层
+trait Transport: Send {
+    /// 发送一批日志, 失败时整批保留等待重试
+    fn send(&mut self, records: &[LogRecord]) -> Result<()>;
+    /// 连接失败后丢弃当前连接
+    fn reset(&mut self) {}
+}
+
+struct SyslogUdp {
+    address: String,
+    socket: Option<UdpSocket>,
+    facility: u8,
+    hostname: String,
+    app_name: Option<String>,
+}
+
+impl Transport for SyslogUdp {
+    fn send(&mut self, records: &[LogRecord]) -> Result<()> {
+        if self.socket.is_none() {
+            let socket = UdpSocket::bind("0.0.0.0:0").context("绑定UDP端口失败")?;
+            socket.connect(&self.address).context("连接syslog服务器失败")?;
+            self.socket = Some(socket);
+        }
+        let socket = self.socket.as_ref().unwrap();
+        for record in records {
+            let app_name = self.app_name.as_deref().unwrap_or(&record.source);
+            let message = record.to_rfc5424(self.facility, &self.hostname, app_name);
+            socket.send(message.as_bytes()).context("发送syslog消息失败")?;
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.socket = None;
+    }
+}
+
+/// 按行写入TCP流的通用格式化方式
+enum LineFormat {
+    /// RFC6587 octet-counting帧
+    Syslog { facility: u8, hostname: String, app_name: Option<String> },
+    Plain,
+}
+
+struct TcpLines {
+    address: String,
+    stream: Option<TcpStream>,
+    format: LineFormat,
+}
+
+impl TcpLines {
+    fn connect(&mut self) -> Result<&mut TcpStream> {
+        if self.stream.is_none() {
+            let addr = self.address.to_socket_addrs()
+                .context("解析地址失败")?
+                .next()
+                .context("地址解析结果为空")?;
+            let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(3))
+                .context("连接日志服务器失败")?;
+            stream.set_write_timeout(Some(Duration::from_secs(3)))?;
+            self.stream = Some(stream);
+        }
+        Ok(self.stream.as_mut().unwrap())
+    }
+}
+
+impl Transport for TcpLines {
+    fn send(&mut self, records: &[LogRecord]) -> Result<()> {
+        let mut payload = String::new();
+        for record in records {
+            match &self.format {
+                LineFormat::Syslog { facility, hostname, app_name } => {
+                    let app_name = app_name.as_deref().unwrap_or(&record.source);
+                    let message = record.to_rfc5424(*facility, hostname, app_name);
+                    payload.push_str(&format!("{} {}", message.len(), message));
+                }
+                LineFormat::Plain => {
+                    payload.push_str(&record.to_line());
+                    payload.push('\n');
+                }
+            }
+        }
+        let stream = self.connect()?;
+        stream.write_all(payload.as_bytes()).context("写入日志失败")?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.stream = None;
+    }
+}
+
+#[cfg(unix)]
+struct UnixSocket {
+    path: String,
+    socket: Option<std::os::unix::net::UnixDatagram>,
+    /// true为journald格式, false为syslog格式
+    journald: bool,
+    facility: u8,
+    app_name: Option<String>,
+}
+
+#[cfg(unix)]
+impl Transport for UnixSocket {
+    fn send(&mut self, records: &[LogRecord]) -> Result<()> {
+        if self.socket.is_none() {
+            let socket = std::os::unix::net::UnixDatagram::unbound()
+                .context("创建Unix socket失败")?;
+            socket.connect(&self.path)
+                .with_context(|| format!("连接Unix socket失败: {}", self.path))?;
+            self.socket = Some(socket);
+        }
+        let socket = self.socket.as_ref().unwrap();
+        for record in records {
+            let message = if self.journald {
+                record.to_journald()
+            } else {
+                // 本机syslog socket不需要主机名
+                let app_name = self.app_name.as_deref().unwrap_or(&record.source);
+                record.to_rfc5424(self.facility, "", app_name).into_bytes()
+            };
+            socket.send(&message).context("发送日志失败")?;
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.socket = None;
+    }
+}
+
+#[cfg(unix)]
+fn unix_socket(path: &str, journald: bool, facility: u8, app_name: Option<String>) -> Result<Box<dyn Transport>> {
+    Ok(Box::new(UnixSocket {
+        path: path.to_string(),
+        socket: None,
+        journald,
+        facility,
+        app_name,
+    }))
+}
+
+#[cfg(windows)]
+fn unix_socket(_path: &str, _journald: bool, _facility: u8, _app_name: Option<String>) -> Result<Box<dyn Transport>> {
+    anyhow::bail!("当前平台不支持Unix socket")
+}
+
+struct HttpBatch {
+    url: String,
+    client: reqwest::Client,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Transport for HttpBatch {
+    fn send(&mut self, records: &[LogRecord]) -> Result<()> {
+        let request = self.client.post(&self.url).json(records);
+        let response = self.runtime.block_on(async { request.send().await })
+            .context("发送日志批次失败")?;
+        if !response.status().is_success() {
+            anyhow::bail!("日志接口返回错误状态: {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+/// 发送策略
+struct Policy {
+    buffer_size: usize,
+    batch_size: usize,
+    flush_interval: Duration,
+    retry_delay: Duration,
+}
+
+/// 写入有界缓冲区, 超出时丢弃最旧的记录, 返回丢弃的条数
+fn push_bounded(pending: &mut VecDeque<LogRecord>, record: LogRecord, capacity: usize) -> u64 {
+    pending.push_back(record);
+    let mut dropped = 0;
+    while pending.len() > capacity.max(1) {
+        pending.pop_front();
+        dropped += 1;
+    }
+    dropped
+}
+
+fn run_worker(
+    name: String,
+    rx: Receiver<LogRecord>,
+    mut transport: Box<dyn Transport>,
+    policy: Policy,
+    dropped: std::sync::Arc<AtomicU64>,
+) {
+    let mut pending: VecDeque<LogRecord> = VecDeque::new();
+    let mut last_flush = Instant::now();
+    let mut retry_at: Option<Instant> = None;
+    let mut failed = false;
+    let mut closed = false;
+
+    while !closed {
+        let wait = if pending.is_empty() {
+            policy.flush_interval
+        } else {
+            Duration::from_millis(50)
+        };
+        match rx.recv_timeout(wait) {
+            Ok(record) => {
+                dropped.fetch_add(push_bounded(&mut pending, record, policy.buffer_size), Ordering::Relaxed);
+                while let Ok(record) = rx.try_recv() {
+                    dropped.fetch_add(push_bounded(&mut pending, record, policy.buffer_size), Ordering::Relaxed);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => closed = true,
+        }
+
+        if pending.is_empty() {
+            continue;
+        }
+        if retry_at.is_some_and(|t| Instant::now() < t) && !closed {
+            continue;
+        }
+        let due = pending.len() >= policy.batch_size
+            || last_flush.elapsed() >= policy.flush_interval
+            || policy.batch_size <= 1
+            || closed;
+        if !due {
+            continue;
+        }
+
+        while !pending.is_empty() {
+            let count = pending.len().min(policy.batch_size.max(1));
+            let batch: Vec<LogRecord> = pending.iter().take(count).cloned().collect();
+            match transport.send(&batch) {
+                Ok(()) => {
+                    pending.drain(..count);
+                    retry_at = None;
+                    if failed {
+                        warn!("日志投递 {} 已恢复", name);
+                        failed = false;
+                    }
+                }
+                Err(e) => {
+                    if !failed {
+                        warn!("日志投递 {} 失败, 将重试: {}", name, e);
+                        failed = true;
+                    }
+                    transport.reset();
+                    retry_at = Some(Instant::now() + policy.retry_delay);
+                    break;
+                }
+            }
+        }
+        last_flush = Instant::now();
+    }
+
+    let lost = dropped.load(Ordering::Relaxed) + pending.len() as u64;
+    if lost > 0 {
+        warn!("日志投递 {} 共丢弃 {} 条日志", name, lost);
+    }
+}
+
+/// 单个日志投递目标的后台发送句柄
+struct SinkHandle {
+    name: String,
+    tx: Option<SyncSender<LogRecord>>,
+    worker: Option<JoinHandle<()>>,
+    dropped: std::sync::Arc<AtomicU64>,
+}
+
+impl SinkHandle {
+    fn spawn(config: &SinkConfig) -> Result<Self> {
+        let hostname = local_hostname();
+        let (name, transport, policy): (String, Box<dyn Transport>, Policy) = match config {
+            SinkConfig::Syslog { address, transport, facility, app_name, buffer_size } => {
+                let sink: Box<dyn Transport> = match transport {
+                    SyslogTransport::Udp => Box::new(SyslogUdp {
+                        address: address.clone(),
+                        socket: None,
+                        facility: *facility,
+                        hostname,
+                        app_name: app_name.clone(),
+                    }),
+                    SyslogTransport::Tcp => Box::new(TcpLines {
+                        address: address.clone(),
+                        stream: None,
+                        format: LineFormat::Syslog {
+                            facility: *facility,
+                            hostname,
+                            app_name: app_name.clone(),
+                        },
+                    }),
+                    SyslogTransport::Unix => unix_socket(address, false, *facility, app_name.clone())?,
+                };
+                (format!("syslog://{}", address), sink, Policy {
+                    buffer_size: *buffer_size,
+                    batch_size: 1,
+                    flush_interval: Duration::from_millis(200),
+                    retry_delay: Duration::from_secs(1),
+                })
+            }
+            SinkConfig::Journald { path, buffer_size } => {
+                let sink = unix_socket(path, true, default_facility(), None)?;
+                (format!("journald://{}", path), sink, Policy {
+                    buffer_size: *buffer_size,
+                    batch_size: 1,
+                    flush_interval: Duration::from_millis(200),
+                    retry_delay: Duration::from_secs(1),
+                })
+            }
+            SinkConfig::Tcp { address, buffer_size, reconnect_delay_ms } => {
+                (format!("tcp://{}", address), Box::new(TcpLines {
+                    address: address.clone(),
+                    stream: None,
+                    format: LineFormat::Plain,
+                }), Policy {
+                    buffer_size: *buffer_size,
+                    batch_size: 1,
+                    flush_interval: Duration::from_millis(200),
+                    retry_delay: Duration::from_millis(*reconnect_delay_ms),
+                })
+            }
+            SinkConfig::Http { url, batch_size, flush_interval_ms, buffer_size } => {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .context("创建HTTP日志投递运行时失败")?;
+                let client = reqwest::Client::builder()
+                    .timeout(Duration::from_secs(10))
+                    .build()?;
+                (url.clone(), Box::new(HttpBatch {
+                    url: url.clone(),
+                    client,
+                    runtime,
+                }), Policy {
+                    buffer_size: *buffer_size,
+                    batch_size: *batch_size,
+                    flush_interval: Duration::from_millis(*flush_interval_ms),
+                    retry_delay: Duration::from_millis(*flush_interval_ms),
+                })
+            }
+        };
+
+        let (tx, rx) = mpsc::sync_channel(policy.buffer_size.max(1));
+        let dropped = std::sync::Arc::new(AtomicU64::new(0));
+        let worker_name = name.clone();
+        let worker_dropped = dropped.clone();
+        let worker = std::thread::Builder::new()
+            .name(format!("sink {}", name))
+            .spawn(move || run_worker(worker_name, rx, transport, policy, worker_dropped))
+            .context("启动日志投递线程失败")?;
+
+        Ok(Self {
+            name,
+            tx: Some(tx),
+            worker: Some(worker),
+            dropped,
+        })
+    }
+
+    fn emit(&self, record: LogRecord) {
+        if let Some(tx) = &self.tx {
+            match tx.try_send(record) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// 关闭发送通道并等待剩余日志发送完成
+    fn shutdown(&mut self, timeout: Duration) {
+        self.tx.take();
+        if let Some(worker) = self.worker.take() {
+            let deadline = Instant::now() + timeout;
+            while !worker.is_finished() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            if worker.is_finished() {
+                let _ = worker.join();
+            } else {
+                warn!("日志投递 {} 未能在 {:?} 内发送完剩余日志", self.name, timeout);
+            }
+        }
+    }
+}
+
+/// 一组日志投递目标
+#[derive(Default)]
+pub struct SinkSet {
+    sinks: Mutex<Vec<SinkHandle>>,
+}
+
+impl SinkSet {
+    /// 根据配置创建投递目标, 创建失败的目标只记录警告
+    pub fn from_configs(configs: &[SinkConfig]) -> Self {
+        let mut sinks = Vec::new();
+        for config in configs {
+            match SinkHandle::spawn(config) {
+                Ok(sink) => sinks.push(sink),
+                Err(e) => warn!("创建日志投递目标失败 {:?}: {}", config, e),
+            }
+        }
+        Self {
+            sinks: Mutex::new(sinks),
+        }
+    }
+
+    /// 投递一条日志(不阻塞, 缓冲区满时丢弃)
+    pub fn emit(&self, record: &LogRecord) {
+        if let Ok(sinks) = self.sinks.lock() {
+            for sink in sinks.iter() {
+                sink.emit(record.clone());
+            }
+        }
+    }
+
+    /// 关闭所有投递目标, 尽量在超时前发送完缓冲的日志
+    pub fn shutdown(&self, timeout: Duration) {
+        if let Ok(mut sinks) = self.sinks.lock() {
+            for sink in sinks.iter_mut() {
+                sink.shutdown(timeout);
+            }
+            sinks.clear();
+        }
+    }
+}
+
+impl std::fmt::Debug for SinkSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self.sinks.lock()
+            .map(|s| s.iter().map(|h| h.name.clone()).collect())
+            .unwrap_or_default();
+        f.debug_struct("SinkSet").field("sinks", &names).finish()
+    }
+}
+
+impl Drop for SinkSet {
+    fn drop(&mut self) {
+        self.shutdown(Duration::from_secs(2));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_rfc5424_format() {
+        let record = LogRecord::new(Level::Error, "web", Some("stderr"), "boom");
+        let message = record.to_rfc5424(16, "host1", "web");
+        assert!(message.starts_with("<131>1 "));
+        assert!(message.contains(" host1 web "));
+        assert!(message.ends_with(" stderr - boom"));
+    }
+
+    #[test]
+    fn test_push_bounded_drops_oldest() {
+        let mut pending = VecDeque::new();
+        let mut dropped = 0;
+        for i in 0..5 {
+            dropped += push_bounded(&mut pending, LogRecord::new(Level::Info, "web", None, &i.to_string()), 3);
+        }
+        assert_eq!(dropped, 2);
+        assert_eq!(pending.front().unwrap().message, "2");
+    }
+
+    #[test]
+    fn test_syslog_udp_sink() {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let sinks = SinkSet::from_configs(&[SinkConfig::Syslog {
+            address,
+            transport: SyslogTransport::Udp,
+            facility: 1,
+            app_name: Some("fuckrun".into()),
+            buffer_size: 10,
+        }]);
+        sinks.emit(&LogRecord::new(Level::Info, "web", Some("stdout"), "hello syslog"));
+
+        let mut buf = [0u8; 1024];
+        let n = listener.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..n]);
+        assert!(message.starts_with("<14>1 "));
+        assert!(message.contains(" fuckrun "));
+        assert!(message.ends_with("hello syslog"));
+    }
+
+    #[test]
+    fn test_tcp_sink_buffers_until_listener_available() {
+        // 先占用一个端口再释放, 让首次连接失败
+        let address = {
+            let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap()
+        };
+
+        let sinks = SinkSet::from_configs(&[SinkConfig::Tcp {
+            address: address.to_string(),
+            buffer_size: 10,
+            reconnect_delay_ms: 100,
+        }]);
+        sinks.emit(&LogRecord::new(Level::Info, "web", Some("stdout"), "first"));
+        sinks.emit(&LogRecord::new(Level::Info, "web", Some("stdout"), "second"));
+        std::thread::sleep(Duration::from_millis(300));
+
+        let listener = TcpListener::bind(address).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        assert!(lines.next().unwrap().unwrap().ends_with("web[stdout] first"));
+        assert!(lines.next().unwrap().unwrap().ends_with("web[stdout] second"));
+    }
+
+    #[test]
+    fn test_http_batch_sink() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/logs", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = stream.read(&mut buf).unwrap();
+                assert!(n > 0, "连接提前关闭");
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(pos) = text.find("\r\n\r\n") {
+                    let length = text.lines()
+                        .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if request.len() >= pos + 4 + length {
+                        break;
+                    }
+                }
+            }
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        let sinks = SinkSet::from_configs(&[SinkConfig::Http {
+            url,
+            batch_size: 2,
+            flush_interval_ms: 5000,
+            buffer_size: 10,
+        }]);
+        sinks.emit(&LogRecord::new(Level::Info, "web", Some("stdout"), "one"));
+        sinks.emit(&LogRecord::new(Level::Warn, "web", Some("stderr"), "two"));
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /logs"));
+        let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+        let records: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(records.as_array().unwrap().len(), 2);
+        assert_eq!(records[1]["level"], "WARN");
+        assert_eq!(records[1]["message"], "two");
+    }
+
+    #[test]
+    fn test_syslog_tcp_sink_uses_octet_counting() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let sinks = SinkSet::from_configs(&[SinkConfig::Syslog {
+            address,
+            transport: SyslogTransport::Tcp,
+            facility: 16,
+            app_name: None,
+            buffer_size: 10,
+        }]);
+        sinks.emit(&LogRecord::new(Level::Warn, "web", Some("stderr"), "first"));
+        sinks.emit(&LogRecord::new(Level::Info, "web", Some("stdout"), "second"));
+
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut received = String::new();
+        let mut buf = [0u8; 1024];
+        while !received.ends_with("second") {
+            let n = stream.read(&mut buf).unwrap();
+            assert!(n > 0, "连接提前关闭");
+            received.push_str(&String::from_utf8_lossy(&buf[..n]));
+        }
+
+        // 每条消息前是十进制长度和一个空格
+        let mut messages = Vec::new();
+        let mut rest = received.as_str();
+        while !rest.is_empty() {
+            let (length, tail) = rest.split_once(' ').unwrap();
+            let length: usize = length.parse().unwrap();
+            messages.push(&tail[..length]);
+            rest = &tail[length..];
+        }
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("<132>1 "));
+        assert!(messages[0].contains(" web "));
+        assert!(messages[0].ends_with("stderr - first"));
+        assert!(messages[1].starts_with("<134>1 "));
+        assert!(messages[1].ends_with("stdout - second"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_syslog_unix_sink() {
+        use std::os::unix::net::UnixDatagram;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.sock");
+        let listener = UnixDatagram::bind(&path).unwrap();
+        listener.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let sinks = SinkSet::from_configs(&[SinkConfig::Syslog {
+            address: path.display().to_string(),
+            transport: SyslogTransport::Unix,
+            facility: 16,
+            app_name: Some("fuckrun".into()),
+            buffer_size: 10,
+        }]);
+        sinks.emit(&LogRecord::new(Level::Info, "web", Some("stdout"), "hello unix"));
+
+        let mut buf = [0u8; 1024];
+        let n = listener.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..n]);
+        // 本机socket不带主机名
+        assert!(message.starts_with("<134>1 "));
+        assert!(message.contains(" - fuckrun "));
+        assert!(message.ends_with("stdout - hello unix"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_journald_sink() {
+        use std::os::unix::net::UnixDatagram;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.sock");
+        let listener = UnixDatagram::bind(&path).unwrap();
+        listener.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let sinks = SinkSet::from_configs(&[SinkConfig::Journald {
+            path: path.display().to_string(),
+            buffer_size: 10,
+        }]);
+        sinks.emit(&LogRecord::new(Level::Error, "web", Some("stderr"), "boom"));
+        sinks.emit(&LogRecord::new(Level::Info, "web", None, "line1\nline2"));
+
+        let mut buf = [0u8; 1024];
+        let n = listener.recv(&mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&buf[..n]),
+            "MESSAGE=boom\nPRIORITY=3\nSYSLOG_IDENTIFIER=web\nFUCKRUN_STREAM=stderr\n"
+        );
+
+        // 多行消息使用带长度前缀的二进制字段
+        let n = listener.recv(&mut buf).unwrap();
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&11u64.to_le_bytes());
+        expected.extend_from_slice(b"line1\nline2\nPRIORITY=6\nSYSLOG_IDENTIFIER=web\n");
+        assert_eq!(&buf[..n], expected.as_slice());
+    }
+}
@@ -0,0 +1,175 @@
+use std::net::TcpListener;
+use anyhow::{Result, Context};
+use log::info;
+use serde::{Serialize, Deserialize};
+use tokio::process::Command;
+use crate::error::Failure;
+
+/// 传给子进程的第一个文件描述符, 与systemd的 `SD_LISTEN_FDS_START` 一致
+pub const LISTEN_FDS_START: i32 = 3;
+
+/// 由FuckRun预先绑定并传给子进程的监听套接字
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SocketConfig {
+    /// 监听地址
+    #[serde(default = "default_host")]
+    pub host: String,
+    /// 监听端口
+    pub port: u16,
+}
+
+fn default_host() -> String { String::from("0.0.0.0") }
+
+impl SocketConfig {
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// 已绑定的监听套接字
+///
+/// 按systemd的socket activation约定传给子进程: 从文件描述符3开始依次排列,
+/// 通过 `LISTEN_FDS` 和 `LISTEN_PID` 环境变量告诉子进程。只要持有者还在,
+/// 重启子进程期间套接字保持监听, 新连接在队列中等待而不是被拒绝
+#[derive(Default)]
+pub struct ListenSockets {
+    listeners: Vec<TcpListener>,
+}
+
+impl ListenSockets {
+    /// 绑定所有配置的地址, 没有配置时返回空集合
+    pub fn bind(configs: &[SocketConfig]) -> Result<Self> {
+        if !configs.is_empty() && cfg!(not(unix)) {
+            return Err(Failure::ConfigInvalid("sockets 只支持Unix系统".into()).into());
+        }
+        let listeners = configs.iter()
+            .map(|config| {
+                let listener = TcpListener::bind(config.address())
+                    .with_context(|| format!("绑定监听地址 {} 失败, 端口可能已被占用", config.address()))?;
+                info!("已绑定监听地址: {}", listener.local_addr()?);
+                Ok(listener)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { listeners })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+
+    /// 实际绑定的地址
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn local_addrs(&self) -> Vec<std::net::SocketAddr> {
+        self.listeners.iter().filter_map(|listener| listener.local_addr().ok()).collect()
+    }
+
+    /// 套接字的文件描述符, 升级监控进程时交给新程序
+    #[cfg(unix)]
+    pub fn raw_fds(&self) -> Vec<i32> {
+        use std::os::unix::io::AsRawFd;
+        self.listeners.iter().map(|listener| listener.as_raw_fd()).collect()
+    }
+
+    /// 接管升级前由旧程序绑定的套接字
+    ///
+    /// # Safety
+    /// 描述符必须是仍然打开的TCP监听套接字, 且不归其他对象所有
+    #[cfg(unix)]
+    pub unsafe fn from_raw_fds(fds: &[i32]) -> Self {
+        use std::os::unix::io::FromRawFd;
+        let listeners = fds.iter().map(|fd| TcpListener::from_raw_fd(*fd)).collect();
+        Self { listeners }
+    }
+
+    /// 创建把套接字传给子进程的命令
+    ///
+    /// `LISTEN_PID` 必须是子进程自己的PID, 启动前无法知道, 所以先由sh设置为自身PID再exec目标程序,
+    /// exec后PID不变
+    pub fn command(&self, program: &str, args: &[String]) -> Command {
+        if self.is_empty() {
+            let mut cmd = Command::new(program);
+            cmd.args(args);
+            return cmd;
+        }
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("LISTEN_PID=$$; export LISTEN_PID; exec \"$0\" \"$@\"")
+            .arg(program)
+            .args(args)
+            .env("LISTEN_FDS", self.listeners.len().to_string())
+            .env_remove("LISTEN_FDNAMES");
+
+        #[cfg(unix)]
+        {
+            let fds = self.raw_fds();
+            // SAFETY: 闭包在fork后的子进程中执行, 只调用dup系列系统调用, 不分配内存
+            unsafe {
+                cmd.pre_exec(move || pass_fds(&fds));
+            }
+        }
+        cmd
+    }
+}
+
+/// 把套接字依次复制到3, 4, ...
+///
+/// 先全部复制到目标范围之上, 避免原描述符正好占用目标位置时被覆盖。
+/// dup2得到的描述符没有 `FD_CLOEXEC`, exec后仍然有效
+#[cfg(unix)]
+fn pass_fds(fds: &[i32]) -> std::io::Result<()> {
+    use nix::fcntl::{fcntl, FcntlArg};
+    use nix::unistd::{close, dup2};
+
+    let above = LISTEN_FDS_START + fds.len() as i32;
+    let mut temporary = [0i32; 64];
+    if fds.len() > temporary.len() {
+        return Err(std::io::Error::from_raw_os_error(nix::errno::Errno::EMFILE as i32));
+    }
+    for (i, fd) in fds.iter().enumerate() {
+        temporary[i] = fcntl(*fd, FcntlArg::F_DUPFD(above))?;
+    }
+    for (i, fd) in temporary[..fds.len()].iter().enumerate() {
+        dup2(*fd, LISTEN_FDS_START + i as i32)?;
+        close(*fd)?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+
+    #[tokio::test]
+    async fn test_child_receives_listening_sockets() -> Result<()> {
+        let configs = vec![
+            SocketConfig { host: "127.0.0.1".into(), port: 0 },
+            SocketConfig { host: "127.0.0.1".into(), port: 0 },
+        ];
+        let sockets = ListenSockets::bind(&configs)?;
+        let script = "echo $LISTEN_FDS $LISTEN_PID $$; readlink /proc/$$/fd/3 /proc/$$/fd/4";
+        let output = sockets.command("sh", &["-c".into(), script.into()])
+            .stdout(Stdio::piped())
+            .output()
+            .await?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let lines: Vec<&str> = stdout.lines().collect();
+
+        let fields: Vec<&str> = lines[0].split_whitespace().collect();
+        assert_eq!(fields[0], "2");
+        // exec之后PID不变, LISTEN_PID就是程序自己的PID
+        assert_eq!(fields[1], fields[2]);
+        assert!(lines[1].starts_with("socket:") && lines[2].starts_with("socket:"));
+
+        // 子进程退出后套接字仍在监听, 连接进入队列
+        for addr in sockets.local_addrs() {
+            std::net::TcpStream::connect(addr)?;
+        }
+
+        // 端口已被占用时报错
+        let taken = SocketConfig { host: "127.0.0.1".into(), port: sockets.local_addrs()[0].port() };
+        assert!(ListenSockets::bind(&[taken]).is_err());
+        Ok(())
+    }
+}
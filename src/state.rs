@@ -1,12 +1,22 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use anyhow::{Result, Context};
+use log::{info, warn};
 use serde::{Serialize, Deserialize};
-use std::fs;
-use crate::types::StateConfig;
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
 use crate::workspace::Workspace;
+use crate::config::ProcessConfig;
+use crate::process::identity::ProcessIdentity;
+
+/// 当前状态文件格式版本
+pub const STATE_VERSION: u32 = 2;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessState {
+    /// 状态文件格式版本
+    #[serde(default)]
+    pub version: u32,
     pub pid: Option<i32>,
     pub monitor_pid: Option<i32>,  // 添加monitor_pid字段
     pub program: String,
@@ -15,11 +25,30 @@ pub struct ProcessState {
     pub port: u16,
     pub health_check_url: Option<String>,
     pub restart_count: u32,  // 添加重启次数字段
+    /// 启动时记录的进程身份, 用于识别PID复用
+    #[serde(default)]
+    pub identity: Option<ProcessIdentity>,
+    /// 监控进程的身份
+    #[serde(default)]
+    pub monitor_identity: Option<ProcessIdentity>,
+    /// 记录的PID已被其他进程复用, 状态已失效
+    #[serde(default)]
+    pub stale: bool,
+    /// run命令启动的临时进程定义, 配置文件中没有该进程时使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transient: Option<ProcessConfig>,
+    /// 启动时是否开启自动重启, recover按此恢复; 旧的状态文件中没有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_restart: Option<bool>,
+    /// 启动时是否以守护进程方式运行
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daemon: Option<bool>,
 }
 
 impl Default for ProcessState {
     fn default() -> Self {
         Self {
+            version: STATE_VERSION,
             pid: None,
             monitor_pid: None,  // 初始化monitor_pid
             program: String::new(),
@@ -28,68 +57,380 @@ impl Default for ProcessState {
             port: 5000,
             health_check_url: None,
             restart_count: 0,  // 初始化为0
+            identity: None,
+            monitor_identity: None,
+            stale: false,
+            transient: None,
+            auto_restart: None,
+            daemon: None,
         }
     }
 }
 
-impl ProcessState {
-    pub fn new(program: String, args: Vec<String>, working_dir: PathBuf, port: u16) -> Self {
-        Self {
-            pid: None,
-            monitor_pid: None,  // 初始化monitor_pid
-            program,
-            args,
-            working_dir,
-            port,
-            health_check_url: None,
-            restart_count: 0,  // 初始化为0
-        }
+/// 状态文件的独占锁, 离开作用域时自动释放
+pub struct StateLock {
+    file: File,
+}
+
+impl StateLock {
+    /// 获取进程状态文件的独占锁(阻塞等待)
+    pub fn acquire(workspace: &Workspace, process_name: &str) -> Result<Self> {
+        workspace.ensure_process_dirs(process_name)?;
+        let lock_path = workspace.get_process_dir(process_name).join("state.lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("打开状态锁文件失败: {:?}", lock_path))?;
+        file.lock().context("获取状态文件锁失败")?;
+        Ok(Self { file })
     }
+}
 
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+impl ProcessState {
     pub fn save(&self, workspace: &Workspace, process_name: &str) -> Result<()> {
+        let _lock = StateLock::acquire(workspace, process_name)?;
+        self.save_locked(workspace, process_name)
+    }
+
+    /// 在已持有锁的情况下保存状态
+    fn save_locked(&self, workspace: &Workspace, process_name: &str) -> Result<()> {
         // 确保进程目录存在
         workspace.ensure_process_dirs(process_name)?;
-        
-        // 保存状态到文件
+
+        let mut state = self.clone();
+        state.version = STATE_VERSION;
+        let content = serde_json::to_string(&state).context("序列化状态失败")?;
+
+        // 保存状态到文件, 同时保留一份备份用于损坏时恢复
         let state_file = workspace.get_process_state_file(process_name);
-        let content = serde_json::to_string(self).context("序列化状态失败")?;
-        fs::write(&state_file, content).context("保存状态文件失败")?;
-        
+        write_atomic(&state_file, content.as_bytes()).context("保存状态文件失败")?;
+        write_atomic(&backup_path(&state_file), content.as_bytes()).context("保存状态备份失败")?;
+
         Ok(())
     }
 
     pub fn load(workspace: &Workspace, process_name: &str) -> Result<Self> {
         let state_file = workspace.get_process_state_file(process_name);
-        
         let content = fs::read_to_string(&state_file).context("读取状态文件失败")?;
-        let state = serde_json::from_str(&content).context("解析状态失败")?;
-        Ok(state)
+        match parse_state(&content) {
+            Ok(state) => Ok(state),
+            Err(StateParseError::Newer(version)) => {
+                anyhow::bail!("状态文件版本({})高于当前支持的版本({}), 请升级fuckrun", version, STATE_VERSION)
+            }
+            Err(StateParseError::Corrupt(e)) => {
+                warn!("状态文件已损坏 {:?}: {}", state_file, e);
+                let _lock = StateLock::acquire(workspace, process_name)?;
+                Self::load_locked(workspace, process_name)
+            }
+        }
     }
 
-    /// 更新为已停止状态
-    pub fn update_stopped_state(&mut self) {
-        self.pid = None;
-        self.monitor_pid = None;  // 清除monitor_pid
+    /// 在已持有锁的情况下加载状态, 文件损坏时自动修复
+    fn load_locked(workspace: &Workspace, process_name: &str) -> Result<Self> {
+        let state_file = workspace.get_process_state_file(process_name);
+        let content = fs::read_to_string(&state_file).context("读取状态文件失败")?;
+        match parse_state(&content) {
+            Ok(state) => Ok(state),
+            Err(StateParseError::Newer(version)) => {
+                anyhow::bail!("状态文件版本({})高于当前支持的版本({}), 请升级fuckrun", version, STATE_VERSION)
+            }
+            Err(StateParseError::Corrupt(e)) => Self::repair(workspace, process_name, &e),
+        }
     }
 
-    /// 清除状态文件（仅在需要完全清理进程数据时使用）
-    pub fn clear(&self, workspace: &Workspace, process_name: &str) -> Result<()> {
+    /// 修复损坏的状态文件: 保留损坏文件, 优先从备份恢复, 否则重置为默认状态
+    fn repair(workspace: &Workspace, process_name: &str, error: &str) -> Result<Self> {
         let state_file = workspace.get_process_state_file(process_name);
-        
-        if let Ok(()) = fs::remove_file(state_file) {
-            Ok(())
+        let corrupt_file = state_file.with_extension("json.corrupt");
+        if let Err(e) = fs::rename(&state_file, &corrupt_file) {
+            warn!("保留损坏的状态文件失败: {}", e);
         } else {
-            Ok(()) // 忽略文件不存在的错误
+            warn!("损坏的状态文件已移动到 {:?} ({})", corrupt_file, error);
         }
+
+        let state = match fs::read_to_string(backup_path(&state_file))
+            .ok()
+            .and_then(|content| parse_state(&content).ok())
+        {
+            Some(state) => {
+                info!("已从备份恢复进程 {} 的状态", process_name);
+                state
+            }
+            None => {
+                warn!("没有可用的状态备份, 进程 {} 的状态已重置", process_name);
+                Self::default()
+            }
+        };
+
+        state.save_locked(workspace, process_name)?;
+        Ok(state)
+    }
+
+    /// 在锁保护下加载、修改并保存状态, 状态文件不存在时从默认状态开始
+    pub fn update<F>(workspace: &Workspace, process_name: &str, f: F) -> Result<Self>
+    where
+        F: FnOnce(&mut Self),
+    {
+        let _lock = StateLock::acquire(workspace, process_name)?;
+        let mut state = if workspace.get_process_state_file(process_name).exists() {
+            Self::load_locked(workspace, process_name)?
+        } else {
+            Self::default()
+        };
+        f(&mut state);
+        state.save_locked(workspace, process_name)?;
+        Ok(state)
+    }
+
+    /// 记录新的进程PID及其身份
+    pub fn set_pid(&mut self, pid: i32) {
+        self.pid = Some(pid);
+        self.identity = ProcessIdentity::capture(pid);
+        self.stale = false;
+    }
+
+    /// 记录新的监控进程PID及其身份
+    pub fn set_monitor_pid(&mut self, pid: i32) {
+        self.monitor_pid = Some(pid);
+        self.monitor_identity = ProcessIdentity::capture(pid);
+    }
+
+    /// PID已被其他进程复用时清除对应记录, 并把状态标记为失效
+    pub fn mark_stale(&mut self, pid: i32) {
+        if self.pid == Some(pid) {
+            self.pid = None;
+            self.identity = None;
+        }
+        if self.monitor_pid == Some(pid) {
+            self.monitor_pid = None;
+            self.monitor_identity = None;
+        }
+        self.stale = true;
+    }
+
+    /// 更新为已停止状态
+    pub fn update_stopped_state(&mut self) {
+        self.pid = None;
+        self.monitor_pid = None;  // 清除monitor_pid
+        self.identity = None;
+        self.monitor_identity = None;
+        self.stale = false;
     }
 
     /// 增加重启次数
     pub fn increment_restart_count(&mut self) {
         self.restart_count += 1;
     }
+}
+
+enum StateParseError {
+    /// 文件由更新版本的fuckrun写入
+    Newer(u32),
+    /// 文件内容无法解析
+    Corrupt(String),
+}
 
-    /// 重置重启次数
-    pub fn reset_restart_count(&mut self) {
-        self.restart_count = 0;
+/// 解析状态文件内容, 并把旧版本格式迁移到当前版本
+fn parse_state(content: &str) -> std::result::Result<ProcessState, StateParseError> {
+    let value: Value = serde_json::from_str(content)
+        .map_err(|e| StateParseError::Corrupt(e.to_string()))?;
+    let version = value.get("version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as u32;
+    if version > STATE_VERSION {
+        return Err(StateParseError::Newer(version));
     }
-} 
\ No newline at end of file
+    let value = migrate(value, version).map_err(StateParseError::Corrupt)?;
+    serde_json::from_value(value).map_err(|e| StateParseError::Corrupt(e.to_string()))
+}
+
+/// 逐个版本迁移状态文件
+fn migrate(mut value: Value, from_version: u32) -> std::result::Result<Value, String> {
+    let object = value.as_object_mut().ok_or("状态文件不是JSON对象")?;
+    let mut version = from_version;
+
+    // v0 -> v1: 增加version字段, 早期版本缺少monitor_pid和restart_count
+    if version == 0 {
+        object.entry("monitor_pid").or_insert(Value::Null);
+        object.entry("restart_count").or_insert(Value::from(0));
+        version = 1;
+    }
+
+    // v1 -> v2: 增加进程身份信息, 旧状态没有身份记录, 无法校验
+    if version == 1 {
+        object.entry("identity").or_insert(Value::Null);
+        object.entry("monitor_identity").or_insert(Value::Null);
+        object.entry("stale").or_insert(Value::from(false));
+        version = 2;
+    }
+
+    object.insert("version".to_string(), Value::from(version));
+    Ok(value)
+}
+
+fn backup_path(state_file: &Path) -> PathBuf {
+    state_file.with_extension("json.bak")
+}
+
+/// 原子写入: 先写临时文件并落盘, 再重命名覆盖目标文件
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let dir = path.parent().context("文件没有父目录")?;
+    let file_name = path.file_name().context("无效的文件名")?.to_string_lossy();
+    let tmp_path = dir.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    {
+        let mut file = File::create(&tmp_path)
+            .with_context(|| format!("创建临时文件失败: {:?}", tmp_path))?;
+        file.write_all(content)?;
+        file.sync_all()?;
+    }
+
+    if let Err(e) = fs::rename(&tmp_path, path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e).with_context(|| format!("替换文件失败: {:?}", path));
+    }
+
+    // 确保重命名本身也已落盘
+    #[cfg(unix)]
+    {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_workspace() -> (tempfile::TempDir, Workspace) {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = Workspace::new(dir.path());
+        (dir, workspace)
+    }
+
+    #[test]
+    fn test_save_and_load() -> Result<()> {
+        let (_dir, workspace) = temp_workspace();
+        let state = ProcessState {
+            pid: Some(42),
+            program: "python".into(),
+            args: vec!["app.py".into()],
+            working_dir: PathBuf::from("/app"),
+            port: 8000,
+            ..Default::default()
+        };
+        state.save(&workspace, "web")?;
+
+        let loaded = ProcessState::load(&workspace, "web")?;
+        assert_eq!(loaded.version, STATE_VERSION);
+        assert_eq!(loaded.pid, Some(42));
+        assert_eq!(loaded.port, 8000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_v0_state() -> Result<()> {
+        let (_dir, workspace) = temp_workspace();
+        workspace.ensure_process_dirs("web")?;
+        fs::write(
+            workspace.get_process_state_file("web"),
+            r#"{"pid":7,"program":"python","args":[],"working_dir":".","port":5000,"health_check_url":null}"#,
+        )?;
+
+        let state = ProcessState::load(&workspace, "web")?;
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.pid, Some(7));
+        assert_eq!(state.restart_count, 0);
+        assert_eq!(state.monitor_pid, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mark_stale_clears_reused_pid() {
+        let mut state = ProcessState::default();
+        state.set_pid(std::process::id() as i32);
+        state.monitor_pid = Some(99);
+        state.mark_stale(std::process::id() as i32);
+        assert_eq!(state.pid, None);
+        assert_eq!(state.identity, None);
+        assert_eq!(state.monitor_pid, Some(99));
+        assert!(state.stale);
+
+        state.set_pid(1);
+        assert!(!state.stale);
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() -> Result<()> {
+        let (_dir, workspace) = temp_workspace();
+        workspace.ensure_process_dirs("web")?;
+        fs::write(workspace.get_process_state_file("web"), r#"{"version":99}"#)?;
+        assert!(ProcessState::load(&workspace, "web").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_state_is_repaired_from_backup() -> Result<()> {
+        let (_dir, workspace) = temp_workspace();
+        let state = ProcessState {
+            monitor_pid: Some(1234),
+            ..Default::default()
+        };
+        state.save(&workspace, "web")?;
+
+        // 模拟写入到一半的状态文件
+        let state_file = workspace.get_process_state_file("web");
+        fs::write(&state_file, r#"{"version":1,"pid":12"#)?;
+
+        let loaded = ProcessState::load(&workspace, "web")?;
+        assert_eq!(loaded.monitor_pid, Some(1234));
+        assert!(state_file.with_extension("json.corrupt").exists());
+        assert!(ProcessState::load(&workspace, "web").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_state_without_backup_is_reset() -> Result<()> {
+        let (_dir, workspace) = temp_workspace();
+        workspace.ensure_process_dirs("web")?;
+        fs::write(workspace.get_process_state_file("web"), "garbage")?;
+
+        let loaded = ProcessState::load(&workspace, "web")?;
+        assert_eq!(loaded.pid, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_updates_are_serialized() -> Result<()> {
+        let (_dir, workspace) = temp_workspace();
+        ProcessState::default().save(&workspace, "web")?;
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let workspace = workspace.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        ProcessState::update(&workspace, "web", |s| s.increment_restart_count()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(ProcessState::load(&workspace, "web")?.restart_count, 80);
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+// 端到端测试辅助: 临时工作区、配置文件、用sh实现的小型夹具程序和假的健康检查服务
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use crate::config::{Config, ProcessConfig};
+use crate::events::{EventKind, ProcessEvent};
+use crate::process::backend;
+use crate::process::ProcessManager;
+use crate::state::ProcessState;
+use crate::types::ProcessConfig as TypesProcessConfig;
+use crate::workspace::Workspace;
+
+/// 夹具程序, 都通过 `sh -c` 运行
+#[derive(Debug, Clone)]
+pub enum Fixture {
+    /// 持续运行
+    Sleep,
+    /// 输出若干行后正常退出
+    Print(Vec<String>),
+    /// 运行指定时间后以退出码1退出
+    CrashAfter(Duration),
+    /// 运行指定时间后正常退出
+    ExitAfter(Duration),
+    /// 忽略SIGTERM, 只能被强制终止
+    IgnoreTerm,
+    /// 启动n个子进程后等待, 父子进程都忽略SIGTERM, 用于验证强制终止会清理整个进程树
+    SpawnChildren(usize),
+}
+
+impl Fixture {
+    pub fn program(&self) -> &'static str {
+        "sh"
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        vec!["-c".to_string(), self.script()]
+    }
+
+    fn script(&self) -> String {
+        match self {
+            Fixture::Sleep => "exec sleep 60".to_string(),
+            Fixture::Print(lines) => {
+                let echoes: Vec<String> = lines.iter().map(|line| format!("echo '{}'", line)).collect();
+                echoes.join("; ")
+            }
+            Fixture::CrashAfter(after) => {
+                format!("echo starting; sleep {}; echo crashing >&2; exit 1", after.as_secs_f64())
+            }
+            Fixture::ExitAfter(after) => format!("echo starting; sleep {}; exit 0", after.as_secs_f64()),
+            Fixture::IgnoreTerm => "trap '' TERM; while :; do sleep 0.1; done".to_string(),
+            Fixture::SpawnChildren(n) => {
+                format!("trap '' TERM; for i in $(seq {}); do sleep 60 & done; echo ready; wait", n)
+            }
+        }
+    }
+
+    /// 生成配置文件中的进程配置
+    pub fn process_config(&self, name: &str) -> ProcessConfig {
+        ProcessConfig {
+            name: name.to_string(),
+            program: self.program().to_string(),
+            args: self.args(),
+            process: fast_process_config(),
+            ..Default::default()
+        }
+    }
+
+    /// 直接启动夹具, 输出通过管道读取, 用于交给 `monitor_and_restart`
+    pub fn spawn(&self, working_dir: &Path) -> Result<tokio::process::Child> {
+        Ok(tokio::process::Command::new(self.program())
+            .args(self.args())
+            .current_dir(working_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdin(Stdio::null())
+            .spawn()?)
+    }
+}
+
+/// 缩短各种等待时间的进程管理配置
+///
+/// 端口使用一个空闲端口, 避免停止进程时清理到机器上的其他服务
+pub fn fast_process_config() -> TypesProcessConfig {
+    TypesProcessConfig {
+        default_port: free_port(),
+        init_wait_secs: 1,
+        health_check_timeout_secs: 1,
+        health_check_retries: 3,
+        retry_interval_secs: 0,
+        graceful_shutdown_timeout_secs: 1,
+        exit_wait_ms: 200,
+        ..Default::default()
+    }
+}
+
+/// 获取一个当前空闲的本地端口
+pub fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .expect("无法分配空闲端口")
+}
+
+/// 轮询直到条件成立, 超时返回false
+pub async fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    while !condition() {
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    true
+}
+
+/// 临时工作区, 销毁时强制终止状态中记录的所有进程和守护进程fork出的进程, 避免测试遗留进程
+pub struct TestWorkspace {
+    pub workspace: Workspace,
+    dir: TempDir,
+}
+
+impl TestWorkspace {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::new(dir.path());
+        workspace.init().expect("初始化工作区失败");
+        Self { workspace, dir }
+    }
+
+    pub fn root(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// 写入config.yaml并重新读取, 返回与命令行加载方式一致的配置
+    pub fn write_config(&self, processes: Vec<ProcessConfig>) -> Result<Config> {
+        let config = Config {
+            processes: processes.into_iter().map(|p| (p.name.clone(), p)).collect(),
+            ..Default::default()
+        };
+        let path = self.root().join("config.yaml");
+        config.save_to_file(&path)?;
+        Config::from_file(&path)
+    }
+
+    /// 使用快速配置的进程管理器
+    pub fn manager(&self, name: &str) -> ProcessManager<'_> {
+        ProcessManager::with_config(&self.workspace, name.to_string(), fast_process_config())
+    }
+
+    /// 进程的工作目录
+    pub fn working_dir(&self, name: &str) -> PathBuf {
+        self.workspace.get_app_dir().join(name)
+    }
+
+    pub fn state(&self, name: &str) -> ProcessState {
+        ProcessState::load(&self.workspace, name).expect("加载进程状态失败")
+    }
+
+    /// 进程的事件类型, 按发生顺序
+    pub fn events(&self, name: &str) -> Vec<EventKind> {
+        ProcessEvent::list(&self.workspace, name, None)
+            .expect("读取事件失败")
+            .into_iter()
+            .map(|event| event.kind)
+            .collect()
+    }
+}
+
+impl Drop for TestWorkspace {
+    fn drop(&mut self) {
+        let backend = backend::native();
+        let Ok(entries) = std::fs::read_dir(self.workspace.get_processes_dir()) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Ok(state) = ProcessState::load(&self.workspace, &name) else {
+                continue;
+            };
+            let recorded = [(state.monitor_pid, state.monitor_identity), (state.pid, state.identity)];
+            for (pid, identity) in recorded {
+                let Some(pid) = pid else { continue };
+                // 只终止仍是原来那个的进程
+                if backend.exists(pid) {
+                    let _ = backend.kill_tree(pid, identity.as_ref());
+                }
+            }
+        }
+
+        // 守护进程两次fork后不再是测试进程的子进程, 状态中没有记录时(测试中途失败、达到重启上限)
+        // 只能按工作目录找到
+        #[cfg(target_os = "linux")]
+        for pid in processes_in(self.root()) {
+            let _ = nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), nix::sys::signal::Signal::SIGKILL);
+        }
+    }
+}
+
+/// 工作目录在指定目录下的进程
+#[cfg(target_os = "linux")]
+fn processes_in(root: &Path) -> Vec<i32> {
+    let Ok(root) = root.canonicalize() else {
+        return Vec::new();
+    };
+    let me = std::process::id() as i32;
+    std::fs::read_dir("/proc")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|pid| *pid != me)
+        .filter(|pid| std::fs::read_link(format!("/proc/{}/cwd", pid)).is_ok_and(|cwd| cwd.starts_with(&root)))
+        .collect()
+}
+
+/// 假的HTTP健康检查服务, 可以随时切换健康状态
+pub struct HealthServer {
+    addr: SocketAddr,
+    healthy: Arc<AtomicBool>,
+    requests: Arc<AtomicUsize>,
+    task: JoinHandle<()>,
+}
+
+impl HealthServer {
+    pub async fn start(healthy: bool) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("无法启动健康检查服务");
+        let addr = listener.local_addr().expect("无法获取监听地址");
+        let healthy = Arc::new(AtomicBool::new(healthy));
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let task = {
+            let healthy = healthy.clone();
+            let requests = requests.clone();
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    let status = if healthy.load(Ordering::SeqCst) {
+                        "200 OK"
+                    } else {
+                        "503 Service Unavailable"
+                    };
+                    tokio::spawn(async move {
+                        let mut buf = [0u8; 1024];
+                        let _ = stream.read(&mut buf).await;
+                        let response = format!(
+                            "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                            status
+                        );
+                        let _ = stream.write_all(response.as_bytes()).await;
+                        let _ = stream.shutdown().await;
+                    });
+                }
+            })
+        };
+
+        Self { addr, healthy, requests, task }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}/health", self.addr)
+    }
+
+    pub fn set_healthy(&self, healthy: bool) {
+        self.healthy.store(healthy, Ordering::SeqCst);
+    }
+
+    /// 收到的请求数
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for HealthServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
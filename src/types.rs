@@ -73,13 +73,21 @@ pub struct ProcessConfig {
     #[serde(default = "default_exit_wait_ms")]
     pub exit_wait_ms: u64,
 
-    /// 默认Python解释器路径
-    #[serde(default = "default_python_interpreter")]
-    pub default_python_interpreter: String,
+    /// 崩溃记录中保留的输出行数
+    #[serde(default = "default_crash_tail_lines")]
+    pub crash_tail_lines: usize,
 
-    /// 默认Python脚本路径
-    #[serde(default = "default_script_path")]
-    pub default_script_path: String,
+    /// 每个进程最多保留的崩溃记录数
+    #[serde(default = "default_max_crash_records")]
+    pub max_crash_records: usize,
+
+    /// 等待程序写入PID文件的时间(秒)
+    #[serde(default = "default_pid_file_timeout")]
+    pub pid_file_timeout_secs: u64,
+
+    /// 程序没有PID文件但会自己fork到后台时, 启动的程序正常退出后继续监控它留下的进程(仅Linux守护进程模式)
+    #[serde(default)]
+    pub follow_forks: bool,
 }
 
 /// 状态管理配置
@@ -130,8 +138,10 @@ impl Default for ProcessConfig {
             graceful_shutdown_timeout_secs: default_graceful_shutdown_timeout(),
             windows_process_flags: default_windows_process_flags(),
             exit_wait_ms: default_exit_wait_ms(),
-            default_python_interpreter: default_python_interpreter(),
-            default_script_path: default_script_path(),
+            crash_tail_lines: default_crash_tail_lines(),
+            max_crash_records: default_max_crash_records(),
+            pid_file_timeout_secs: default_pid_file_timeout(),
+            follow_forks: false,
         }
     }
 }
@@ -170,8 +180,9 @@ fn default_state_filename() -> String { String::from("state.json") }
 fn default_working_dir() -> PathBuf { PathBuf::from(".") }
 fn default_exit_wait_ms() -> u64 { 500 }
 fn default_windows_process_flags() -> u32 { 0x00000200 | 0x00000008 } // CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS
-fn default_python_interpreter() -> String { String::from("python") }
-fn default_script_path() -> String { String::from("examples/simple_web.py") }
+fn default_crash_tail_lines() -> usize { 200 }
+fn default_max_crash_records() -> usize { 20 }
+fn default_pid_file_timeout() -> u64 { 10 }
 fn default_config_paths() -> Vec<String> { vec!["config.yaml".to_string(), "config.json".to_string()] }
 
 // 辅助函数
@@ -196,6 +207,10 @@ impl ProcessConfig {
         Duration::from_millis(self.exit_wait_ms)
     }
 
+    pub fn pid_file_timeout(&self) -> Duration {
+        Duration::from_secs(self.pid_file_timeout_secs)
+    }
+
     /// 验证配置的合法性
     pub fn validate(&self) -> anyhow::Result<()> {
         if self.init_wait_secs == 0 {
@@ -249,21 +264,6 @@ impl ProcessPriority {
     pub fn value(&self) -> u8 {
         *self as u8
     }
-
-    /// 检查是否为系统级进程
-    pub fn is_system(&self) -> bool {
-        matches!(self, ProcessPriority::System)
-    }
-
-    /// 检查是否为应用级进程
-    pub fn is_application(&self) -> bool {
-        matches!(self, ProcessPriority::Application)
-    }
-
-    /// 检查是否为临时进程
-    pub fn is_temporary(&self) -> bool {
-        matches!(self, ProcessPriority::Temporary)
-    }
 }
 
 impl StateConfig {
@@ -289,9 +289,7 @@ mod tests {
     fn test_process_priority() {
         let priority = ProcessPriority::System;
         assert_eq!(priority.value(), 1);
-        assert!(priority.is_system());
-        assert!(!priority.is_application());
-        assert!(!priority.is_temporary());
+        assert!(ProcessPriority::Application.value() < ProcessPriority::Temporary.value());
     }
 
     #[test]
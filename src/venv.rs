@@ -0,0 +1,186 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use log::info;
+use serde::{Serialize, Deserialize};
+use tokio::process::Command;
+use crate::hash::StableHasher;
+use crate::workspace::Workspace;
+
+/// 进程的Python配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PythonConfig {
+    /// 虚拟环境, 设置后 `start` 和 `prepare` 会自动创建并安装依赖
+    #[serde(default)]
+    pub venv: Option<VenvConfig>,
+}
+
+/// 虚拟环境配置, 相对路径都基于进程的工作目录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VenvConfig {
+    /// 虚拟环境目录
+    #[serde(default = "default_venv_path")]
+    pub path: PathBuf,
+    /// 依赖文件, 不存在时跳过安装
+    #[serde(default = "default_requirements")]
+    pub requirements: PathBuf,
+    /// 创建虚拟环境使用的Python解释器
+    #[serde(default = "default_interpreter")]
+    pub interpreter: String,
+    /// 本地wheel目录, 传给pip的 `--find-links`
+    #[serde(default)]
+    pub find_links: Option<PathBuf>,
+    /// 不访问PyPI, 只从find_links安装(离线环境)
+    #[serde(default)]
+    pub no_index: bool,
+}
+
+impl Default for VenvConfig {
+    fn default() -> Self {
+        Self {
+            path: default_venv_path(),
+            requirements: default_requirements(),
+            interpreter: default_interpreter(),
+            find_links: None,
+            no_index: false,
+        }
+    }
+}
+
+fn default_venv_path() -> PathBuf { PathBuf::from(".venv") }
+fn default_requirements() -> PathBuf { PathBuf::from("requirements.txt") }
+fn default_interpreter() -> String {
+    if cfg!(windows) { String::from("python") } else { String::from("python3") }
+}
+
+/// 准备虚拟环境的结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PrepareOutcome {
+    pub venv: PathBuf,
+    /// 本次新建了虚拟环境
+    pub created: bool,
+    /// 本次安装了依赖
+    pub installed: bool,
+}
+
+/// 虚拟环境中的Python解释器
+pub fn venv_python(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python")
+    }
+}
+
+/// 依赖安装记录, 保存上次安装时依赖文件的哈希
+fn hash_file(workspace: &Workspace, name: &str) -> PathBuf {
+    workspace.get_process_dir(name).join("requirements.hash")
+}
+
+/// 计算依赖文件和安装来源的哈希, 任何一个变化都需要重新安装
+fn requirements_hash(content: &str, config: &VenvConfig) -> String {
+    let mut hasher = StableHasher::new();
+    hasher.write(content.as_bytes());
+    match &config.find_links {
+        Some(find_links) => hasher.write(b"find_links").write(find_links.to_string_lossy().as_bytes()),
+        None => hasher.write(b""),
+    };
+    hasher.write(&[config.no_index as u8]);
+    format!("{:016x}", hasher.finish())
+}
+
+/// 虚拟环境不存在时创建, 依赖文件变化后重新安装依赖
+///
+/// `force` 为true时忽略哈希, 总是重新安装
+pub async fn prepare(
+    workspace: &Workspace,
+    name: &str,
+    working_dir: &Path,
+    config: &VenvConfig,
+    force: bool,
+) -> Result<PrepareOutcome> {
+    let venv = working_dir.join(&config.path);
+    let python = venv_python(&venv);
+    let mut outcome = PrepareOutcome { venv: venv.clone(), ..Default::default() };
+
+    if !python.exists() {
+        info!("创建虚拟环境: {:?}", venv);
+        fs::create_dir_all(working_dir).context("创建工作目录失败")?;
+        run(Command::new(&config.interpreter).arg("-m").arg("venv").arg(&venv), "创建虚拟环境失败").await?;
+        outcome.created = true;
+    }
+
+    let requirements = working_dir.join(&config.requirements);
+    if !requirements.exists() {
+        info!("依赖文件不存在, 跳过安装: {:?}", requirements);
+        return Ok(outcome);
+    }
+    let content = fs::read_to_string(&requirements).context("读取依赖文件失败")?;
+    let hash = requirements_hash(&content, config);
+    let record = hash_file(workspace, name);
+    let installed_hash = fs::read_to_string(&record).unwrap_or_default();
+    if !force && !outcome.created && installed_hash.trim() == hash {
+        info!("依赖文件未变化, 跳过安装");
+        return Ok(outcome);
+    }
+
+    info!("安装依赖: {:?}", requirements);
+    let mut pip = Command::new(&python);
+    pip.args(["-m", "pip", "install", "--disable-pip-version-check", "-r"])
+        .arg(&requirements)
+        .current_dir(working_dir);
+    if config.no_index {
+        pip.arg("--no-index");
+    }
+    if let Some(links) = &config.find_links {
+        pip.arg("--find-links").arg(working_dir.join(links));
+    }
+    run(&mut pip, "安装依赖失败").await?;
+
+    workspace.ensure_process_dirs(name)?;
+    fs::write(&record, &hash).context("保存依赖哈希失败")?;
+    outcome.installed = true;
+    Ok(outcome)
+}
+
+/// 执行命令, 失败时把输出带到错误信息里
+async fn run(command: &mut Command, context: &str) -> Result<()> {
+    let output = command.output().await.context(context.to_string())?;
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("{}({}):\n{}\n{}", context, output.status, stdout.trim(), stderr.trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_prepare_creates_venv_and_installs_on_change() -> Result<()> {
+        let config = VenvConfig { find_links: Some("wheels".into()), no_index: true, ..Default::default() };
+        if which::which(&config.interpreter).is_err() {
+            return Ok(());
+        }
+        let dir = tempfile::tempdir()?;
+        let workspace = Workspace::new(dir.path());
+        let working_dir = dir.path().join("app");
+        fs::create_dir_all(working_dir.join("wheels"))?;
+        fs::write(working_dir.join("requirements.txt"), "# 没有依赖\n")?;
+
+        let first = prepare(&workspace, "web", &working_dir, &config, false).await?;
+        assert!(first.created && first.installed);
+        assert!(venv_python(&first.venv).exists());
+
+        // 依赖文件没变时不重新安装
+        let second = prepare(&workspace, "web", &working_dir, &config, false).await?;
+        assert!(!second.created && !second.installed);
+
+        fs::write(working_dir.join("requirements.txt"), "# 仍然没有依赖\n")?;
+        assert!(prepare(&workspace, "web", &working_dir, &config, false).await?.installed);
+        assert!(prepare(&workspace, "web", &working_dir, &config, true).await?.installed);
+        Ok(())
+    }
+}
@@ -14,22 +14,56 @@ pub struct Workspace {
     fuckrun_dir: PathBuf,
 }
 
+/// 指定工作区根目录的环境变量
+pub const HOME_ENV: &str = "FUCKRUN_HOME";
+
+/// 标记工作区根目录的配置文件名
+pub const MARKER_FILE: &str = "fuckrun.yaml";
+
+/// 工作区根目录的来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkspaceSource {
+    /// 命令行 --workspace 参数
+    Flag,
+    /// FUCKRUN_HOME 环境变量
+    Env,
+    /// 从当前目录向上找到的 .fuckrun/ 目录
+    FuckrunDir,
+    /// 从当前目录向上找到的 fuckrun.yaml
+    MarkerFile,
+    /// 用户默认目录
+    UserDefault,
+}
+
+impl WorkspaceSource {
+    /// 来源名称, 用于JSON输出
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WorkspaceSource::Flag => "flag",
+            WorkspaceSource::Env => "env",
+            WorkspaceSource::FuckrunDir => "fuckrun_dir",
+            WorkspaceSource::MarkerFile => "marker_file",
+            WorkspaceSource::UserDefault => "user_default",
+        }
+    }
+
+    /// 选择该目录的原因
+    pub fn reason(&self) -> String {
+        match self {
+            WorkspaceSource::Flag => "由命令行参数 --workspace 指定".to_string(),
+            WorkspaceSource::Env => format!("由环境变量 {} 指定", HOME_ENV),
+            WorkspaceSource::FuckrunDir => "从当前目录向上找到 .fuckrun/ 目录".to_string(),
+            WorkspaceSource::MarkerFile => format!("从当前目录向上找到 {}", MARKER_FILE),
+            WorkspaceSource::UserDefault => "未找到工作区, 使用用户默认目录".to_string(),
+        }
+    }
+}
+
 impl Workspace {
     /// 创建新的工作区
     pub fn new<P: AsRef<Path>>(root: P) -> Self {
         // 将传入的路径转换为绝对路径
-        let root = if root.as_ref() == Path::new(".") {
-            // 如果是".",返回项目根目录
-            std::env::current_exe()
-                .expect("无法获取当前可执行文件路径")
-                .parent()  // target/debug
-                .expect("无法获取父目录")
-                .parent()  // target
-                .expect("无法获取父目录")
-                .parent()  // 项目根目录
-                .expect("无法获取父目录")
-                .to_path_buf()
-        } else if root.as_ref().is_absolute() {
+        let root = if root.as_ref().is_absolute() {
             root.as_ref().to_path_buf()
         } else {
             std::env::current_dir()
@@ -50,6 +84,18 @@ impl Workspace {
         }
     }
 
+    /// 查找工作区根目录
+    ///
+    /// 优先级: 命令行参数 > FUCKRUN_HOME > 从当前目录向上查找 .fuckrun/ 或 fuckrun.yaml > 用户默认目录
+    pub fn discover(explicit: Option<&Path>) -> Result<(Self, WorkspaceSource)> {
+        let cwd = std::env::current_dir().context("无法获取当前目录")?;
+        let home = std::env::var_os(HOME_ENV).map(PathBuf::from);
+        let (root, source) = discover_root(explicit, home, &cwd, user_default_root())
+            .context("无法确定工作区目录, 请使用 --workspace 或设置 FUCKRUN_HOME")?;
+        info!("工作区根目录: {:?} ({})", root, source.reason());
+        Ok((Self::new(root), source))
+    }
+
     /// 设置应用程序目录
     pub fn set_app_dir<P: AsRef<Path>>(&mut self, app_dir: P) {
         let app_dir = if app_dir.as_ref().is_absolute() {
@@ -67,6 +113,22 @@ impl Workspace {
         self.app_dir.join(process_name).join("app")
     }
 
+    /// 获取进程的部署目录, 发布的版本保存在其中的 releases/ 下
+    pub fn get_process_deploy_dir(&self, process_name: &str) -> PathBuf {
+        self.app_dir.join(process_name)
+    }
+
+    /// 获取进程的工作目录: 有发布版本时为指向当前版本的 current 链接, 否则为部署目录
+    pub fn get_process_working_dir(&self, process_name: &str) -> PathBuf {
+        let deploy_dir = self.get_process_deploy_dir(process_name);
+        let current = deploy_dir.join("current");
+        if current.exists() {
+            current
+        } else {
+            deploy_dir
+        }
+    }
+
     /// 初始化工作区目录结构
     pub fn init(&self) -> Result<()> {
         info!("初始化工作区目录结构");
@@ -131,6 +193,16 @@ impl Workspace {
         self.get_process_dir(process_name).join("logs")
     }
 
+    /// 获取进程崩溃记录目录
+    pub fn get_process_crash_dir(&self, process_name: &str) -> PathBuf {
+        self.get_process_dir(process_name).join("crashes")
+    }
+
+    /// 获取进程生命周期事件日志文件
+    pub fn get_process_events_file(&self, process_name: &str) -> PathBuf {
+        self.get_process_dir(process_name).join("events.jsonl")
+    }
+
     /// 获取应用目录
     pub fn get_app_dir(&self) -> &Path {
         &self.app_dir
@@ -169,4 +241,87 @@ impl Workspace {
 
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+
+/// 按优先级选择工作区根目录, 相对路径基于cwd解析
+fn discover_root(
+    explicit: Option<&Path>,
+    home: Option<PathBuf>,
+    cwd: &Path,
+    user_default: Option<PathBuf>,
+) -> Option<(PathBuf, WorkspaceSource)> {
+    if let Some(root) = explicit {
+        return Some((cwd.join(root), WorkspaceSource::Flag));
+    }
+    if let Some(root) = home.filter(|home| !home.as_os_str().is_empty()) {
+        return Some((cwd.join(root), WorkspaceSource::Env));
+    }
+    for dir in cwd.ancestors() {
+        if dir.join(".fuckrun").is_dir() {
+            return Some((dir.to_path_buf(), WorkspaceSource::FuckrunDir));
+        }
+        if dir.join(MARKER_FILE).is_file() {
+            return Some((dir.to_path_buf(), WorkspaceSource::MarkerFile));
+        }
+    }
+    user_default.map(|root| (root, WorkspaceSource::UserDefault))
+}
+
+/// 用户默认工作区: $XDG_STATE_HOME/fuckrun, 未设置时为 ~/.local/state/fuckrun
+#[cfg(unix)]
+fn user_default_root() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))?;
+    Some(state_home.join("fuckrun"))
+}
+
+/// 用户默认工作区: %LOCALAPPDATA%\fuckrun
+#[cfg(windows)]
+fn user_default_root() -> Option<PathBuf> {
+    std::env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("fuckrun"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_root_priority() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let project = dir.path().join("project");
+        let nested = project.join("src").join("bin");
+        fs::create_dir_all(&nested)?;
+        let user_default = Some(dir.path().join("state"));
+
+        // 没有任何标记时使用用户默认目录
+        assert_eq!(
+            discover_root(None, None, &nested, user_default.clone()),
+            Some((dir.path().join("state"), WorkspaceSource::UserDefault))
+        );
+
+        fs::write(project.join(MARKER_FILE), "processes: {}\n")?;
+        assert_eq!(
+            discover_root(None, None, &nested, user_default.clone()),
+            Some((project.clone(), WorkspaceSource::MarkerFile))
+        );
+
+        // 更近的 .fuckrun/ 目录优先
+        fs::create_dir_all(nested.join(".fuckrun"))?;
+        assert_eq!(
+            discover_root(None, None, &nested, user_default.clone()),
+            Some((nested.clone(), WorkspaceSource::FuckrunDir))
+        );
+
+        assert_eq!(
+            discover_root(None, Some(PathBuf::from("/srv/fuckrun")), &nested, user_default.clone()),
+            Some((PathBuf::from("/srv/fuckrun"), WorkspaceSource::Env))
+        );
+        assert_eq!(
+            discover_root(Some(Path::new("ws")), Some(PathBuf::from("/srv/fuckrun")), &nested, None),
+            Some((nested.join("ws"), WorkspaceSource::Flag))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+//! 通过构建出的程序验证守护进程模式下的在线升级: SIGUSR2 -> 写入交接文件 -> execv -> resume
+#![cfg(target_os = "linux")]
+
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use serde_json::Value;
+
+const EXE: &str = env!("CARGO_BIN_EXE_fuckrun");
+
+/// 临时工作区, 结束时停止进程并清理残留的监控进程
+struct Workspace {
+    dir: tempfile::TempDir,
+}
+
+impl Workspace {
+    fn new(config: &str) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("fuckrun.yaml"), config).unwrap();
+        Self { dir }
+    }
+
+    fn root(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// 运行fuckrun命令, 守护进程会继承输出, 所以输出写入文件而不是管道
+    fn fuckrun(&self, args: &[&str]) -> std::process::ExitStatus {
+        let stderr = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.root().join("stderr.log"))
+            .unwrap();
+        Command::new(EXE)
+            .arg("--workspace")
+            .arg(self.root())
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(stderr)
+            .status()
+            .unwrap()
+    }
+
+    fn process_dir(&self, name: &str) -> PathBuf {
+        self.root().join(".fuckrun").join("processes").join(name)
+    }
+
+    fn state(&self, name: &str) -> Value {
+        std::fs::read_to_string(self.process_dir(name).join("state.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or(Value::Null)
+    }
+
+    fn pids(&self, name: &str) -> (Option<i32>, Option<i32>) {
+        let state = self.state(name);
+        let pid = |key: &str| state[key].as_i64().map(|pid| pid as i32);
+        (pid("pid"), pid("monitor_pid"))
+    }
+
+    fn events(&self, name: &str) -> Vec<Value> {
+        std::fs::read_to_string(self.process_dir(name).join("events.jsonl"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let (pid, monitor_pid) = self.pids("web");
+        let _ = self.fuckrun(&["stop", "--name", "web"]);
+        for pid in [monitor_pid, pid].into_iter().flatten() {
+            let _ = kill(Pid::from_raw(pid), Signal::SIGKILL);
+        }
+    }
+}
+
+fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    while !condition() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    true
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn alive(pid: i32) -> bool {
+    kill(Pid::from_raw(pid), None).is_ok()
+}
+
+/// 文件描述符指向的套接字, 如 `socket:[12345]`
+fn socket_of(pid: i32, fd: i32) -> Option<String> {
+    std::fs::read_link(format!("/proc/{}/fd/{}", pid, fd))
+        .ok()
+        .map(|target| target.to_string_lossy().to_string())
+        .filter(|target| target.starts_with("socket:"))
+}
+
+/// 进程打开的所有套接字
+fn sockets_of(pid: i32) -> Vec<String> {
+    std::fs::read_dir(format!("/proc/{}/fd", pid))
+        .map(|entries| {
+            entries.flatten()
+                .filter_map(|entry| std::fs::read_link(entry.path()).ok())
+                .map(|target| target.to_string_lossy().to_string())
+                .filter(|target| target.starts_with("socket:"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn env_of(pid: i32, key: &str) -> Option<String> {
+    let environ = std::fs::read(format!("/proc/{}/environ", pid)).ok()?;
+    environ.split(|byte| *byte == 0)
+        .filter_map(|entry| std::str::from_utf8(entry).ok())
+        .find_map(|entry| entry.strip_prefix(&format!("{}=", key)).map(String::from))
+}
+
+/// 进程是否已注册SIGUSR2处理函数
+fn handles_sigusr2(pid: i32) -> bool {
+    std::fs::read_to_string(format!("/proc/{}/status", pid))
+        .ok()
+        .and_then(|status| {
+            status.lines()
+                .find_map(|line| line.strip_prefix("SigCgt:"))
+                .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+        })
+        .is_some_and(|mask| mask & (1 << (Signal::SIGUSR2 as u64 - 1)) != 0)
+}
+
+#[test]
+fn test_sigusr2_upgrades_daemon_without_restarting_child() {
+    let port = free_port();
+    let workspace = Workspace::new(&format!(
+        r#"processes:
+  web:
+    name: web
+    program: sleep
+    args: ["600"]
+    auto_restart: true
+    sockets:
+      - host: 127.0.0.1
+        port: {}
+    process:
+      default_port: {}
+      init_wait_secs: 1
+      graceful_shutdown_timeout_secs: 1
+      exit_wait_ms: 200
+"#,
+        port,
+        free_port(),
+    ));
+
+    std::fs::create_dir_all(workspace.root().join("deployments").join("web")).unwrap();
+    let status = workspace.fuckrun(&["start", "--name", "web", "--daemon", "--auto-restart"]);
+    assert!(status.success(), "启动失败: {}", std::fs::read_to_string(workspace.root().join("stderr.log")).unwrap_or_default());
+
+    assert!(wait_until(Duration::from_secs(10), || {
+        matches!(workspace.pids("web"), (Some(pid), Some(monitor)) if handles_sigusr2(monitor) && socket_of(pid, 3).is_some())
+    }));
+    let (Some(pid), Some(monitor_pid)) = workspace.pids("web") else { unreachable!() };
+    let socket = socket_of(pid, 3).unwrap();
+    assert_eq!(env_of(pid, "LISTEN_FDS").as_deref(), Some("1"));
+    assert!(sockets_of(monitor_pid).contains(&socket));
+
+    kill(Pid::from_raw(monitor_pid), Signal::SIGUSR2).unwrap();
+
+    // exec后新程序读取交接文件, 重新记录监控进程并继续监控
+    assert!(wait_until(Duration::from_secs(10), || {
+        workspace.events("web").iter().any(|event| {
+            event["event"] == "reexecuted" && event["monitor_pid"] == monitor_pid
+        })
+    }));
+    assert!(!workspace.process_dir("web").join("handoff.json").exists());
+    assert!(alive(monitor_pid));
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", monitor_pid)).unwrap();
+    assert!(cmdline.split(|byte| *byte == 0).any(|arg| arg == b"resume"));
+    assert!(wait_until(Duration::from_secs(5), || handles_sigusr2(monitor_pid)));
+
+    // 被监控的进程没有重启, 仍持有同一个监听套接字
+    assert_eq!(workspace.pids("web"), (Some(pid), Some(monitor_pid)));
+    assert!(alive(pid));
+    assert_eq!(socket_of(pid, 3).as_deref(), Some(socket.as_str()));
+    assert!(sockets_of(monitor_pid).contains(&socket));
+
+    // 新程序仍在监控: 进程被杀后用同一个套接字重启
+    kill(Pid::from_raw(pid), Signal::SIGKILL).unwrap();
+    assert!(wait_until(Duration::from_secs(15), || {
+        matches!(workspace.pids("web"), (Some(new_pid), _) if new_pid != pid && socket_of(new_pid, 3).is_some())
+    }));
+    let (Some(new_pid), Some(new_monitor_pid)) = workspace.pids("web") else { unreachable!() };
+    assert_eq!(new_monitor_pid, monitor_pid);
+    assert_eq!(socket_of(new_pid, 3).as_deref(), Some(socket.as_str()));
+    assert!(workspace.events("web").iter().any(|event| {
+        event["event"] == "restarted" && event["pid"] == new_pid
+    }));
+}